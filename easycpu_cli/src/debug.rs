@@ -0,0 +1,344 @@
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+};
+
+use easycpu_lib::{
+    asm::disasm::disassemle_instruction,
+    cpu::{Instruction, Register},
+    exec::{ExecCpu, RunLimits, Snapshot, StopReason},
+};
+
+use crate::{
+    source::Source,
+    symbols::{parse_value, Symbols},
+};
+
+const REGISTERS: [Register; 7] = [
+    Register::PC,
+    Register::R2,
+    Register::R3,
+    Register::R4,
+    Register::R5,
+    Register::LP,
+    Register::SP,
+];
+
+const HELP: &str = "Commands:
+  step|s [n]              execute n instructions
+  next|n                  execute one instruction, stepping over $CALL
+  continue|c              run until breakpoint, watchpoint or halt
+  break|b [addr]          set breakpoint, list breakpoints without argument
+  delete|d <addr>         remove breakpoint or watchpoint
+  watch|w <addr> [r|w|rw] stop when memory is read or written
+  regs|r                  print registers
+  set <reg> <val>         change register
+  x <addr> [count]        dump memory
+  poke <addr> <val>...    write memory
+  disas|l [addr] [count]  disassemble, around PC by default
+  stack [count]           show top of the stack
+  input <text>            send text to the serial port
+  save <file>             write the machine state to a file
+  load <file>             continue from a saved machine state
+  quit|q                  exit debugger
+Addresses accept numbers, labels and label+offset";
+
+pub struct Debugger {
+    cpu: ExecCpu,
+    symbols: Symbols,
+    source: Option<Source>,
+    limits: RunLimits,
+}
+
+fn parse_register(s: &str) -> Result<Register, String> {
+    REGISTERS
+        .iter()
+        .find(|r| r.to_string().eq_ignore_ascii_case(s))
+        .copied()
+        .ok_or_else(|| format!("Unknown register {}", s))
+}
+
+impl Debugger {
+    pub fn new(cpu: ExecCpu, symbols: Symbols, source: Option<Source>) -> Self {
+        Debugger {
+            cpu,
+            symbols,
+            source,
+            limits: RunLimits::new(),
+        }
+    }
+
+    fn describe(&self, addr: u16) -> String {
+        match self.symbols.describe(addr) {
+            Some(name) => format!("{:#06x} <{}>", addr, name),
+            None => format!("{:#06x}", addr),
+        }
+    }
+
+    fn flush_serial(&mut self) {
+        let output = self
+            .cpu
+            .serial_mut()
+            .map(|s| s.take_output())
+            .unwrap_or_default();
+
+        if !output.is_empty() {
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(&output);
+            let _ = stdout.flush();
+            println!();
+        }
+    }
+
+    fn report_stop(&self, reason: StopReason) {
+        match reason {
+            StopReason::Halted => println!("Halted"),
+            StopReason::StepLimit => (),
+            StopReason::Breakpoint(addr) => println!("Breakpoint at {}", self.describe(addr)),
+            StopReason::ReadWatch { pc, addr, val } => println!(
+                "Read {:#06x} from {} at {}",
+                val,
+                self.describe(addr),
+                self.describe(pc)
+            ),
+            StopReason::WriteWatch { pc, addr, val } => println!(
+                "Write {:#06x} to {} at {}",
+                val,
+                self.describe(addr),
+                self.describe(pc)
+            ),
+            StopReason::Condition(cond) => println!("Condition {:?} met", cond),
+        }
+    }
+
+    fn run(&mut self, limits: &RunLimits) -> StopReason {
+        let reason = self.cpu.run(limits);
+        self.flush_serial();
+        reason
+    }
+
+    fn print_current(&mut self) {
+        let pc = self.cpu.peek_reg(Register::PC);
+        if let Some((line, text)) = self.source.as_ref().and_then(|s| s.line(pc)) {
+            println!("{:>5} | {}", line, text);
+        }
+        self.print_disas(pc, 1);
+    }
+
+    fn print_disas(&mut self, from: u16, count: u16) {
+        let pc = self.cpu.peek_reg(Register::PC);
+        for addr in (0..count).map(|i| from.wrapping_add(i)) {
+            if let Some(name) = self.symbols.describe(addr).filter(|n| !n.contains('+')) {
+                println!("{}:", name);
+            }
+
+            let ins = Instruction::decode(self.cpu.bus_mut().read(addr));
+            println!(
+                "{} {:#06x}  {}",
+                if addr == pc { "=>" } else { "  " },
+                addr,
+                disassemle_instruction(ins)
+            );
+        }
+    }
+
+    fn print_regs(&self) {
+        for reg in REGISTERS {
+            let val = self.cpu.peek_reg(reg);
+            match reg {
+                Register::PC => println!("{}  {}", reg, self.describe(val)),
+                _ => println!("{}  {:#06x} ({})", reg, val, val as i16),
+            }
+        }
+    }
+
+    fn dump(&mut self, from: u16, count: u16) {
+        // Rows are counted wider than addresses, the last one may end at 0x10000
+        let count = count as u32;
+        for row in (0..count).step_by(8) {
+            let addr = from.wrapping_add(row as u16);
+            let words: Vec<String> = (row..count.min(row + 8))
+                .map(|i| format!("{:04x}", self.cpu.bus_mut().read(from.wrapping_add(i as u16))))
+                .collect();
+            println!("{:#06x}: {}", addr, words.join(" "));
+        }
+    }
+
+    fn print_stack(&mut self, count: u16) {
+        let sp = self.cpu.peek_reg(Register::SP);
+        let lp = self.cpu.peek_reg(Register::LP);
+
+        for i in 1..=count {
+            let addr = sp.wrapping_sub(i);
+            let val = self.cpu.bus_mut().read(addr);
+            let mark = if addr == lp { " <- LP" } else { "" };
+            println!("SP-{:<3} {:#06x}: {:#06x}{}", i, addr, val, mark);
+        }
+    }
+
+    fn command(&mut self, line: &str) -> Result<bool, String> {
+        let mut args = line.split_whitespace();
+        let Some(cmd) = args.next() else {
+            return Ok(true);
+        };
+        let args: Vec<&str> = args.collect();
+        let arg = |i: usize| -> Result<u16, String> {
+            args.get(i)
+                .ok_or_else(|| String::from("Not enough arguments"))
+                .and_then(|a| self.symbols.resolve(a))
+        };
+
+        match cmd {
+            "step" | "s" => {
+                let count = args
+                    .first()
+                    .map(|a| parse_value(a))
+                    .transpose()?
+                    .unwrap_or(1);
+                let reason = self.run(&RunLimits::new().steps(count as usize));
+                self.report_stop(reason);
+                self.print_current();
+            }
+            "next" | "n" => {
+                let reason = self.cpu.next(&self.limits);
+                self.flush_serial();
+                self.report_stop(reason);
+                self.print_current();
+            }
+            "continue" | "c" => {
+                let limits = self.limits.clone();
+                let reason = self.run(&limits);
+                self.report_stop(reason);
+                self.print_current();
+            }
+            "break" | "b" if args.is_empty() => {
+                let mut breakpoints: Vec<u16> = self.limits.breakpoints.iter().copied().collect();
+                breakpoints.sort();
+                for addr in breakpoints {
+                    println!("break {}", self.describe(addr));
+                }
+                for addr in self.limits.read_watch.iter() {
+                    println!("watch r {}", self.describe(*addr));
+                }
+                for addr in self.limits.write_watch.iter() {
+                    println!("watch w {}", self.describe(*addr));
+                }
+            }
+            "break" | "b" => {
+                let addr = arg(0)?;
+                self.limits.breakpoints.insert(addr);
+                println!("Breakpoint at {}", self.describe(addr));
+            }
+            "delete" | "d" => {
+                let addr = arg(0)?;
+                self.limits.breakpoints.remove(&addr);
+                self.limits.read_watch.remove(&addr);
+                self.limits.write_watch.remove(&addr);
+            }
+            "watch" | "w" => {
+                let addr = arg(0)?;
+                let mode = args.get(1).copied().unwrap_or("w");
+                if mode.contains('r') {
+                    self.limits.read_watch.insert(addr);
+                }
+                if mode.contains('w') {
+                    self.limits.write_watch.insert(addr);
+                }
+            }
+            "regs" | "r" => self.print_regs(),
+            "set" => {
+                let reg = parse_register(args.first().ok_or("Not enough arguments")?)?;
+                self.cpu.set_reg(reg, arg(1)?);
+            }
+            "x" => {
+                let count = args
+                    .get(1)
+                    .map(|a| parse_value(a))
+                    .transpose()?
+                    .unwrap_or(16);
+                self.dump(arg(0)?, count);
+            }
+            "poke" => {
+                let addr = arg(0)?;
+                for i in 1..args.len() {
+                    self.cpu.set_mem(addr.wrapping_add(i as u16 - 1), arg(i)?);
+                }
+            }
+            "disas" | "l" => {
+                let pc = self.cpu.peek_reg(Register::PC);
+                let from = if args.is_empty() {
+                    pc.wrapping_sub(4)
+                } else {
+                    arg(0)?
+                };
+                let count = args
+                    .get(1)
+                    .map(|a| parse_value(a))
+                    .transpose()?
+                    .unwrap_or(10);
+                self.print_disas(from, count);
+            }
+            "stack" => {
+                let count = args
+                    .first()
+                    .map(|a| parse_value(a))
+                    .transpose()?
+                    .unwrap_or(8);
+                self.print_stack(count);
+            }
+            "input" => {
+                let text = line.trim_start()[cmd.len()..].trim_start();
+                if let Some(serial) = self.cpu.serial_mut() {
+                    serial.push_input(text.as_bytes());
+                    serial.push_input(b"\n");
+                }
+            }
+            "save" => {
+                let path = args.first().ok_or("Not enough arguments")?;
+                fs::write(path, self.cpu.snapshot().write())
+                    .map_err(|e| format!("Failed to write file {:?}: {}", path, e))?;
+            }
+            "load" => {
+                let path = args.first().ok_or("Not enough arguments")?;
+                let data =
+                    fs::read(path).map_err(|e| format!("Failed to read file {:?}: {}", path, e))?;
+                let snapshot = Snapshot::read(&data).map_err(|e| e.to_string())?;
+                self.cpu.restore(&snapshot).map_err(|e| e.to_string())?;
+                self.print_current();
+            }
+            "help" | "h" => println!("{}", HELP),
+            "quit" | "q" => return Ok(false),
+            _ => return Err(format!("Unknown command {}, try help", cmd)),
+        }
+
+        Ok(true)
+    }
+
+    pub fn repl(&mut self) {
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        let mut prev = String::new();
+
+        self.print_current();
+        loop {
+            print!("(ecdb) ");
+            let _ = io::stdout().flush();
+
+            let Some(Ok(mut line)) = lines.next() else {
+                break;
+            };
+
+            // Empty line repeats previous command
+            if line.trim().is_empty() {
+                line = prev.clone();
+            }
+
+            match self.command(&line) {
+                Ok(true) => (),
+                Ok(false) => break,
+                Err(e) => eprintln!("{}", e),
+            }
+            prev = line;
+        }
+    }
+}
@@ -1,16 +1,56 @@
-use easycpu_lib::{exec::{ExecCpu, ExecEvent}, cpu::Register};
+use std::io::{self, Write};
+
+use easycpu_lib::{
+    cpu::Register,
+    exec::{ExecCpu, ExecEvent, ExecStats, RunLimits, Snapshot, StopReason, Timing},
+};
+
+use crate::source::Source;
 
 pub struct DebugCpu {
     cpu: ExecCpu,
+    source: Option<Source>,
 }
 
 impl DebugCpu {
-    pub fn new(init_ram: Vec<u16>)  -> Self {
+    pub fn new(init_ram: Vec<u16>, entry: u16, source: Option<Source>)  -> Self {
+        let mut cpu = ExecCpu::new(init_ram);
+        cpu.set_reg(Register::PC, entry);
         Self {
-            cpu: ExecCpu::new(init_ram)
+            cpu,
+            source,
         } 
     }
 
+    pub fn feed_serial(&mut self, data: &[u8]) {
+        if let Some(serial) = self.cpu.serial_mut() {
+            serial.push_input(data);
+        }
+    }
+
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.cpu.set_timing(timing);
+    }
+
+    pub fn stats(&self) -> &ExecStats {
+        self.cpu.get_stats()
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        self.cpu.snapshot()
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), String> {
+        self.cpu.restore(snapshot).map_err(|e| e.to_string())
+    }
+
+    fn take_serial_output(&mut self) -> Vec<u8> {
+        self.cpu
+            .serial_mut()
+            .map(|serial| serial.take_output())
+            .unwrap_or_default()
+    }
+
     fn format_event(event: ExecEvent) -> Option<String> {
         match event {
             ExecEvent::NONE => None,
@@ -18,17 +58,50 @@ impl DebugCpu {
             ExecEvent::REGGET(_, _) => None,
             ExecEvent::REGSET(_, _) => None,
             ExecEvent::MEMGET(addr, val) => Some(format!("MEMGET({:#06x}) => {:#06x}", addr, val)),
-            ExecEvent::MEMSET(addr, val) => Some(format!("MEMGET({:#06x}) => {:#06x}", addr, val)),
+            ExecEvent::MEMSET(addr, val) => Some(format!("MEMSET({:#06x}) <= {:#06x}", addr, val)),
         }
     }
     
-    pub fn run(&mut self) {
+    fn run_quiet(&mut self, mut steps: usize) {
+        let mut stdout = io::stdout();
+        while self.cpu.is_running() && steps > 0 {
+            let chunk = steps.min(0x1000);
+            if self.cpu.run(&RunLimits::new().steps(chunk)) == StopReason::StepLimit {
+                steps -= chunk;
+            }
+
+            let output = self.take_serial_output();
+            if !output.is_empty() {
+                let _ = stdout.write_all(&output);
+                let _ = stdout.flush();
+            }
+        }
+    }
+
+    /// Run until the program halts or after `steps` instructions
+    pub fn run(&mut self, trace: bool, steps: Option<usize>) {
+        let mut steps = steps.unwrap_or(usize::MAX);
+        if !trace {
+            return self.run_quiet(steps);
+        }
+
         println!("INS                      |     PC |     R1 |     R2 |     R3 |     R4 |     LP |     SP | EVENT");
         println!("=========================|========|========|========|========|========|========|========|=============");
-        while self.cpu.get_mem(0xffff) != 0 {
+        while self.cpu.is_running() && steps > 0 {
+            steps -= 1;
+            let pc = self.cpu.peek_reg(Register::PC);
             let (ins, events) = self.cpu.exec_next();
 
-            let events_fmt = events.into_iter().filter_map(Self::format_event).fold(String::new(), |a, b| a + &b + "; ");
+            let mut events_fmt = events.into_iter().filter_map(Self::format_event).fold(String::new(), |a, b| a + &b + "; ");
+
+            let output = self.take_serial_output();
+            if !output.is_empty() {
+                events_fmt += &format!("SERIAL({:?}); ", String::from_utf8_lossy(&output));
+            }
+
+            if let Some((line, text)) = self.source.as_ref().and_then(|s| s.line(pc)) {
+                events_fmt += &format!("@{}: {}", line, text);
+            }
 
             println!(
                 "{:24} | {:#06x} | {:#06x} | {:#06x} | {:#06x} | {:#06x} | {:#06x} | {:#06x} | {}",
@@ -1,51 +1,270 @@
+mod debug;
 mod exec;
+mod source;
+mod symbols;
 
 use clap::Parser;
+use debug::Debugger;
 use exec::DebugCpu;
-use std::{fs, io::Write, process::exit};
+use easycpu_lib::exec::{ExecCpu, Snapshot, Timing};
+use source::Source;
+use symbols::Symbols;
+use std::{fs, io::{IsTerminal, Read, Write}, process::exit};
 
-use easycpu_lib::asm::{disasm::disassemle_instruction, parse_and_compile};
-use easycpu_lib::cpu::Instruction;
+use easycpu_lib::asm::{
+    disasm::{disassemble_program, disassemle_instruction},
+    assemble_files,
+    listing::listing_files,
+    AsmOptions,
+};
+use easycpu_lib::compile::{CompiledProgram, SymbolTable};
+use easycpu_lib::executable::Executable;
+use easycpu_lib::cpu::{Instruction, Register};
+use easycpu_lib::diagnostic::{render, render_json, render_warnings, render_warnings_json, Style};
+use easycpu_lib::image::{write_image, ImageFormat};
+use easycpu_lib::link::{link, Object};
+use easycpu_lib::parser::{FsLoader, SourceSet};
 
-fn compile_file(src: std::path::PathBuf, dst: std::path::PathBuf) -> Result<(), String> {
-    let source =
-        fs::read_to_string(&src).map_err(|e| format!("Failed to read file {:#?}: {}", src, e))?;
+/// How compile errors are printed
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ErrorFormat {
+    /// Colours when printing to a terminal
+    Auto,
+    Plain,
+    Color,
+    Json,
+}
+
+impl ErrorFormat {
+    /// Style of text output, none for JSON
+    fn style(&self) -> Option<Style> {
+        match self {
+            ErrorFormat::Auto if std::io::stderr().is_terminal() => Some(Style::Color),
+            ErrorFormat::Auto | ErrorFormat::Plain => Some(Style::Plain),
+            ErrorFormat::Color => Some(Style::Color),
+            ErrorFormat::Json => None,
+        }
+    }
+}
+
+/// Assemble files as one program, returns the sources read including the included ones
+fn compile_sources(
+    src: &[std::path::PathBuf],
+    options: &AsmOptions,
+    format: ErrorFormat,
+) -> Result<(CompiledProgram, SourceSet), String> {
+    let names: Vec<_> = src.iter().map(|p| p.to_string_lossy()).collect();
+    let names: Vec<&str> = names.iter().map(|n| n.as_ref()).collect();
 
-    match parse_and_compile(&source) {
-        Ok(compiled) => {
-            let bytes: Vec<_> = compiled.iter().flat_map(|x| x.to_be_bytes()).collect();
-            let mut file = fs::File::create(&dst)
-                .map_err(|e| format!("Failed to create file {:#?}: {}", dst, e))?;
-            // Write a slice of bytes to the file
-            file.write_all(&bytes)
-                .map_err(|e| format!("Failed to write file {:#?}: {}", dst, e))?;
+    let mut sources = SourceSet::new();
+    let loader = Box::new(FsLoader);
+    let compiled = assemble_files(&names, loader, &mut sources, options).map_err(|errs| {
+        let rendered = match format.style() {
+            Some(style) => render(&errs, &sources, style),
+            None => render_json(&errs, &sources),
+        };
+        rendered.trim_end().to_owned()
+    })?;
+    Ok((compiled, sources))
+}
 
-            Ok(())
+fn print_warnings(compiled: &CompiledProgram, sources: &SourceSet, format: ErrorFormat) {
+    if compiled.warnings.is_empty() {
+        return;
+    }
+    let rendered = match format.style() {
+        Some(style) => render_warnings(&compiled.warnings, sources, style),
+        None => render_warnings_json(&compiled.warnings, sources),
+    };
+    eprintln!("{}", rendered.trim_end());
+}
+
+/// References to `.extern` labels are only resolved by `link`
+fn check_unresolved(compiled: &CompiledProgram) -> Result<(), String> {
+    let mut names: Vec<&str> = compiled.relocations.iter().map(|r| r.symbol.as_str()).collect();
+    names.sort();
+    names.dedup();
+    if names.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Unresolved symbols: {}, assemble with -c and link the objects",
+        names.join(", ")
+    ))
+}
+
+/// Entry point given as a label name or an address
+fn resolve_entry(entry: &str, symbols: &SymbolTable) -> Result<u16, String> {
+    match symbols.get(&entry.to_uppercase()) {
+        Some(symbol) => Ok(symbol.addr),
+        None => symbols::parse_value(entry).map_err(|_| format!("Unknown entry point {}", entry)),
+    }
+}
+
+/// Memory image, or an executable keeping the symbols and the source map with
+/// the names of `files` it refers to
+fn write_program(
+    dst: &std::path::Path,
+    format: Option<ImageFormat>,
+    program: &CompiledProgram,
+    files: Vec<String>,
+    entry: Option<&str>,
+) -> Result<(), String> {
+    let format = format.unwrap_or_else(|| ImageFormat::from_path(dst));
+    let bytes = match (format, entry) {
+        (ImageFormat::Executable, _) => {
+            let mut exe = Executable::from_program(program);
+            exe.files = files;
+            if let Some(entry) = entry {
+                exe.entry = resolve_entry(entry, &program.symbols)?;
+            }
+            exe.write()
         }
-        Err(errs) => Err(errs
-            .into_iter()
-            .map(|e| format!("Error at {}: {:#?}", e.start_pos, e.error))
+        (_, Some(_)) => return Err(String::from("Entry point needs the exe format")),
+        _ => write_image(format, &program.code),
+    };
+    let mut file = fs::File::create(dst)
+        .map_err(|e| format!("Failed to create file {:#?}: {}", dst, e))?;
+    // Write a slice of bytes to the file
+    file.write_all(&bytes)
+        .map_err(|e| format!("Failed to write file {:#?}: {}", dst, e))
+}
+
+fn compile_file(args: Asm) -> Result<(), String> {
+    let dst = args.output;
+    let mut options = AsmOptions::new();
+    for define in &args.defines {
+        options = options
+            .define_str(define)
+            .map_err(|e| format!("Invalid define {:?}: {}", define, e))?;
+    }
+    for warning in &args.warnings {
+        options = options.warning(warning).map_err(|e| e.to_string())?;
+    }
+
+    let (compiled, sources) = compile_sources(&args.src, &options, args.error_format)?;
+    print_warnings(&compiled, &sources, args.error_format);
+    if args.stats {
+        let stats = compiled.layout;
+        eprintln!(
+            "{} words in {} passes, {} of {} jumps relaxed",
+            compiled.code.len(),
+            stats.passes,
+            stats.relaxed,
+            stats.jumps
+        );
+    }
+    if let Some(lst) = args.listing {
+        fs::write(&lst, listing_files(&sources, &compiled))
+            .map_err(|e| format!("Failed to write file {:#?}: {}", lst, e))?;
+    }
+    if let Some(sym) = args.symbols {
+        fs::write(&sym, compiled.symbols.to_string())
+            .map_err(|e| format!("Failed to write file {:#?}: {}", sym, e))?;
+    }
+
+    if args.object {
+        return fs::write(&dst, Object::from(compiled).to_string())
+            .map_err(|e| format!("Failed to write file {:#?}: {}", dst, e));
+    }
+
+    check_unresolved(&compiled)?;
+    let files = sources.iter().map(|f| f.name.clone()).collect();
+    write_program(&dst, args.format, &compiled, files, args.entry.as_deref())
+}
+
+fn link_files(args: Link) -> Result<(), String> {
+    let mut objects = Vec::new();
+    for src in &args.objects {
+        let text = fs::read_to_string(src)
+            .map_err(|e| format!("Failed to read file {:#?}: {}", src, e))?;
+        let object = Object::parse(&text)
+            .map_err(|e| format!("Failed to load {:#?}: {}", src, e))?;
+        objects.push(object);
+    }
+
+    let linked = link(&objects).map_err(|errs| {
+        errs.iter()
+            .map(|e| format!("Link error: {}", e))
             .collect::<Vec<String>>()
-            .join("\n")),
+            .join("\n")
+    })?;
+
+    if let Some(sym) = args.symbols {
+        fs::write(&sym, linked.symbols.to_string())
+            .map_err(|e| format!("Failed to write file {:#?}: {}", sym, e))?;
     }
+    write_program(&args.output, args.format, &linked, Vec::new(), args.entry.as_deref())
+}
+
+fn parse_format(name: &str) -> Result<ImageFormat, String> {
+    ImageFormat::from_name(name).ok_or_else(|| {
+        let names: Vec<_> = ImageFormat::ALL.iter().map(|f| f.name()).collect();
+        format!("unknown format, expected one of: {}", names.join(", "))
+    })
 }
 
-fn load_u16_file(src: std::path::PathBuf) -> Vec<u16> {
-    let assembled = fs::read(src).unwrap();
-    assembled
-        .chunks(2)
-        .map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]]))
-        .collect()
+/// Memory image or executable, raw binaries starting with the executable magic are executables
+fn load_image(src: &std::path::Path, format: Option<ImageFormat>) -> Result<Executable, String> {
+    let data = fs::read(src).map_err(|e| format!("Failed to read file {:#?}: {}", src, e))?;
+    let format = format.unwrap_or_else(|| ImageFormat::from_path(src));
+    Executable::load(format, &data)
+        .map_err(|e| format!("Failed to load {:#?} as {}: {}", src, format, e))
+}
+
+/// Load a program, assembly sources are compiled and keep their source
+fn load_program(
+    src: std::path::PathBuf,
+    format: Option<ImageFormat>,
+) -> Result<(Executable, Option<Source>), String> {
+    let is_source = src
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("s") || ext.eq_ignore_ascii_case("asm"));
+    if !is_source || format.is_some() {
+        let exe = load_image(&src, format)?;
+        let source = Source::from_executable(&exe);
+        return Ok((exe, source));
+    }
+
+    let (compiled, sources) = compile_sources(&[src], &AsmOptions::new(), ErrorFormat::Auto)?;
+    check_unresolved(&compiled)?;
+    let source = Source::new(sources, &compiled);
+    Ok((Executable::from_program(&compiled), Some(source)))
+}
+
+fn read_serial_input(src: std::path::PathBuf) -> Result<Vec<u8>, String> {
+    if src.as_os_str() == "-" {
+        let mut input = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut input)
+            .map_err(|e| format!("Failed to read stdin: {}", e))?;
+        Ok(input)
+    } else {
+        fs::read(&src).map_err(|e| format!("Failed to read file {:#?}: {}", src, e))
+    }
 }
 
-fn dissassemle_file(src: std::path::PathBuf) -> Result<(), String> {
-    let assembled = load_u16_file(src);
-    let dissassembled: Vec<String> = assembled
-        .into_iter()
-        .map(Instruction::decode)
-        .map(disassemle_instruction)
-        .collect();
-    println!("{}", dissassembled.join("\n"));
+fn read_state(src: &std::path::Path) -> Result<Snapshot, String> {
+    let data = fs::read(src).map_err(|e| format!("Failed to read file {:#?}: {}", src, e))?;
+    Snapshot::read(&data).map_err(|e| format!("Failed to load {:#?}: {}", src, e))
+}
+
+fn dissassemle_file(args: DisAsm) -> Result<(), String> {
+    let exe = load_image(&args.src, args.format)?;
+    let symbols = load_symbols(args.symbols, &args.src, exe.symbols.as_ref())?;
+    let assembled = exe.memory();
+
+    if !args.raw {
+        print!("{}", disassemble_program(&assembled, Some(&symbols.to_table())));
+        return Ok(());
+    }
+
+    for (addr, word) in assembled.into_iter().enumerate() {
+        for name in symbols.names_at(addr as u16) {
+            println!("{}:", name);
+        }
+        println!("{}", disassemle_instruction(Instruction::decode(word)));
+    }
     Ok(())
 }
 
@@ -54,18 +273,80 @@ fn dissassemle_file(src: std::path::PathBuf) -> Result<(), String> {
 #[command(bin_name = "easycpu_toolkit")]
 enum EasyCpuToolkit {
     Asm(Asm),
+    Link(Link),
     Disasm(DisAsm),
     Exec(Exec),
+    Debug(Debug),
 }
 
 #[derive(clap::Args)]
 #[command(author, version, about, long_about = None)]
 struct Asm {
-    #[arg(index = 1)]
-    src: std::path::PathBuf,
+    /// Source files, assembled one after another as a single program
+    #[arg(index = 1, required = true, num_args = 1..)]
+    src: Vec<std::path::PathBuf>,
+
+    #[arg(short = 'O', default_value = "./ram.bin")]
+    output: std::path::PathBuf,
+
+    /// Output format: bin, memh, memb, ihex, logisim, c, rust or exe, guessed from the extension by default
+    #[arg(short = 'f', long = "format", value_parser = parse_format)]
+    format: Option<ImageFormat>,
+
+    /// Write label addresses to a symbol file
+    #[arg(short = 's', long = "symbols")]
+    symbols: Option<std::path::PathBuf>,
+
+    /// Write source lines with their addresses and encoded words
+    #[arg(short = 'l', long = "listing")]
+    listing: Option<std::path::PathBuf>,
+
+    /// Define `NAME=value` for `@if` conditions and as a constant, a bare `NAME` is 1
+    #[arg(short = 'D', long = "define", value_name = "NAME=value")]
+    defines: Vec<String>,
+
+    /// Write a relocatable object for `link` instead of a memory image
+    #[arg(short = 'c', long = "object")]
+    object: bool,
+
+    /// Entry point label or address, stored in exe output
+    #[arg(short = 'e', long = "entry")]
+    entry: Option<String>,
+
+    /// Enable a warning by name, disable it with `no-NAME`, `error` turns warnings into errors
+    #[arg(short = 'W', value_name = "WARNING")]
+    warnings: Vec<String>,
+
+    /// How errors and warnings are printed
+    #[arg(long = "error-format", value_enum, default_value = "auto")]
+    error_format: ErrorFormat,
+
+    /// Print how many passes laying out the program took and the jumps relaxed
+    #[arg(long = "stats")]
+    stats: bool,
+}
+
+#[derive(clap::Args)]
+#[command(author, version, about, long_about = None)]
+struct Link {
+    /// Object files written by `asm -c`, placed in the given order
+    #[arg(index = 1, required = true, num_args = 1..)]
+    objects: Vec<std::path::PathBuf>,
 
     #[arg(short = 'O', default_value = "./ram.bin")]
     output: std::path::PathBuf,
+
+    /// Output format: bin, memh, memb, ihex, logisim, c, rust or exe, guessed from the extension by default
+    #[arg(short = 'f', long = "format", value_parser = parse_format)]
+    format: Option<ImageFormat>,
+
+    /// Write label addresses to a symbol file
+    #[arg(short = 's', long = "symbols")]
+    symbols: Option<std::path::PathBuf>,
+
+    /// Entry point label or address, stored in exe output
+    #[arg(short = 'e', long = "entry")]
+    entry: Option<String>,
 }
 
 #[derive(clap::Args)]
@@ -73,6 +354,18 @@ struct Asm {
 struct DisAsm {
     #[arg(index = 1)]
     src: std::path::PathBuf,
+
+    /// Symbol file, defaults to the binary path with `.sym` extension
+    #[arg(short = 's', long = "symbols")]
+    symbols: Option<std::path::PathBuf>,
+
+    /// Decode every word on its own instead of emitting assembler source
+    #[arg(short = 'r', long = "raw")]
+    raw: bool,
+
+    /// Image format, guessed from the extension by default
+    #[arg(short = 'f', long = "format", value_parser = parse_format)]
+    format: Option<ImageFormat>,
     // TODO: add output to file with flags
     // #[arg(short = 'O', default_value = "-")]
     // output: std::path::PathBuf,
@@ -81,27 +374,147 @@ struct DisAsm {
 #[derive(clap::Args)]
 #[command(author, version, about, long_about = None)]
 struct Exec {
+    /// Binary image or `.s` assembly source
     #[arg(index = 1)]
     initram: std::path::PathBuf,
+
+    /// File fed into the serial port, use `-` for stdin
+    #[arg(short = 'i', long = "input")]
+    input: Option<std::path::PathBuf>,
+
+    /// Do not print trace, only data sent over the serial port
+    #[arg(short = 'q', long = "quiet")]
+    quiet: bool,
+
+    /// Print instructions and clock cycles of the HDL to stderr once stopped
+    #[arg(short = 'c', long = "cycles")]
+    cycles: bool,
+
+    /// Stop after this many instructions even if the program did not halt
+    #[arg(short = 'n', long = "steps")]
+    steps: Option<usize>,
+
+    /// Continue from a machine state written by `--save-state` instead of the
+    /// entry point, the program is still used for the trace. `-c` turns on the
+    /// HDL timing whatever the saved state used
+    #[arg(long = "load-state")]
+    load_state: Option<std::path::PathBuf>,
+
+    /// Write the machine state to a file once execution stops
+    #[arg(long = "save-state")]
+    save_state: Option<std::path::PathBuf>,
+
+    /// Image format, guessed from the extension by default
+    #[arg(short = 'f', long = "format", value_parser = parse_format)]
+    format: Option<ImageFormat>,
     // TODO: add output to file with flags
     // #[arg(short = 'O', default_value = "-")]
     // output: std::path::PathBuf,
 }
 
+fn load_symbols(
+    path: Option<std::path::PathBuf>,
+    program: &std::path::Path,
+    embedded: Option<&SymbolTable>,
+) -> Result<Symbols, String> {
+    // Pick up symbols written next to the binary
+    let sym_path = path.or_else(|| Some(program.with_extension("sym")).filter(|p| p.exists()));
+    match (sym_path, embedded) {
+        (Some(path), _) => Symbols::load(&path),
+        (None, Some(symbols)) => Ok(Symbols::from(symbols)),
+        (None, None) => Ok(Symbols::default()),
+    }
+}
+
+fn debug_file(args: Debug) -> Result<(), String> {
+    let (exe, source) = load_program(args.initram.clone(), args.format)?;
+    let symbols = match &source {
+        Some(source) if args.symbols.is_none() => Symbols::from(source.symbols()),
+        _ => load_symbols(args.symbols, &args.initram, exe.symbols.as_ref())?,
+    };
+
+    let mut cpu = ExecCpu::new(exe.memory());
+    cpu.set_reg(Register::PC, exe.entry);
+    if let Some(state) = args.load_state {
+        cpu.restore(&read_state(&state)?)
+            .map_err(|e| format!("Failed to restore {:#?}: {}", state, e))?;
+    }
+    if let Some(input) = args.input {
+        if let Some(serial) = cpu.serial_mut() {
+            serial.push_input(&read_serial_input(input)?);
+        }
+    }
+
+    Debugger::new(cpu, symbols, source).repl();
+    Ok(())
+}
+
+fn exec_file(args: Exec) -> Result<(), String> {
+    let (exe, source) = load_program(args.initram, args.format)?;
+    let mut cpu = DebugCpu::new(exe.memory(), exe.entry, source);
+    if let Some(state) = &args.load_state {
+        cpu.restore(&read_state(state)?)
+            .map_err(|e| format!("Failed to restore {:#?}: {}", state, e))?;
+    }
+
+    if let Some(input) = args.input {
+        cpu.feed_serial(&read_serial_input(input)?);
+    }
+
+    // Applied after restoring so that it wins over the saved timing
+    if args.cycles {
+        cpu.set_timing(Timing::Hdl);
+    }
+
+    cpu.run(!args.quiet, args.steps);
+    if let Some(dst) = args.save_state {
+        fs::write(&dst, cpu.snapshot().write())
+            .map_err(|e| format!("Failed to write file {:#?}: {}", dst, e))?;
+    }
+    if args.cycles {
+        let stats = cpu.stats();
+        let instructions = stats.nop + stats.alu + stats.load + stats.store + stats.branch;
+        eprintln!("{} instructions in {} cycles", instructions, stats.cycles);
+    }
+    Ok(())
+}
+
+#[derive(clap::Args)]
+#[command(author, version, about, long_about = None)]
+struct Debug {
+    /// Binary image or `.s` assembly source
+    #[arg(index = 1)]
+    initram: std::path::PathBuf,
+
+    /// Symbol file, defaults to the binary path with `.sym` extension
+    #[arg(short = 's', long = "symbols")]
+    symbols: Option<std::path::PathBuf>,
+
+    /// File fed into the serial port
+    #[arg(short = 'i', long = "input")]
+    input: Option<std::path::PathBuf>,
+
+    /// Start from a machine state written by `--save-state` or `save`
+    #[arg(long = "load-state")]
+    load_state: Option<std::path::PathBuf>,
+
+    /// Image format, guessed from the extension by default
+    #[arg(short = 'f', long = "format", value_parser = parse_format)]
+    format: Option<ImageFormat>,
+}
+
 fn main() {
     let res: Result<(), String> = match EasyCpuToolkit::parse() {
-        EasyCpuToolkit::Asm(args) => compile_file(args.src, args.output),
+        EasyCpuToolkit::Asm(args) => compile_file(args),
+        EasyCpuToolkit::Link(args) => link_files(args),
         EasyCpuToolkit::Disasm(args) => {
             // dissassemle_file
-            dissassemle_file(args.src)
+            dissassemle_file(args)
         }
         EasyCpuToolkit::Exec(args) => {
-            let init_ram = load_u16_file(args.initram);
-            let mut cpu = DebugCpu::new(init_ram);
-            // dissassemle_file
-            cpu.run();
-            Ok(())
+            exec_file(args)
         }
+        EasyCpuToolkit::Debug(args) => debug_file(args),
     };
     if let Err(e) = res {
         eprintln!("{}", e);
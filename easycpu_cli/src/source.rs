@@ -0,0 +1,52 @@
+use std::fs;
+
+use easycpu_lib::compile::{CompiledProgram, SourceMap, SymbolTable};
+use easycpu_lib::executable::Executable;
+use easycpu_lib::parser::SourceSet;
+
+/// Assembly source of a program executed by the toolkit
+pub struct Source {
+    sources: SourceSet,
+    map: SourceMap,
+    symbols: SymbolTable,
+}
+
+impl Source {
+    pub fn new(sources: SourceSet, program: &CompiledProgram) -> Self {
+        Source {
+            sources,
+            map: program.source_map.clone(),
+            symbols: program.symbols.clone(),
+        }
+    }
+
+    /// Source of an executable with a source map, when its files can still be read
+    pub fn from_executable(exe: &Executable) -> Option<Self> {
+        let map = exe.source_map.clone()?;
+        let mut sources = SourceSet::new();
+        for name in exe.files.iter() {
+            sources.add(name.clone(), fs::read_to_string(name).ok()?);
+        }
+
+        Some(Source {
+            sources,
+            map,
+            symbols: exe.symbols.clone().unwrap_or_default(),
+        })
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// One based line number, prefixed by the file name when the program
+    /// has includes, and trimmed text of the line `addr` came from
+    pub fn line(&self, addr: u16) -> Option<(String, &str)> {
+        let (file, line, text) = self.map.file_line(&self.sources, addr)?;
+        let location = match self.sources.len() {
+            1 => (line + 1).to_string(),
+            _ => format!("{}:{}", file.name, line + 1),
+        };
+        Some((location, text.trim()))
+    }
+}
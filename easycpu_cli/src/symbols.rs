@@ -0,0 +1,119 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use easycpu_lib::{
+    compile::{Symbol, SymbolTable},
+    parser::{convert_to_u16, parse_parts::parse_number},
+};
+
+// Addresses further away from a label are printed as plain numbers
+const MAX_OFFSET: u16 = 0x100;
+
+/// Label addresses loaded from a symbol file.
+///
+/// Every non-empty line holds an address and a name, `#` starts a comment.
+#[derive(Debug, Default)]
+pub struct Symbols {
+    by_name: HashMap<String, u16>,
+    by_addr: Vec<(u16, String)>,
+}
+
+pub fn parse_value(s: &str) -> Result<u16, String> {
+    parse_number(&s.to_uppercase())
+        .and_then(convert_to_u16)
+        .map_err(|_| format!("Invalid number {}", s))
+}
+
+impl Symbols {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut symbols = Symbols::default();
+
+        for (line_no, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (addr, name) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| format!("Invalid symbol at line {}", line_no + 1))?;
+            symbols.insert(name.trim(), parse_value(addr)?);
+        }
+
+        Ok(symbols)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read file {:#?}: {}", path, e))?;
+        Self::parse(&source)
+    }
+
+    pub fn insert(&mut self, name: &str, addr: u16) {
+        self.by_name.insert(name.to_uppercase(), addr);
+
+        let idx = self.by_addr.partition_point(|(a, _)| *a <= addr);
+        self.by_addr.insert(idx, (addr, name.to_owned()));
+    }
+
+    /// Names of labels placed exactly at `addr`
+    pub fn names_at(&self, addr: u16) -> impl Iterator<Item = &str> {
+        let start = self.by_addr.partition_point(|(a, _)| *a < addr);
+        self.by_addr[start..]
+            .iter()
+            .take_while(move |(a, _)| *a == addr)
+            .map(|(_, name)| name.as_str())
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<u16> {
+        self.by_name.get(&name.to_uppercase()).copied()
+    }
+
+    /// Value of a number or a label, optionally followed by `+offset`
+    pub fn resolve(&self, s: &str) -> Result<u16, String> {
+        let (base, offset) = match s.split_once('+') {
+            Some((base, offset)) => (base, parse_value(offset)?),
+            None => (s, 0),
+        };
+
+        match self.lookup(base) {
+            Some(addr) => Ok(addr.wrapping_add(offset)),
+            None => parse_value(base)
+                .map(|addr| addr.wrapping_add(offset))
+                .map_err(|_| format!("Unknown symbol or number {}", s)),
+        }
+    }
+
+    pub fn to_table(&self) -> SymbolTable {
+        let mut table = SymbolTable::new();
+        for (addr, name) in self.by_addr.iter() {
+            table.push(Symbol {
+                name: name.clone(),
+                scope: Vec::new(),
+                addr: *addr,
+            });
+        }
+        table
+    }
+
+    /// Describe address relative to the closest preceding label
+    pub fn describe(&self, addr: u16) -> Option<String> {
+        let idx = self.by_addr.partition_point(|(a, _)| *a <= addr);
+        let (base, name) = self.by_addr.get(idx.checked_sub(1)?)?;
+
+        match addr - base {
+            0 => Some(name.clone()),
+            offset if offset < MAX_OFFSET => Some(format!("{}+{}", name, offset)),
+            _ => None,
+        }
+    }
+}
+
+impl From<&SymbolTable> for Symbols {
+    fn from(table: &SymbolTable) -> Self {
+        let mut symbols = Symbols::default();
+        for symbol in table.iter() {
+            symbols.insert(&symbol.full_name(), symbol.addr);
+        }
+        symbols
+    }
+}
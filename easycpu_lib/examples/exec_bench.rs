@@ -0,0 +1,137 @@
+//! Instructions per second of `ExecCpu::run` with and without the decode
+//! cache, next to stepping with `exec_next` as callers did before `run`
+//! existed, collecting the events of every instruction. The cached run is
+//! measured again with an observer attached, which only costs when one is.
+//!
+//! `cargo run --release -p easycpu_lib --example exec_bench`
+
+use std::time::Instant;
+
+use easycpu_lib::{
+    asm::{parse_and_compile, AsmOptions},
+    cpu::{Instruction, Register},
+    exec::{ExecCpu, ExecObserver, RunLimits},
+};
+
+const STEPS: usize = 10_000_000;
+
+const PROGRAMS: [(&str, &str); 3] = [
+    (
+        "alu",
+        "LCONST r3 0x1234
+        LOOP: ADD r2 r2 r3
+        DEC r3 r3
+        JNE r3 LOOP
+        JMP LOOP",
+    ),
+    (
+        "memory",
+        "LCONST r3 0x4000
+        OUTER: LCONST r4 0xff
+        LOOP: STORE r4 r3 0
+        LOAD r5 r3 0
+        ADD r2 r2 r5
+        DEC r4 r4
+        JNE r4 LOOP
+        INC r3 r3
+        JMP OUTER",
+    ),
+    (
+        "calls",
+        "$INIT
+        LOOP: $CALL F
+        JMP LOOP
+        F: {
+        $FUNC 0 0 0
+        $RET
+        }",
+    ),
+];
+
+/// Observer counting instructions, the least one can do
+#[derive(Clone, Debug, Default)]
+struct Counter(usize);
+
+impl ExecObserver for Counter {
+    fn after_instruction(&mut self, _: u16, _: &Instruction) {
+        self.0 += 1;
+    }
+
+    fn duplicate(&self) -> Box<dyn ExecObserver> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Mode {
+    /// `exec_next` in a loop without the cache
+    Step,
+    /// `run` decoding every instruction
+    Decode,
+    /// `run` with the decode cache
+    Cached,
+    /// `run` with the decode cache and a `Counter` attached
+    Observed,
+}
+
+/// Instructions per second and the registers at the end
+fn measure(code: &[u16], mode: Mode) -> (f64, Vec<u16>) {
+    let mut cpu = ExecCpu::new(code.to_vec());
+    cpu.set_decode_cache(matches!(mode, Mode::Cached | Mode::Observed));
+    if let Mode::Observed = mode {
+        cpu.attach(Box::<Counter>::default());
+    }
+
+    let start = Instant::now();
+    match mode {
+        Mode::Step => {
+            for _ in 0..STEPS {
+                cpu.exec_next();
+            }
+        }
+        Mode::Decode | Mode::Cached | Mode::Observed => {
+            cpu.run(&RunLimits::new().steps(STEPS));
+        }
+    }
+    let rate = STEPS as f64 / start.elapsed().as_secs_f64();
+
+    let regs = [
+        Register::PC,
+        Register::R2,
+        Register::R3,
+        Register::R4,
+        Register::SP,
+    ];
+    (rate, regs.iter().map(|r| cpu.peek_reg(*r)).collect())
+}
+
+fn main() {
+    println!(
+        "{:8} {:>14} {:>12} {:>12} {:>8} {:>14}",
+        "program", "exec_next M/s", "decode M/s", "cached M/s", "speedup", "observed M/s"
+    );
+    for (name, text) in PROGRAMS {
+        let code = match parse_and_compile(text, &AsmOptions::new()) {
+            Ok(compiled) => compiled.code,
+            Err(errs) => panic!("{} does not assemble: {:?}", name, errs),
+        };
+
+        let (step, step_regs) = measure(&code, Mode::Step);
+        let (plain, plain_regs) = measure(&code, Mode::Decode);
+        let (cached, cached_regs) = measure(&code, Mode::Cached);
+        let (observed, observed_regs) = measure(&code, Mode::Observed);
+        assert_eq!(step_regs, plain_regs, "{} ends differently", name);
+        assert_eq!(plain_regs, cached_regs, "{} ends differently", name);
+        assert_eq!(cached_regs, observed_regs, "{} ends differently", name);
+
+        println!(
+            "{:8} {:>14.1} {:>12.1} {:>12.1} {:>7.2}x {:>14.1}",
+            name,
+            step / 1e6,
+            plain / 1e6,
+            cached / 1e6,
+            cached / plain,
+            observed / 1e6
+        );
+    }
+}
@@ -190,6 +190,7 @@ impl Atom for AluInstruction {
         };
 
         ctx.instruct(self.op.match_instruction(ins));
+        ctx.flow.write(&ctx.status, self.dst);
 
         Ok(())
     }
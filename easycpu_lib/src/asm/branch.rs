@@ -82,6 +82,7 @@ impl Atom for BranchInstruction {
         }
 
         ctx.instruct(ins);
+        ctx.flow.instruction(&ctx.status, false);
 
         Ok(())
     }
@@ -0,0 +1,50 @@
+use crate::compile::{Atom, CompileContext, CompileError, Constant};
+use crate::parser::expr::{symbol_char, Expr};
+
+use super::deferred::with_symbols;
+
+/// Named constant from `.equ`, `.set` or `NAME = value`
+#[derive(Debug)]
+pub struct ConstantDefinition {
+    name: String,
+    expr: Expr,
+    redefinable: bool,
+}
+
+impl ConstantDefinition {
+    pub fn new(name: String, expr: Expr, redefinable: bool) -> Self {
+        ConstantDefinition {
+            name,
+            expr,
+            redefinable,
+        }
+    }
+
+    pub fn parse_asm(name: &str, expr: &str, redefinable: bool) -> Result<Self, CompileError> {
+        let name = name.trim();
+        let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(symbol_char);
+        if !valid {
+            return Err(CompileError::InvalidExpression(name.to_owned()));
+        }
+
+        let expr = Expr::parse(expr.trim())?;
+        Ok(ConstantDefinition::new(name.to_owned(), expr, redefinable))
+    }
+}
+
+impl Atom for ConstantDefinition {
+    fn compile(&self, ctx: &mut CompileContext) -> Result<(), CompileError> {
+        let Some(value) = with_symbols(ctx, |env| self.expr.eval(Some(env)))? else {
+            return Ok(());
+        };
+
+        ctx.define_constant(
+            &self.name,
+            Constant {
+                value,
+                redefinable: self.redefinable,
+            },
+        )
+    }
+}
@@ -84,6 +84,7 @@ impl NopInstruction {
 impl Atom for NopInstruction {
     fn compile(&self, ctx: &mut CompileContext) -> Result<(), CompileError> {
         ctx.instruct(cpu::Instruction::NOP);
+        ctx.flow.instruction(&ctx.status, false);
         Ok(())
     }
 }
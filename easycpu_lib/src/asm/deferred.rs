@@ -0,0 +1,51 @@
+use crate::compile::{Atom, AtomBox, CompileContext, CompileError, ContextEnv};
+use crate::parser::expr::SymbolEnv;
+
+pub type ParseFn = fn(&str, Option<&dyn SymbolEnv>) -> Result<AtomBox, CompileError>;
+
+/// Statement with operands referring to symbols, parsed again on every pass
+/// once their values are known
+#[derive(Debug)]
+pub struct DeferredAtom {
+    text: String,
+    parse: ParseFn,
+}
+
+impl DeferredAtom {
+    pub fn new(text: String, parse: ParseFn) -> Self {
+        DeferredAtom { text, parse }
+    }
+
+    /// Parse statement, deferring it when operands need symbols
+    pub fn parse(text: String, parse: ParseFn) -> Result<AtomBox, CompileError> {
+        match parse(&text, None) {
+            Err(CompileError::DeferredExpression) => Ok(Box::new(DeferredAtom::new(text, parse))),
+            res => res,
+        }
+    }
+}
+
+/// Evaluate with the context symbols, failures while labels are still being
+/// resolved come from placeholder values and only request another pass
+pub fn with_symbols<T>(
+    ctx: &mut CompileContext,
+    f: impl FnOnce(&dyn SymbolEnv) -> Result<T, CompileError>,
+) -> Result<Option<T>, CompileError> {
+    let res = f(&ContextEnv::new(ctx));
+    match res {
+        Err(_) if !ctx.named_resolver.ready() => {
+            ctx.status.recompile();
+            Ok(None)
+        }
+        res => res.map(Some),
+    }
+}
+
+impl Atom for DeferredAtom {
+    fn compile(&self, ctx: &mut CompileContext) -> Result<(), CompileError> {
+        match with_symbols(ctx, |env| (self.parse)(&self.text, Some(env)))? {
+            Some(atom) => atom.compile(ctx),
+            None => Ok(()),
+        }
+    }
+}
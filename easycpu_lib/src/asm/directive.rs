@@ -0,0 +1,92 @@
+use crate::compile::{Atom, CompileContext, CompileError};
+use crate::cpu;
+use crate::parser::ParseParts;
+
+const MEMORY_WORDS: usize = 0x10000;
+
+/// Layout directives, words they emit are zero unless filled with a value
+#[derive(Clone, Debug)]
+pub enum Directive {
+    /// Pad up to an absolute address
+    Org(u16),
+    /// Pad up to a multiple of the value
+    Align(u16),
+    /// Reserve zeroed words
+    Space(u16),
+    /// Repeat a value
+    Fill(u16, u16),
+    /// Emit values of expressions, labels are absolute addresses
+    Word(Vec<u16>),
+}
+
+impl Directive {
+    pub fn parse_asm(name: &str, mut parts: ParseParts) -> Result<Directive, CompileError> {
+        match name {
+            "ORG" => Ok(Directive::Org(parts.pop_const()?)),
+            "ALIGN" => match parts.pop_const()? {
+                0 => Err(CompileError::InvalidAlignment(0)),
+                align => Ok(Directive::Align(align)),
+            },
+            "SPACE" => Ok(Directive::Space(parts.pop_const()?)),
+            "FILL" => {
+                let count = parts.pop_const()?;
+                let val = parts.pop_const()?;
+                Ok(Directive::Fill(count, val))
+            }
+            "WORD" => {
+                let mut words = Vec::new();
+                while !parts.is_empty() {
+                    words.push(parts.pop_const()?);
+                }
+                if words.is_empty() {
+                    return Err(CompileError::NotEnoughArguments);
+                }
+                Ok(Directive::Word(words))
+            }
+            _ => Err(CompileError::UnknownDirective(format!(".{}", name))),
+        }
+    }
+
+    fn fill(ctx: &mut CompileContext, pc: u16, count: u16, val: u16) -> Result<(), CompileError> {
+        if pc as usize + count as usize > MEMORY_WORDS {
+            return Err(CompileError::ProgramTooLarge);
+        }
+        for _ in 0..count {
+            ctx.instruct(cpu::Instruction::CUSTOM(val));
+        }
+        Ok(())
+    }
+}
+
+impl Atom for Directive {
+    fn compile(&self, ctx: &mut CompileContext) -> Result<(), CompileError> {
+        // Fails once in contexts without a location counter, like stackopt blocks
+        let pc = ctx.current_pc()?;
+
+        match self {
+            Directive::Org(addr) => {
+                ctx.position_dependent = true;
+                if *addr < pc {
+                    return Err(CompileError::OriginBehind(*addr));
+                }
+                Self::fill(ctx, pc, addr - pc, 0)?;
+            }
+            Directive::Align(align) => {
+                ctx.position_dependent = true;
+                let rem = pc % align;
+                if rem != 0 {
+                    Self::fill(ctx, pc, align - rem, 0)?;
+                }
+            }
+            Directive::Space(count) => Self::fill(ctx, pc, *count, 0)?,
+            Directive::Fill(count, val) => Self::fill(ctx, pc, *count, *val)?,
+            Directive::Word(words) => {
+                for word in words {
+                    ctx.instruct(cpu::Instruction::CUSTOM(*word));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,751 @@
-use crate::cpu;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::{
+    compile::{comp::CompContext, CompileError, SymbolTable},
+    cpu::{self, Instruction, Register},
+    stack::{compile_stackop, instr::CallStackOp, StackOperation},
+};
+
+use super::{
+    alu::AluOperation,
+    jump::{JumpInstruction, JumpOperation},
+    load_label::LoadLabelInstruction,
+    parse_and_compile, AsmOptions,
+};
 
 pub fn disassemle_instruction(ins: cpu::Instruction) -> String {
     ins.to_string()
 }
+
+const JUMP_OPS: [JumpOperation; 7] = [
+    JumpOperation::JMP,
+    JumpOperation::JEQ,
+    JumpOperation::JGT,
+    JumpOperation::JLT,
+    JumpOperation::JGE,
+    JumpOperation::JLE,
+    JumpOperation::JNE,
+];
+
+// Preferred names first, plain ADD and AND always match
+const ALU_OPS: [AluOperation; 13] = [
+    AluOperation::MOV,
+    AluOperation::INC,
+    AluOperation::DEC,
+    AluOperation::NEG,
+    AluOperation::NOT,
+    AluOperation::SHL,
+    AluOperation::SHR,
+    AluOperation::SUB,
+    AluOperation::OR,
+    AluOperation::NAND,
+    AluOperation::NOR,
+    AluOperation::ADD,
+    AluOperation::AND,
+];
+
+// Constants `LoadConstInstruction` encodes without a literal, two word
+// variants first so they are not split into separate instructions
+const SHORT_CONSTS: [u16; 5] = [2, 0xfffe, 0, 1, 0xffff];
+
+const MAX_DATA_PER_LINE: usize = 8;
+const MIN_STRING_LEN: usize = 4;
+
+/// Compilation context used to encode label idioms at a fixed address
+struct ProbeComp {
+    pc: u16,
+    target: u16,
+    instructions: Vec<Instruction>,
+}
+
+impl CompContext for ProbeComp {
+    fn instruct(&mut self, instruction: Instruction) {
+        self.instructions.push(instruction);
+        self.pc = self.pc.wrapping_add(1);
+    }
+
+    fn emit_new_label(&mut self) -> usize {
+        0
+    }
+
+    fn emit_label(&mut self, _: usize) -> Result<(), CompileError> {
+        Ok(())
+    }
+
+    fn resolve_label(&mut self, _: usize) -> Result<u16, CompileError> {
+        Ok(self.target.wrapping_sub(self.pc))
+    }
+
+    fn current_pc(&mut self) -> Result<u16, CompileError> {
+        Ok(self.pc)
+    }
+
+    fn stack(&mut self, _: Box<dyn StackOperation>) {}
+}
+
+fn probe(
+    at: usize,
+    target: usize,
+    f: impl FnOnce(&mut dyn CompContext) -> Result<(), CompileError>,
+) -> Option<Vec<u16>> {
+    let mut comp = ProbeComp {
+        pc: at as u16,
+        target: target as u16,
+        instructions: Vec::new(),
+    };
+    f(&mut comp).ok()?;
+    comp.instructions.iter().map(|x| x.encode().ok()).collect()
+}
+
+/// Number of words of the `$CALL` sequence `words` start with, `at` is where
+/// they are placed
+pub fn call_len(at: u16, words: &[u16]) -> Option<usize> {
+    let offset = *words.get(6)?;
+    let target = at.wrapping_add(4).wrapping_add(offset);
+    let call = probe(at as usize, target as usize, |c| {
+        compile_stackop(c, Box::new(CallStackOp::new(0)))
+    })?;
+    words.starts_with(&call).then_some(call.len())
+}
+
+/// Number of words `text` assembles to if they are a prefix of `words`
+fn assembles_to(text: &str, words: &[u16]) -> Option<usize> {
+    let code = parse_and_compile(text, &AsmOptions::new()).ok()?.code;
+    (!code.is_empty() && words.starts_with(&code)).then_some(code.len())
+}
+
+fn flags(names: &str, flags: [bool; 3]) -> String {
+    let set: String = names
+        .chars()
+        .zip(flags)
+        .filter(|(_, f)| *f)
+        .map(|(c, _)| c)
+        .collect();
+
+    if set.is_empty() {
+        set
+    } else {
+        format!(".{}", set)
+    }
+}
+
+fn literal(word: u16) -> String {
+    format!("{:#06x}", word)
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Flow {
+    Next,
+    Jump(u16),
+    Branch(u16),
+    Stop,
+}
+
+#[derive(Clone, Debug)]
+struct Item {
+    text: String,
+    len: usize,
+    /// Address written as a label after `text`
+    target: Option<usize>,
+    flow: Flow,
+}
+
+impl Item {
+    fn new(text: String, len: usize, flow: Flow) -> Self {
+        Item {
+            text,
+            len,
+            target: None,
+            flow,
+        }
+    }
+
+    fn is_idiom(&self) -> bool {
+        self.len > 1 || self.target.is_some()
+    }
+}
+
+struct Disassembler<'a> {
+    code: &'a [u16],
+    names: HashMap<usize, Vec<String>>,
+
+    /// Addresses which are disassembled word by word
+    raw: HashSet<usize>,
+    cache: HashMap<(usize, bool), Item>,
+
+    items: BTreeMap<usize, Item>,
+    labels: BTreeSet<usize>,
+}
+
+fn sanitize_name(name: &str) -> String {
+    let name: String = name
+        .to_uppercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => name,
+        _ => format!("L_{}", name),
+    }
+}
+
+impl<'a> Disassembler<'a> {
+    fn new(code: &'a [u16], symbols: Option<&SymbolTable>) -> Self {
+        let mut names: HashMap<usize, Vec<String>> = HashMap::new();
+        let mut used = HashSet::new();
+
+        for symbol in symbols.iter().flat_map(|s| s.iter()) {
+            let addr = symbol.addr as usize;
+            if addr > code.len() {
+                continue;
+            }
+
+            let mut name = sanitize_name(&symbol.full_name());
+            if used.contains(&name) {
+                name = format!("{}_{:04X}", name, addr);
+            }
+            used.insert(name.clone());
+            names.entry(addr).or_default().push(name);
+        }
+
+        Disassembler {
+            code,
+            names,
+            raw: HashSet::new(),
+            cache: HashMap::new(),
+            items: BTreeMap::new(),
+            labels: BTreeSet::new(),
+        }
+    }
+
+    fn jump_item(&self, at: usize) -> Option<Item> {
+        let w = &self.code[at..];
+        let mut candidates = Vec::new();
+
+        match Instruction::decode(w[0]) {
+            Instruction::BRANCH(b) => {
+                // Long conditional jump skips over inverted branch, prefer it
+                // over a short jump to the next instruction
+                if let (3, Some(lit)) = (b.shift, w.get(2)) {
+                    let target = (at as u16).wrapping_add(1).wrapping_add(*lit);
+                    candidates.push(((!b.eq, !b.gt, !b.lt), b.cond, target as i32));
+                }
+
+                let flags = (b.eq, b.gt, b.lt);
+                candidates.push((flags, b.cond, at as i32 + b.shift as i32));
+            }
+            Instruction::LOAD(m) if (m.dst, m.addr, m.shift) == (Register::PC, Register::PC, 1) => {
+                if let Some(lit) = w.get(1) {
+                    let target = (at as u16).wrapping_add(*lit);
+                    candidates.push(((true, true, true), Register::ZX, target as i32));
+                }
+            }
+            _ => (),
+        }
+
+        for (flags, cond, target) in candidates {
+            if target < 0 || target as usize > self.code.len() {
+                continue;
+            }
+            let target = target as usize;
+
+            let Some(op) = JUMP_OPS.into_iter().find(|op| op.get_flags() == flags) else {
+                continue;
+            };
+            if op == JumpOperation::JMP && cond != Register::ZX {
+                continue;
+            }
+
+            let Some(words) = probe(at, target, |c| JumpInstruction::instr(c, op, cond, 0)) else {
+                continue;
+            };
+            if !w.starts_with(&words) {
+                continue;
+            }
+
+            let (text, flow) = match op {
+                JumpOperation::JMP => (String::from("JMP"), Flow::Jump(target as u16)),
+                _ => (format!("{:?} {}", op, cond), Flow::Branch(target as u16)),
+            };
+            return Some(Item {
+                text,
+                len: words.len(),
+                target: Some(target),
+                flow,
+            });
+        }
+
+        None
+    }
+
+    fn call_item(&self, at: usize) -> Option<Item> {
+        // Call jumps with the offset stored two words after `LADD PC PC 2`
+        let offset = *self.code.get(at + 6)?;
+        let target = (at as u16).wrapping_add(4).wrapping_add(offset) as usize;
+        if target > self.code.len() {
+            return None;
+        }
+
+        let len = call_len(at as u16, &self.code[at..])?;
+        Some(Item {
+            text: String::from("$CALL"),
+            len,
+            target: Some(target),
+            flow: Flow::Branch(target as u16),
+        })
+    }
+
+    fn load_label_item(&self, at: usize) -> Option<Item> {
+        let w = &self.code[at..];
+        let Instruction::ADD(alu) = Instruction::decode(w[0]) else {
+            return None;
+        };
+        if (alu.nx, alu.ny, alu.no) != (false, false, false)
+            || (alu.src_a, alu.src_b) != (Register::PC, Register::ZX)
+            || alu.dst == Register::PC
+        {
+            return None;
+        }
+
+        let mut offsets = SHORT_CONSTS.to_vec();
+        offsets.extend(w.get(2).copied());
+        offsets.extend(w.get(2).map(|x| x.wrapping_neg()));
+        offsets.extend(w.get(3).copied());
+
+        for offset in offsets {
+            let target = (at as u16).wrapping_add(offset) as usize;
+            if target > self.code.len() {
+                continue;
+            }
+
+            let Some(words) = probe(at, target, |c| LoadLabelInstruction::instr(c, alu.dst, 0))
+            else {
+                continue;
+            };
+            if w.starts_with(&words) {
+                return Some(Item {
+                    text: format!("LLABEL {}", alu.dst),
+                    len: words.len(),
+                    target: Some(target),
+                    flow: Flow::Next,
+                });
+            }
+        }
+
+        None
+    }
+
+    /// Label free spellings of the code at `at`, most readable first
+    fn text_candidates(&self, at: usize) -> Vec<(String, Flow)> {
+        let w = &self.code[at..];
+        let mut res = Vec::new();
+
+        match Instruction::decode(w[0]) {
+            Instruction::NOP => res.push((String::from("NOP"), Flow::Next)),
+            Instruction::CUSTOM(_) => (),
+
+            Instruction::ADD(alu) | Instruction::AND(alu) => {
+                let is_add = matches!(Instruction::decode(w[0]), Instruction::ADD(_));
+                let flow = if alu.dst == Register::PC {
+                    Flow::Stop
+                } else {
+                    Flow::Next
+                };
+
+                // Constants loaded from ZX or a literal placed after it
+                let consts = w.get(2).map(|x| x.wrapping_neg()).into_iter();
+                for val in consts.chain(SHORT_CONSTS) {
+                    res.push((format!("LCONST {} {}", alu.dst, literal(val)), flow));
+                }
+
+                for op in ALU_OPS {
+                    let text = match op.get_second_reg(alu.src_a) {
+                        Some(_) => format!("{:?} {} {}", op, alu.dst, alu.src_a),
+                        None => format!("{:?} {} {} {}", op, alu.dst, alu.src_a, alu.src_b),
+                    };
+                    res.push((text, flow));
+                }
+
+                res.push((
+                    format!(
+                        "{}{} {} {} {}",
+                        if is_add { "ADD" } else { "AND" },
+                        flags("XYO", [alu.nx, alu.ny, alu.no]),
+                        alu.dst,
+                        alu.src_a,
+                        alu.src_b
+                    ),
+                    flow,
+                ));
+            }
+
+            Instruction::LOAD(m) => {
+                if m.addr == Register::PC {
+                    for val in [w.get(1), w.get(2)].into_iter().flatten() {
+                        let flow = match m.dst {
+                            Register::PC => Flow::Jump(*val),
+                            _ => Flow::Next,
+                        };
+                        res.push((format!("LCONST {} {}", m.dst, literal(*val)), flow));
+                    }
+
+                    let lits = [w.get(1).copied(), w.get(1).map(|x| x.wrapping_neg())];
+                    let lits = lits.into_iter().flatten().chain(w.get(2).copied());
+                    for val in lits {
+                        let flow = match m.dst {
+                            Register::PC => Flow::Jump((at as u16).wrapping_add(val)),
+                            _ => Flow::Next,
+                        };
+                        res.push((format!("ACONST {} {}", m.dst, literal(val)), flow));
+                    }
+                }
+
+                let flow = if m.dst == Register::PC {
+                    Flow::Stop
+                } else {
+                    Flow::Next
+                };
+                let args = format!("{} {} {}", m.dst, m.addr, m.shift);
+                for name in ["LOAD", "LOAD.S", "LADD", "LSUB"] {
+                    res.push((format!("{} {}", name, args), flow));
+                }
+                // LADD has no default flags, so they are written as encoded
+                res.push((
+                    format!("LADD{} {}", flags("HLS", [m.hi, m.lo, m.sw]), args),
+                    flow,
+                ));
+            }
+
+            Instruction::STORE(m) => {
+                res.push((String::from("HALT"), Flow::Stop));
+
+                let args = format!("{} {} {}", m.dst, m.addr, m.shift);
+                res.push((format!("STORE {}", args), Flow::Next));
+                res.push((
+                    format!("STORE{} {}", flags("HLS", [!m.hi, !m.lo, m.sw]), args),
+                    Flow::Next,
+                ));
+            }
+
+            Instruction::BRANCH(b) => {
+                let target = (at as u16).wrapping_add(b.shift as u16);
+                let flow = if b.eq && b.gt && b.lt && b.cond == Register::ZX {
+                    Flow::Jump(target)
+                } else {
+                    Flow::Branch(target)
+                };
+
+                if b.eq || b.gt || b.lt {
+                    res.push((
+                        format!(
+                            "BRANCH{} {} {}",
+                            flags("EGL", [b.eq, b.gt, b.lt]),
+                            b.cond,
+                            b.shift
+                        ),
+                        flow,
+                    ));
+                }
+            }
+        }
+
+        res.push((literal(w[0]), Flow::Next));
+        res
+    }
+
+    fn match_at(&mut self, at: usize) -> Item {
+        let raw = self.raw.contains(&at);
+        if let Some(item) = self.cache.get(&(at, raw)) {
+            return item.clone();
+        }
+
+        let mut item = None;
+        if !raw {
+            item = self
+                .jump_item(at)
+                .or_else(|| self.call_item(at))
+                .or_else(|| self.load_label_item(at));
+        }
+
+        if item.is_none() {
+            let words = &self.code[at..];
+            item = self
+                .text_candidates(at)
+                .into_iter()
+                .find_map(|(text, flow)| {
+                    let len = assembles_to(&text, words)?;
+                    (!raw || len == 1).then(|| Item::new(text, len, flow))
+                });
+        }
+
+        let item = item.unwrap_or_else(|| Item::new(literal(self.code[at]), 1, Flow::Next));
+        self.cache.insert((at, raw), item.clone());
+        item
+    }
+
+    fn demote(&mut self, start: usize, len: usize) {
+        self.raw.extend(start..start + len);
+    }
+
+    /// Follow control flow from the entry point, returns false if some
+    /// items had to be demoted to raw words
+    fn analyze(&mut self) -> bool {
+        self.items.clear();
+        self.labels.clear();
+
+        let len = self.code.len();
+        let mut covered: Vec<Option<usize>> = vec![None; len];
+        let mut work: Vec<usize> = Vec::new();
+        if len > 0 {
+            work.push(0);
+        }
+
+        let mut maybe_code: Vec<usize> = self.names.keys().copied().collect();
+        maybe_code.sort();
+        maybe_code.reverse();
+
+        let mut conflicts = Vec::new();
+
+        loop {
+            let Some(at) = work.pop() else {
+                // Pointers loaded into registers may point to code, data
+                // words do not look like instructions
+                let Some(at) = maybe_code.pop() else {
+                    break;
+                };
+                if at < len && covered[at].is_none() && matches!(self.code[at] >> 12, 1..=5) {
+                    work.push(at);
+                }
+                continue;
+            };
+
+            if at >= len {
+                continue;
+            }
+            match covered[at] {
+                Some(start) if start == at => continue,
+                Some(start) => {
+                    conflicts.push(start);
+                    continue;
+                }
+                None => (),
+            }
+
+            let item = self.match_at(at);
+            let end = (at + item.len).min(len);
+            if let Some(start) = covered[at..end].iter().flatten().next() {
+                conflicts.push(*start);
+                conflicts.push(at);
+                continue;
+            }
+            covered[at..end].fill(Some(at));
+
+            let next = at + item.len;
+            match item.flow {
+                Flow::Next => work.push(next),
+                Flow::Jump(target) => work.push(target as usize),
+                Flow::Branch(target) => {
+                    work.push(next);
+                    work.push(target as usize);
+                }
+                Flow::Stop => (),
+            }
+
+            if let Some(target) = item.target {
+                self.labels.insert(target);
+                if let Flow::Next = item.flow {
+                    maybe_code.push(target);
+                }
+            }
+
+            self.items.insert(at, item);
+        }
+
+        // Labels have to be placed between items
+        for label in self.labels.iter() {
+            if let Some(Some(start)) = covered.get(*label) {
+                if start != label {
+                    conflicts.push(*start);
+                }
+            }
+        }
+
+        for start in conflicts.iter() {
+            if let Some(item) = self.items.get(start) {
+                let item_len = item.len;
+                self.demote(*start, item_len);
+            } else {
+                self.raw.insert(*start);
+            }
+        }
+
+        for (addr, _) in self.names.iter() {
+            match covered.get(*addr) {
+                Some(Some(start)) if start != addr => (),
+                _ => {
+                    self.labels.insert(*addr);
+                }
+            }
+        }
+
+        conflicts.is_empty()
+    }
+
+    fn label_name(&self, addr: usize) -> String {
+        match self.names.get(&addr) {
+            Some(names) => names[0].clone(),
+            None => format!("L_{:04X}", addr),
+        }
+    }
+
+    fn data_lines(&self, from: usize, to: usize, out: &mut Vec<String>) {
+        let words = &self.code[from..to];
+        let mut pending: Vec<String> = Vec::new();
+        let mut i = 0;
+
+        let flush = |pending: &mut Vec<String>, out: &mut Vec<String>| {
+            for chunk in pending.chunks(MAX_DATA_PER_LINE) {
+                out.push(format!("    {}", chunk.join(" ")));
+            }
+            pending.clear();
+        };
+
+        while i < words.len() {
+            let run = words[i..]
+                .iter()
+                .take_while(|w| matches!(**w, 0x20..=0x7e | 0x0a | 0x09 | 0x00))
+                .take_while(|w| **w != '"' as u16 && **w != '\\' as u16)
+                .count();
+            let printable = words[i..i + run]
+                .iter()
+                .filter(|w| matches!(**w, 0x20..=0x7e))
+                .count();
+
+            if run >= MIN_STRING_LEN && printable + 1 >= MIN_STRING_LEN {
+                let text: String = words[i..i + run]
+                    .iter()
+                    .map(|w| match *w {
+                        0x0a => String::from("\\n"),
+                        0x09 => String::from("\\t"),
+                        0x00 => String::from("\\0"),
+                        w => char::from(w as u8).to_string(),
+                    })
+                    .collect();
+                let text = format!("\"{}\"", text);
+
+                if assembles_to(&text, &words[i..]) == Some(run) {
+                    flush(&mut pending, out);
+                    out.push(format!("    {}", text));
+                    i += run;
+                    continue;
+                }
+            }
+
+            pending.push(literal(words[i]));
+            i += 1;
+        }
+
+        flush(&mut pending, out);
+    }
+
+    fn render(&self) -> String {
+        let len = self.code.len();
+        let mut out: Vec<String> = Vec::new();
+        let mut at = 0;
+
+        let mut boundaries: Vec<usize> = self.labels.iter().copied().collect();
+        boundaries.push(len);
+
+        while at <= len {
+            if self.labels.contains(&at) {
+                match self.names.get(&at) {
+                    Some(names) => out.extend(names.iter().map(|n| format!("{}:", n))),
+                    None => out.push(format!("{}:", self.label_name(at))),
+                }
+            }
+            if at == len {
+                break;
+            }
+
+            if let Some(item) = self.items.get(&at) {
+                match item.target {
+                    Some(target) => {
+                        out.push(format!("    {} {}", item.text, self.label_name(target)))
+                    }
+                    None => out.push(format!("    {}", item.text)),
+                }
+                at += item.len;
+                continue;
+            }
+
+            // Data up to the next item or label
+            let next_item = self.items.range(at..).next().map(|(a, _)| *a);
+            let next_label = boundaries.iter().find(|b| **b > at).copied();
+            let end = [next_item, next_label, Some(len)]
+                .into_iter()
+                .flatten()
+                .min()
+                .unwrap_or(len);
+
+            self.data_lines(at, end, &mut out);
+            at = end;
+        }
+
+        out.join("\n") + "\n"
+    }
+
+    /// Demote the idiom responsible for the first mismatching word
+    fn fix_mismatch(&mut self, rendered: &[u16]) -> bool {
+        let Some(addr) = (0..self.code.len().max(rendered.len()))
+            .find(|i| self.code.get(*i) != rendered.get(*i))
+        else {
+            return false;
+        };
+
+        let culprit = self
+            .items
+            .range(..=addr.min(self.code.len().saturating_sub(1)))
+            .rev()
+            .find(|(_, item)| item.is_idiom())
+            .map(|(start, item)| (*start, item.len));
+
+        match culprit {
+            Some((start, len)) if !self.raw.contains(&start) => {
+                self.demote(start, len);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Disassemble a whole program into source which assembles back to `code`.
+///
+/// Code reachable from address 0 is decoded into assembler idioms with
+/// labels for jump targets, everything else is written as data.
+pub fn disassemble_program(code: &[u16], symbols: Option<&SymbolTable>) -> String {
+    let mut dis = Disassembler::new(code, symbols);
+
+    loop {
+        if !dis.analyze() {
+            continue;
+        }
+
+        let text = dis.render();
+        let rendered = parse_and_compile(&text, &AsmOptions::new()).map(|p| p.code);
+        let fixed = match rendered {
+            Ok(rendered) if rendered == code => return text,
+            Ok(rendered) => dis.fix_mismatch(&rendered),
+            Err(_) => false,
+        };
+
+        if !fixed {
+            // Every word on its own always assembles back
+            if dis.raw.len() == code.len() {
+                return text;
+            }
+            dis.raw.extend(0..code.len());
+        }
+    }
+}
@@ -25,7 +25,7 @@ pub struct JumpInstruction {
 }
 
 impl JumpOperation {
-    fn get_flags(&self) -> (bool, bool, bool) {
+    pub(crate) fn get_flags(&self) -> (bool, bool, bool) {
         match self {
             JumpOperation::JMP => (true, true, true),
 
@@ -92,15 +92,22 @@ impl JumpInstruction {
         let mut targ = comp.resolve_label(label_id)?;
         let (eq, gt, lt) = op.get_flags();
 
-        let converted = JumpInstruction::convert_u16_to_shift(targ).ok();
+        // Distance to labels of other objects is only known when linking
+        let converted = match comp.is_external(label_id) {
+            true => None,
+            false => JumpInstruction::convert_u16_to_shift(targ).ok(),
+        };
+        let short = comp.relax_jump(converted.is_some());
 
-        if let Some(converted) = converted {
+        if short {
+            // Jumps are measured as short before they are relaxed, the shift
+            // of one not reaching only takes up the space
             comp.instruct(cpu::Instruction::BRANCH(cpu::BranchInstruction {
                 eq,
                 gt,
                 lt,
                 cond,
-                shift: converted,
+                shift: converted.unwrap_or(0),
             }));
             return Ok(());
         }
@@ -117,6 +124,7 @@ impl JumpInstruction {
         }
 
         comp.instruct(MemOperation::LADD.instr(cpu::Register::PC, cpu::Register::PC, 1)?);
+        comp.relocate(label_id);
         comp.instruct(cpu::Instruction::CUSTOM(targ));
 
         Ok(())
@@ -127,6 +135,8 @@ impl JumpInstruction {
 impl Atom for JumpInstruction {
     fn compile(&self, ctx: &mut CompileContext) -> Result<(), CompileError> {
         let label_id = self.targ.resolve(ctx)?;
-        Self::instr(ctx.comp.as_mut(), self.op, self.cond, label_id)
+        Self::instr(ctx.comp.as_mut(), self.op, self.cond, label_id)?;
+        ctx.flow.instruction(&ctx.status, self.op == JumpOperation::JMP);
+        Ok(())
     }
 }
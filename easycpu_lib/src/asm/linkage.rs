@@ -0,0 +1,66 @@
+use std::cell::RefCell;
+
+use crate::compile::{Atom, CompileContext, CompileError};
+use crate::parser::ParseParts;
+
+/// `.extern` and `.global` declarations of a relocatable object
+#[derive(Debug)]
+pub enum Linkage {
+    /// Labels defined in other objects, references to them are left to the linker
+    Import {
+        names: Vec<String>,
+        ids: RefCell<Vec<usize>>,
+    },
+    /// Labels other objects may refer to
+    Export(Vec<String>),
+}
+
+impl Linkage {
+    pub fn parse_asm(name: &str, mut parts: ParseParts) -> Result<Linkage, CompileError> {
+        let mut names = Vec::new();
+        while !parts.is_empty() {
+            names.push(parts.pop_label()?.label);
+        }
+        if names.is_empty() {
+            return Err(CompileError::NotEnoughArguments);
+        }
+
+        match name {
+            "EXTERN" => Ok(Linkage::Import {
+                names,
+                ids: RefCell::new(Vec::new()),
+            }),
+            _ => Ok(Linkage::Export(names)),
+        }
+    }
+}
+
+impl Atom for Linkage {
+    fn compile(&self, ctx: &mut CompileContext) -> Result<(), CompileError> {
+        match self {
+            Linkage::Import { names, ids } => {
+                let mut ids = ids.borrow_mut();
+                for (i, name) in names.iter().enumerate() {
+                    if ids.len() == i {
+                        let id = ctx.comp.emit_external_label()?;
+                        let (pos, _) = ctx.status.pos();
+                        ctx.named_resolver.register_label(name, id, pos)?;
+                        ids.push(id);
+                    }
+                    ctx.imports.push((name.clone(), ids[i]));
+                }
+            }
+            Linkage::Export(names) => {
+                for name in names {
+                    let id = ctx.named_resolver.resolve_label_id(name)?;
+                    if ctx.comp.is_external(id) {
+                        return Err(CompileError::ExternalExported(name.clone()));
+                    }
+                    ctx.exports.push((name.clone(), id));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{compile::CompiledProgram, cpu, parser::SourceSet};
+
+/// Render every source line next to the words compiled from it.
+///
+/// Each line starts with its number and the address range it occupies,
+/// every further word of the line gets its own row below.
+pub fn listing(source: &str, program: &CompiledProgram) -> String {
+    listing_files(&SourceSet::single("", source), program)
+}
+
+/// Listing of a program assembled from several files, each file is
+/// introduced by a line with its name
+pub fn listing_files(sources: &SourceSet, program: &CompiledProgram) -> String {
+    let mut by_line: BTreeMap<(usize, usize), Vec<u16>> = BTreeMap::new();
+    for (addr, (start, _)) in program.source_map.iter().enumerate() {
+        by_line
+            .entry((start.file, start.line))
+            .or_default()
+            .push(addr as u16);
+    }
+
+    let mut out = String::new();
+    for (file_no, file) in sources.iter().enumerate() {
+        if sources.len() > 1 {
+            let _ = writeln!(out, "# {}", file.name);
+        }
+        for (line_no, line) in file.text.lines().enumerate() {
+            let addrs = by_line.remove(&(file_no, line_no)).unwrap_or_default();
+            write_line(&mut out, program, Some((line_no, line)), &addrs);
+        }
+    }
+
+    // Words mapped past the end of the source, e.g. the implicit position
+    for addrs in by_line.into_values() {
+        write_line(&mut out, program, None, &addrs);
+    }
+
+    out
+}
+
+fn write_line(
+    out: &mut String,
+    program: &CompiledProgram,
+    line: Option<(usize, &str)>,
+    addrs: &[u16],
+) {
+    // Lines emitted several times, like macro bodies, are not a single range
+    let contiguous = addrs.windows(2).all(|pair| pair[1] == pair[0] + 1);
+    let range = match (addrs.first(), addrs.last()) {
+        (Some(first), Some(last)) if first != last && contiguous => {
+            format!("{:04x}-{:04x}", first, last)
+        }
+        (Some(first), _) => format!("{:04x}     ", first),
+        _ => " ".repeat(9),
+    };
+    let (line_no, text) = match line {
+        Some((line_no, text)) => (format!("{:>5}", line_no + 1), text),
+        None => (" ".repeat(5), ""),
+    };
+
+    let mut words = addrs.iter().map(|addr| {
+        let word = program.code[*addr as usize];
+        (*addr, word, cpu::Instruction::decode(word).to_string())
+    });
+
+    let (word, ins) = match words.next() {
+        Some((_, word, ins)) => (format!("{:04x}", word), ins),
+        None => (String::new(), String::new()),
+    };
+    let row = format!("{} {}  {:4}  {:<24} {}", line_no, range, word, ins, text);
+    let _ = writeln!(out, "{}", row.trim_end());
+
+    for (addr, word, ins) in words {
+        let _ = writeln!(out, "{:5} {:04x}       {:04x}  {}", "", addr, word, ins);
+    }
+}
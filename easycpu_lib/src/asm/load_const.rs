@@ -80,93 +80,156 @@ impl LoadConstInstruction {
     }
 
     pub fn instr_add(dst: cpu::Register, val: u16) -> Vec<cpu::Instruction> {
+        Self::short_variant(val, dst, dst)
+            .or_else(|| Self::instr_add_word(dst, val))
+            .unwrap_or_else(|| Self::instr_add_literal(dst, val))
+    }
+
+    /// Add taking at least `words` words, for code which must not shrink
+    pub fn instr_add_min(dst: cpu::Register, val: u16, words: usize) -> Vec<cpu::Instruction> {
+        let v = Self::instr_add(dst, val);
+        if v.len() >= words {
+            return v;
+        }
+        match words {
+            2 => Self::instr_add_word(dst, val),
+            _ => None,
+        }
+        .unwrap_or_else(|| Self::instr_add_literal(dst, val))
+    }
+
+    /// Add of the value in the next word, which is executed as an instruction after it
+    fn instr_add_word(dst: cpu::Register, val: u16) -> Option<Vec<cpu::Instruction>> {
         let val_neg = u16::MAX.wrapping_sub(val).wrapping_add(1);
 
-        if let Some(v) = Self::short_variant(val, dst, dst) {
-            v
-        } else if val < 4096 || dst == cpu::Register::PC {
+        if val < 4096 || dst == cpu::Register::PC {
             // We can fit up to 12 bits of data into two operations
             // Ooor if we are operating on PC basically everything
-            vec![
+            Some(vec![
                 MemOperation::LADD
                     .instr(dst, cpu::Register::PC, 1)
                     .expect("CONST BAD LADD"),
                 cpu::Instruction::CUSTOM(val),
-            ]
+            ])
         } else if val_neg < 4096 {
-            vec![
+            Some(vec![
                 MemOperation::LSUB
                     .instr(dst, cpu::Register::PC, 1)
                     .expect("CONST BAD LSUB"),
                 cpu::Instruction::CUSTOM(val_neg),
-            ]
+            ])
         } else {
-            vec![
+            None
+        }
+    }
+
+    /// Add with the value as the last word whatever it is, so it can be patched later
+    pub fn instr_add_literal(dst: cpu::Register, val: u16) -> Vec<cpu::Instruction> {
+        if dst == cpu::Register::PC {
+            return vec![
                 MemOperation::LADD
-                    .instr(dst, cpu::Register::PC, 2)
+                    .instr(dst, cpu::Register::PC, 1)
                     .expect("CONST BAD LADD"),
-                cpu::Instruction::BRANCH(cpu::BranchInstruction {
-                    eq: true,
-                    gt: true,
-                    lt: true,
-                    cond: cpu::Register::ZX,
-                    shift: 2,
-                }), // Jump over value so it would not be executed
                 cpu::Instruction::CUSTOM(val),
-            ]
+            ];
         }
+
+        vec![
+            MemOperation::LADD
+                .instr(dst, cpu::Register::PC, 2)
+                .expect("CONST BAD LADD"),
+            cpu::Instruction::BRANCH(cpu::BranchInstruction {
+                eq: true,
+                gt: true,
+                lt: true,
+                cond: cpu::Register::ZX,
+                shift: 2,
+            }), // Jump over value so it would not be executed
+            cpu::Instruction::CUSTOM(val),
+        ]
     }
 
     pub fn instr_load(dst: cpu::Register, val: u16) -> Vec<cpu::Instruction> {
+        Self::short_variant(val, dst, cpu::Register::ZX)
+            .or_else(|| Self::instr_load_word(dst, val))
+            .unwrap_or_else(|| Self::instr_load_literal(dst, val))
+    }
+
+    /// Load taking at least `words` words, for code which must not shrink
+    pub fn instr_load_min(dst: cpu::Register, val: u16, words: usize) -> Vec<cpu::Instruction> {
+        let v = Self::instr_load(dst, val);
+        if v.len() >= words {
+            return v;
+        }
+        match words {
+            2 => Self::instr_load_word(dst, val),
+            _ => None,
+        }
+        .unwrap_or_else(|| Self::instr_load_literal(dst, val))
+    }
+
+    /// Load of the value in the next word, which is executed as an instruction after it
+    fn instr_load_word(dst: cpu::Register, val: u16) -> Option<Vec<cpu::Instruction>> {
         let val_neg = u16::MAX.wrapping_sub(val).wrapping_add(1);
 
-        if let Some(v) = Self::short_variant(val, dst, cpu::Register::ZX) {
-            v
-        } else if val < 4096 || dst == cpu::Register::PC {
+        if val < 4096 || dst == cpu::Register::PC {
             // We can fit up to 12 bits of data into two operations
             // Ooor if we are operating on PC basically everything
-            vec![
+            Some(vec![
                 MemOperation::LOAD
                     .instr(dst, cpu::Register::PC, 1)
                     .expect("CONST BAD LOAD"),
                 cpu::Instruction::CUSTOM(val),
-            ]
+            ])
         } else if val_neg < 4096 {
-            vec![
+            Some(vec![
                 AluOperation::MOV.instr(dst, cpu::Register::ZX, cpu::Register::ZX),
                 MemOperation::LSUB
                     .instr(dst, cpu::Register::PC, 1)
                     .expect("CONST BAD LSUB"),
                 cpu::Instruction::CUSTOM(val_neg),
-            ]
+            ])
         } else {
-            vec![
-                MemOperation::LOAD
-                    .instr(dst, cpu::Register::PC, 2)
-                    .expect("CONST BAD LADD"),
-                cpu::Instruction::BRANCH(cpu::BranchInstruction {
-                    eq: true,
-                    gt: true,
-                    lt: true,
-                    cond: cpu::Register::ZX,
-                    shift: 2,
-                }), // Jump over value so it would not be executed
-                cpu::Instruction::CUSTOM(val),
-            ]
+            None
         }
     }
+
+    fn instr_load_literal(dst: cpu::Register, val: u16) -> Vec<cpu::Instruction> {
+        vec![
+            MemOperation::LOAD
+                .instr(dst, cpu::Register::PC, 2)
+                .expect("CONST BAD LADD"),
+            cpu::Instruction::BRANCH(cpu::BranchInstruction {
+                eq: true,
+                gt: true,
+                lt: true,
+                cond: cpu::Register::ZX,
+                shift: 2,
+            }), // Jump over value so it would not be executed
+            cpu::Instruction::CUSTOM(val),
+        ]
+    }
 }
 
 impl Atom for LoadConstInstruction {
     fn compile(&self, ctx: &mut CompileContext) -> Result<(), CompileError> {
+        // Values from deferred expressions change with label addresses, the
+        // size must only grow for the layout to settle
         let v = match self.op {
-            LoadConstOperation::LOAD => Self::instr_load(self.dst, self.val),
-            LoadConstOperation::ADD => Self::instr_add(self.dst, self.val),
+            LoadConstOperation::LOAD => {
+                let words = ctx.comp.relax(Self::instr_load(self.dst, self.val).len());
+                Self::instr_load_min(self.dst, self.val, words)
+            }
+            LoadConstOperation::ADD => {
+                let words = ctx.comp.relax(Self::instr_add(self.dst, self.val).len());
+                Self::instr_add_min(self.dst, self.val, words)
+            }
         };
 
         for inst in v {
             ctx.instruct(inst);
         }
+        ctx.flow.write(&ctx.status, self.dst);
 
         Ok(())
     }
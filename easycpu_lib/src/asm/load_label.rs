@@ -40,8 +40,18 @@ impl LoadLabelInstruction {
             comp.instruct(AluOperation::MOV.instr(dst, cpu::Register::PC, cpu::Register::ZX));
         }
 
-        let v = LoadConstInstruction::instr_add(dst, targ_pos);
-        for inst in v {
+        let v = match comp.is_external(label_id) {
+            true => LoadConstInstruction::instr_add_literal(dst, targ_pos),
+            false => {
+                let words = comp.relax(LoadConstInstruction::instr_add(dst, targ_pos).len());
+                LoadConstInstruction::instr_add_min(dst, targ_pos, words)
+            }
+        };
+        let literal = v.len() - 1;
+        for (i, inst) in v.into_iter().enumerate() {
+            if i == literal {
+                comp.relocate(label_id);
+            }
             comp.instruct(inst);
         }
 
@@ -61,6 +71,7 @@ impl Atom for LoadLabelInstruction {
     fn compile(&self, ctx: &mut CompileContext) -> Result<(), CompileError> {
         let label_id = self.label.resolve(ctx)?;
         Self::instr(ctx.comp.as_mut(), self.dst, label_id)?;
+        ctx.flow.write(&ctx.status, self.dst);
 
         Ok(())
     }
@@ -0,0 +1,115 @@
+use crate::compile::label::LabelScope;
+use crate::compile::{Atom, AtomBox, CompileContext, CompileError};
+use crate::parser::ParsePosition;
+
+/// Nesting of expansions past which a macro is taken as endlessly recursive
+pub const MAX_DEPTH: usize = 64;
+/// Expansions allowed in a whole program
+pub const MAX_EXPANSIONS: usize = 65536;
+
+fn valid_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Macro defined by `@macro NAME param... { body }`, the body is kept as text
+/// and parsed on every expansion with `\param` replaced by the argument
+#[derive(Clone, Debug)]
+pub struct MacroDefinition {
+    pub name: String,
+    params: Vec<String>,
+    body: String,
+    /// Position of the first body character
+    pub pos: ParsePosition,
+    expansions: usize,
+}
+
+impl MacroDefinition {
+    pub fn parse(header: &str, body: String, pos: ParsePosition) -> Result<Self, CompileError> {
+        let mut words = header.split_whitespace().map(|w| w.to_uppercase());
+        let name = words
+            .next()
+            .ok_or_else(|| CompileError::InvalidMacro(String::new()))?;
+
+        if !valid_name(&name) {
+            return Err(CompileError::InvalidMacro(name));
+        }
+
+        let params: Vec<String> = words.collect();
+        for (i, param) in params.iter().enumerate() {
+            if !valid_name(param) || params[..i].contains(param) {
+                return Err(CompileError::InvalidMacro(param.to_owned()));
+            }
+        }
+
+        Ok(MacroDefinition {
+            name,
+            params,
+            body,
+            pos,
+            expansions: 0,
+        })
+    }
+
+    /// Body with parameters replaced by `args`, together with the name of the
+    /// label scope the expansion gets
+    pub fn expand(&mut self, args: &[&str]) -> Result<(String, String), CompileError> {
+        if args.len() != self.params.len() {
+            return Err(CompileError::MacroArguments(
+                self.name.clone(),
+                self.params.len(),
+                args.len(),
+            ));
+        }
+
+        let mut body = String::with_capacity(self.body.len());
+        let mut rest = self.body.as_str();
+        while let Some(idx) = rest.find('\\') {
+            body.push_str(&rest[..idx]);
+            rest = &rest[idx + 1..];
+
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            let word = rest[..end].to_uppercase();
+            match self.params.iter().position(|p| *p == word) {
+                Some(param) => {
+                    body.push_str(args[param]);
+                    rest = &rest[end..];
+                }
+                // Not a parameter, like escapes in char literals
+                None => body.push('\\'),
+            }
+        }
+        body.push_str(rest);
+
+        self.expansions += 1;
+        let scope = format!("{}{{{}}}", self.name, self.expansions - 1);
+        Ok((body, scope))
+    }
+}
+
+/// Atoms of one expansion, labels defined inside are local to it. Errors
+/// name the call site, which is the position of the enclosing atom
+#[derive(Debug)]
+pub struct MacroExpansion {
+    scope: LabelScope,
+}
+
+impl MacroExpansion {
+    pub fn new(scope: String, atoms: Vec<AtomBox>) -> Self {
+        MacroExpansion {
+            scope: LabelScope::new(scope, atoms),
+        }
+    }
+}
+
+impl Atom for MacroExpansion {
+    fn compile(&self, ctx: &mut CompileContext) -> Result<(), CompileError> {
+        let (call_site, _) = ctx.status.pos();
+        ctx.status.enter_expansion(call_site);
+        let res = self.scope.compile(ctx);
+        ctx.status.leave_expansion();
+        res
+    }
+}
@@ -2,7 +2,7 @@ use crate::compile::CompileError;
 use crate::parser::ParseParts;
 use crate::cpu;
 
-use crate::compile::{CompileContext, Atom};
+use crate::compile::{CompileContext, Atom, Warning};
 
 #[derive(Copy, Clone, Debug)]
 pub enum MemOperation {
@@ -106,6 +106,11 @@ impl MemInstruction {
         self
     }
 
+    /// Store to the halt register, as `HALT` assembles
+    pub fn is_halt(&self) -> bool {
+        matches!(self.op, MemOperation::STORE) && self.addr == cpu::Register::ZX && self.shift == -1
+    }
+
     pub fn parse_asm(
         op: MemOperation,
         flags: &str,
@@ -141,6 +146,16 @@ impl Atom for MemInstruction {
         }
 
         ctx.instruct(ins);
+
+        match self.op {
+            MemOperation::STORE if self.is_halt() => ctx.flow.halt(&ctx.status),
+            MemOperation::STORE => ctx.flow.instruction(&ctx.status, false),
+            MemOperation::LOAD if self.dst == cpu::Register::PC => {
+                ctx.status.report_warning(Warning::LoadPc);
+                ctx.flow.write(&ctx.status, self.dst);
+            }
+            _ => ctx.flow.write(&ctx.status, self.dst),
+        }
         Ok(())
     }
 }
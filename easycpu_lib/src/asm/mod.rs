@@ -1,4 +1,9 @@
-use crate::{compile::compile_program, parser::PosCompileError};
+use crate::{
+    compile::{compile_program, CompileError, CompiledProgram},
+    parser::{PosCompileError, SourceLoader, SourceSet},
+};
+
+pub use options::AsmOptions;
 
 pub mod alu;
 pub mod branch;
@@ -8,10 +13,50 @@ pub mod load_label;
 pub mod mem;
 pub mod pushpop;
 
+pub mod constant;
 pub mod custom;
+pub mod deferred;
+pub mod directive;
 pub mod disasm;
+pub mod linkage;
+pub mod listing;
+pub mod macros;
+pub mod options;
 pub mod parse;
 
-pub fn parse_and_compile(source: &str) -> Result<Vec<u16>, Vec<PosCompileError>> {
-    compile_program(parse::parse_listing(source).map_err(|x| vec![x])?)
+/// Drop the warnings `options` disable, they fail the program when
+/// warnings are errors
+fn check_warnings(
+    compiled: Result<CompiledProgram, Vec<PosCompileError>>,
+    options: &AsmOptions,
+) -> Result<CompiledProgram, Vec<PosCompileError>> {
+    let mut compiled = compiled?;
+    compiled.warnings.retain(|w| match &w.error {
+        CompileError::Warning(warning) => options.warning_enabled(warning),
+        _ => true,
+    });
+
+    if options.warnings_as_errors && !compiled.warnings.is_empty() {
+        return Err(compiled.warnings);
+    }
+    Ok(compiled)
+}
+
+pub fn parse_and_compile(
+    source: &str,
+    options: &AsmOptions,
+) -> Result<CompiledProgram, Vec<PosCompileError>> {
+    check_warnings(compile_program(parse::parse_listing(source, options)), options)
+}
+
+/// Assemble files as one program, `sources` receives every file read so
+/// error positions can be resolved
+pub fn assemble_files(
+    names: &[&str],
+    loader: Box<dyn SourceLoader>,
+    sources: &mut SourceSet,
+    options: &AsmOptions,
+) -> Result<CompiledProgram, Vec<PosCompileError>> {
+    let compiled = compile_program(parse::parse_files(names, loader, sources, options));
+    check_warnings(compiled, options)
 }
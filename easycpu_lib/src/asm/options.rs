@@ -0,0 +1,74 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::compile::{CompileError, Warning};
+use crate::parser::expr::{symbol_char, Expr, SymbolEnv};
+
+/// Settings of an assembly which do not come from the source
+#[derive(Clone, Debug, Default)]
+pub struct AsmOptions {
+    /// Names seen by `@if` conditions, the program gets them as constants
+    pub defines: BTreeMap<String, i64>,
+    /// Names of the warnings which are not reported
+    pub disabled_warnings: BTreeSet<String>,
+    /// Warnings fail the assembly like errors
+    pub warnings_as_errors: bool,
+}
+
+impl AsmOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn define(mut self, name: &str, value: i64) -> Self {
+        self.defines.insert(name.to_uppercase(), value);
+        self
+    }
+
+    /// Define from `NAME=value`, a bare `NAME` is defined as 1. The value may
+    /// refer to names defined before
+    pub fn define_str(self, define: &str) -> Result<Self, CompileError> {
+        let (name, value) = define.split_once('=').unwrap_or((define, "1"));
+        let name = name.trim();
+        let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(symbol_char);
+        if !valid {
+            return Err(CompileError::InvalidExpression(name.to_owned()));
+        }
+
+        let value = Expr::parse(&value.to_uppercase())?.eval(Some(&self))?;
+        Ok(self.define(name, value))
+    }
+
+    /// Apply a `-W` flag: a warning name enables it, `no-` and the name
+    /// disables it and `error` turns warnings into errors
+    pub fn warning(mut self, flag: &str) -> Result<Self, CompileError> {
+        let (enable, name) = match flag.strip_prefix("no-") {
+            Some(name) => (false, name),
+            None => (true, flag),
+        };
+
+        if name == "error" {
+            self.warnings_as_errors = enable;
+        } else if !Warning::NAMES.contains(&name) {
+            return Err(CompileError::UnknownWarning(flag.to_owned()));
+        } else if enable {
+            self.disabled_warnings.remove(name);
+        } else {
+            self.disabled_warnings.insert(name.to_owned());
+        }
+        Ok(self)
+    }
+
+    pub fn warning_enabled(&self, warning: &Warning) -> bool {
+        !self.disabled_warnings.contains(warning.name())
+    }
+}
+
+impl SymbolEnv for AsmOptions {
+    fn lookup(&self, name: &str) -> Result<i64, CompileError> {
+        self.defines
+            .get(name)
+            .copied()
+            .ok_or_else(|| CompileError::NotDefined(name.to_owned()))
+    }
+}
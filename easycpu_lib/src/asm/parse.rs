@@ -1,7 +1,13 @@
-use std::collections::VecDeque;
-use std::str::Chars;
+use std::cell::RefCell;
+use std::collections::HashMap;
 
+use super::constant::ConstantDefinition;
 use super::custom::{CustomInstruction, CustomMultiInstruction, NopInstruction};
+use super::deferred::DeferredAtom;
+use super::directive::Directive;
+use super::linkage::Linkage;
+use super::macros::{self, MacroDefinition, MacroExpansion};
+use super::options::AsmOptions;
 use super::pushpop::{PushPopInstruction, PushPopOperation};
 use crate::compile::label::LabelScope;
 use crate::compile::{AtomBox, CompileError, ErrorAtom, Label};
@@ -14,14 +20,43 @@ use crate::asm::load_const::{LoadConstInstruction, LoadConstOperation};
 use crate::asm::load_label::LoadLabelInstruction;
 use crate::asm::mem::{MemInstruction, MemOperation};
 use crate::cpu;
+use crate::parser::expr::{self, symbol_char, Expr, SymbolEnv};
 use crate::parser::parse::{end_checker, letter_checker, nummeric_checker};
-use crate::parser::position::{PosCompileError, PositionAtom};
-use crate::parser::{parse_parts, ParseParts, ParseReader};
+use crate::parser::parse_parts::split_operands;
+use crate::parser::position::{CompileErrorWithPos, PosCompileError, PositionAtom};
+use crate::parser::sources::resolve_include;
+use crate::parser::{NoLoader, ParsePosition, ParseParts, ParseReader, SourceLoader, SourceSet};
 use crate::stack::StackOptAtom;
 
-fn parse_instruction(s: String) -> Result<AtomBox, CompileError> {
-    let s = s.to_uppercase();
-    let mut parts: ParseParts = s.split_whitespace().collect::<VecDeque<&str>>().into();
+/// Uppercase a statement, char literals keep their case
+fn uppercase_code(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let (mut quoted, mut escaped) = (false, false);
+    for c in s.chars() {
+        if quoted {
+            quoted = escaped || c != '\'';
+            escaped = !escaped && c == '\\';
+            res.push(c);
+        } else {
+            quoted = c == '\'';
+            res.extend(c.to_uppercase());
+        }
+    }
+    res
+}
+
+/// Split `NAME = value` into its name and value
+fn split_assignment(s: &str) -> Option<(&str, &str)> {
+    let (name, value) = s.split_once('=')?;
+    let name = name.trim();
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(symbol_char);
+    valid.then_some((name, value))
+}
+
+fn parse_instruction(s: &str, env: Option<&dyn SymbolEnv>) -> Result<AtomBox, CompileError> {
+    let s = uppercase_code(s);
+    let mut parts = ParseParts::from(split_operands(&s)).with_env(env);
 
     let command_raw = parts.pop_command()?;
     let command_raw = match command_raw.split_once('.') {
@@ -88,6 +123,53 @@ fn parse_instruction(s: String) -> Result<AtomBox, CompileError> {
     Err(CompileError::UnknownCommand(String::from(command_pure)))
 }
 
+fn parse_directive(s: &str, env: Option<&dyn SymbolEnv>) -> Result<AtomBox, CompileError> {
+    let s = uppercase_code(s);
+    let mut parts = ParseParts::from(split_operands(&s)).with_env(env);
+
+    let name = parts.pop_command()?;
+    let name = name.strip_prefix('.').unwrap_or(name);
+    if name == "EXTERN" || name == "GLOBAL" {
+        return Ok(Box::new(Linkage::parse_asm(name, parts)?));
+    }
+    Ok(Box::new(Directive::parse_asm(name, parts)?))
+}
+
+fn parse_data(s: &str, env: Option<&dyn SymbolEnv>) -> Result<AtomBox, CompileError> {
+    let val = Expr::parse(&uppercase_code(s))?.eval(env)?;
+    Ok(Box::new(CustomInstruction::new(expr::to_u16(val)?)))
+}
+
+fn parse_statement(s: String) -> Result<AtomBox, CompileError> {
+    if let Some((name, value)) = split_assignment(&s) {
+        let (name, value) = (uppercase_code(name), uppercase_code(value));
+        let def = ConstantDefinition::parse_asm(&name, &value, true)?;
+        return Ok(Box::new(def));
+    }
+
+    DeferredAtom::parse(s, parse_instruction)
+}
+
+fn parse_directive_statement(s: String) -> Result<AtomBox, CompileError> {
+    let upper = uppercase_code(&s);
+    let (name, rest) = upper.split_once(char::is_whitespace).unwrap_or((&upper, ""));
+
+    if name == ".EQU" || name == ".SET" {
+        // Name is separated from the value by whitespace or a comma
+        let rest = rest.trim_start();
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == ',')
+            .unwrap_or(rest.len());
+        let value = rest[end..].trim_start();
+        let value = value.strip_prefix(',').unwrap_or(value);
+
+        let def = ConstantDefinition::parse_asm(&rest[..end], value, name == ".SET")?;
+        return Ok(Box::new(def));
+    }
+
+    DeferredAtom::parse(s, parse_directive)
+}
+
 enum Modifier {
     Scope,
     StackOpt,
@@ -102,14 +184,83 @@ impl Modifier {
     }
 }
 
+/// State shared by every parser of a program: files read, the chain of files
+/// currently being parsed and the macros defined so far
+struct ParseState {
+    sources: SourceSet,
+    loader: Box<dyn SourceLoader>,
+    active: Vec<usize>,
+    options: AsmOptions,
+
+    macros: HashMap<String, MacroDefinition>,
+    depth: usize,
+    expansions: usize,
+}
+
+impl ParseState {
+    fn new(
+        sources: SourceSet,
+        loader: Box<dyn SourceLoader>,
+        options: &AsmOptions,
+    ) -> RefCell<ParseState> {
+        RefCell::new(ParseState {
+            sources,
+            loader,
+            active: Vec::new(),
+            options: options.clone(),
+            macros: HashMap::new(),
+            depth: 0,
+            expansions: 0,
+        })
+    }
+
+    fn load(&mut self, name: &str) -> Result<usize, CompileError> {
+        if let Some(file) = self.sources.find(name) {
+            if let Some(start) = self.active.iter().position(|active| *active == file) {
+                let chain = self.active[start..]
+                    .iter()
+                    .chain([&file])
+                    .filter_map(|file| self.sources.get(*file))
+                    .map(|source| source.name.clone())
+                    .collect();
+                return Err(CompileError::IncludeCycle(chain));
+            }
+            return Ok(file);
+        }
+
+        let text = self
+            .loader
+            .load(name)
+            .map_err(|e| CompileError::IncludeFailed(format!("{}: {}", name, e)))?;
+        Ok(self.sources.add(name.to_owned(), text))
+    }
+}
+
+/// Open `@if` block
+struct Conditional {
+    /// Lines of the current branch are assembled
+    active: bool,
+    /// A branch was taken already, or the block is inside a skipped branch
+    done: bool,
+    has_else: bool,
+    pos: ParsePosition,
+}
+
 struct AsmParse<'a> {
     reader: ParseReader<'a>,
+    state: &'a RefCell<ParseState>,
     atoms: Vec<AtomBox>,
     modifier: Modifier,
+    conditionals: Vec<Conditional>,
+
+    // Name for a block following a label, anonymous blocks are numbered
+    last_label: Option<String>,
+    anon_scopes: usize,
 }
 
 enum AsmStartToken {
     Letter,
+    Directive,
     Comment,
     Number,
     String,
@@ -125,11 +276,13 @@ impl<'a> AsmParse<'a> {
         let cur = self.reader.peek()?;
         if letter_checker(cur) {
             Ok(AsmStartToken::Letter)
+        } else if cur == '.' {
+            Ok(AsmStartToken::Directive)
         } else if cur == '#' {
             Ok(AsmStartToken::Comment)
         } else if cur == '@' {
             Ok(AsmStartToken::Modifier)
-        } else if nummeric_checker(cur) {
+        } else if nummeric_checker(cur) || cur == '\'' {
             Ok(AsmStartToken::Number)
         } else if cur == '"' {
             Ok(AsmStartToken::String)
@@ -151,32 +304,249 @@ impl<'a> AsmParse<'a> {
         let mut parser = ParseReader::from(&mut combined);
         parser.pos = start_pos;
 
-        let mut subparser = AsmParse {
-            reader: parser,
-            atoms: Vec::new(),
-            modifier: Modifier::Scope,
+        Ok(AsmParse::new(parser, self.state).atoms())
+    }
+
+    /// Atoms of the file named by `@include "name"`
+    fn parse_include(&mut self) -> Result<Result<Vec<AtomBox>, CompileError>, PosCompileError> {
+        while self.reader.peek()? != '\n' && self.reader.peek()?.is_whitespace() {
+            self.reader.take()?;
+        }
+        if self.reader.peek()? != '"' {
+            return Ok(Err(CompileError::UnexpectedToken(self.reader.take()?)));
+        }
+        let name: String = self.reader.take_block()?.into_iter().collect();
+
+        let file = {
+            let mut state = self.state.borrow_mut();
+            let from = &state.sources.get(self.reader.pos.file).unwrap().name;
+            let path = resolve_include(from, &name);
+            state.load(&path)
+        };
+
+        Ok(file.map(|file| parse_file(self.state, file)))
+    }
+
+    fn skipping(&self) -> bool {
+        self.conditionals.last().is_some_and(|c| !c.active)
+    }
+
+    /// Value of the condition up to the end of the line, it may only refer to defines
+    fn condition(&mut self, modi: &str) -> Result<Result<bool, CompileError>, PosCompileError> {
+        let text = self.reader.read_until(|cur, _| matches!(cur, '\n' | '#' | ';'))?;
+        let text = uppercase_code(String::from_iter(text).trim());
+        let state = self.state.borrow();
+
+        if modi == "if" || modi == "elif" {
+            let value = Expr::parse(&text).and_then(|expr| expr.eval(Some(&state.options)));
+            return Ok(value.map(|value| value != 0));
+        }
+
+        let name = text.trim();
+        if name.is_empty() || !name.chars().all(symbol_char) {
+            return Ok(Err(CompileError::InvalidExpression(name.to_owned())));
+        }
+        let defined = state.options.defines.contains_key(name);
+        Ok(Ok(defined == (modi == "ifdef")))
+    }
+
+    /// Track `@if`, `@ifdef`, `@ifndef`, `@elif`, `@else` and `@endif`
+    fn parse_conditional(
+        &mut self,
+        modi: &str,
+        pos: ParsePosition,
+    ) -> Result<Result<(), CompileError>, PosCompileError> {
+        let unmatched = || Err(CompileError::UnmatchedConditional(format!("@{}", modi)));
+
+        match modi {
+            "if" | "ifdef" | "ifndef" => {
+                let skipping = self.skipping();
+                let res = if skipping {
+                    self.reader.read_until(|cur, _| cur == '\n')?;
+                    Ok(false)
+                } else {
+                    self.condition(modi)?
+                };
+
+                // Blocks with an invalid condition are skipped as a whole
+                let active = matches!(res, Ok(true));
+                self.conditionals.push(Conditional {
+                    active,
+                    done: active || skipping || res.is_err(),
+                    has_else: false,
+                    pos,
+                });
+                Ok(res.map(|_| ()))
+            }
+            "elif" => {
+                let Some(top) = self.conditionals.last() else {
+                    return Ok(unmatched());
+                };
+                if top.has_else {
+                    return Ok(unmatched());
+                }
+                if top.done {
+                    self.reader.read_until(|cur, _| cur == '\n')?;
+                    self.conditionals.last_mut().unwrap().active = false;
+                    return Ok(Ok(()));
+                }
+
+                let res = self.condition(modi)?;
+                let top = self.conditionals.last_mut().unwrap();
+                top.active = matches!(res, Ok(true));
+                top.done = top.active || res.is_err();
+                Ok(res.map(|_| ()))
+            }
+            "else" => match self.conditionals.last_mut() {
+                Some(top) if !top.has_else => {
+                    top.has_else = true;
+                    top.active = !top.done;
+                    top.done = true;
+                    Ok(Ok(()))
+                }
+                _ => Ok(unmatched()),
+            },
+            _ => match self.conditionals.pop() {
+                Some(_) => Ok(Ok(())),
+                None => Ok(unmatched()),
+            },
+        }
+    }
+
+    /// Definition following `@macro`, the body is parsed when it is expanded
+    fn parse_macro(&mut self) -> Result<Result<(), CompileError>, PosCompileError> {
+        let header = self.reader.read_until(|cur, _| matches!(cur, '{' | '#' | ';'))?;
+        let header: String = header.into_iter().collect();
+        if self.reader.peek()? != '{' {
+            return Ok(Err(CompileError::UnexpectedToken(self.reader.take()?)));
+        }
+
+        let mut pos = self.reader.pos;
+        pos.next('{');
+        let body: String = self.reader.take_block()?.into_iter().collect();
+
+        let def = match MacroDefinition::parse(&header, body, pos) {
+            Ok(def) => def,
+            Err(err) => return Ok(Err(err)),
         };
+        let mut state = self.state.borrow_mut();
+        if state.macros.contains_key(&def.name) {
+            return Ok(Err(CompileError::MacroRedefined(def.name)));
+        }
+        state.macros.insert(def.name.clone(), def);
+        Ok(Ok(()))
+    }
+
+    /// Expansion of `statement` when it calls a macro
+    fn expand_macro(
+        &mut self,
+        statement: &str,
+    ) -> Result<Option<Result<AtomBox, CompileError>>, PosCompileError> {
+        let mut args = split_operands(statement);
+        let Some(name) = args.pop_front().map(|name| name.to_uppercase()) else {
+            return Ok(None);
+        };
+        let args: Vec<&str> = args.into_iter().collect();
 
-        subparser.parse()?;
-        Ok(subparser.atoms)
+        let (body, scope, pos) = {
+            let mut state = self.state.borrow_mut();
+            let state = &mut *state;
+            let Some(def) = state.macros.get_mut(&name) else {
+                return Ok(None);
+            };
+
+            if state.depth >= macros::MAX_DEPTH {
+                return Ok(Some(Err(CompileError::MacroTooDeep(name))));
+            }
+            if state.expansions >= macros::MAX_EXPANSIONS {
+                return Ok(Some(Err(CompileError::TooManyExpansions)));
+            }
+            let (body, scope) = match def.expand(&args) {
+                Ok(res) => res,
+                Err(err) => return Ok(Some(Err(err))),
+            };
+            state.depth += 1;
+            state.expansions += 1;
+            (body, scope, def.pos)
+        };
+
+        let mut chars = body.chars();
+        let mut reader = ParseReader::from(&mut chars);
+        reader.pos = pos;
+        let atoms = AsmParse::new(reader, self.state).atoms();
+        self.state.borrow_mut().depth -= 1;
+
+        Ok(Some(Ok(Box::new(MacroExpansion::new(scope, atoms)))))
+    }
+
+    /// Read a statement, or a single operand when `operand` is set. Parentheses
+    /// not starting a `$` block and char literals are part of the operands
+    fn read_statement(&mut self, operand: bool) -> Result<String, PosCompileError> {
+        let mut res = String::new();
+        let (mut depth, mut quoted, mut escaped) = (0, false, false);
+        let mut prev = '\0';
+
+        while !self.reader.is_empty() {
+            let cur = self.reader.peek()?;
+            if quoted {
+                quoted = escaped || cur != '\'';
+                escaped = !escaped && cur == '\\';
+            } else {
+                if prev == ':' && depth == 0 && !operand {
+                    break;
+                }
+                match cur {
+                    ';' | '#' | '\n' => break,
+                    '(' if depth == 0 && self.reader.peek_significant(1) == Some('$') => break,
+                    '(' => depth += 1,
+                    ')' if depth > 0 => depth -= 1,
+                    '\'' => quoted = true,
+                    c if c.is_whitespace() && depth == 0 && operand => break,
+                    _ => (),
+                }
+            }
+
+            prev = cur;
+            res.push(self.reader.take()?);
+        }
+
+        Ok(res)
     }
 
     fn parse_atom(&mut self) -> Result<(), PosCompileError> {
         let start_pos = self.reader.pos;
-        let atom = match self.peek_start_token()? {
+        let mut label = None;
+        let token = self.peek_start_token()?;
+
+        // Skipped branches are only searched for conditionals
+        if self.skipping() && !matches!(token, AsmStartToken::Modifier) {
+            if self.reader.peek()?.is_whitespace() {
+                self.reader.take()?;
+            } else {
+                self.reader.read_until(|cur, _| cur == '\n')?;
+            }
+            return Ok(());
+        }
+
+        let atom = match token {
             AsmStartToken::Letter => {
-                let collected: Vec<char> = self
-                    .reader
-                    .read_until(|cur, prev| prev == ':' || end_checker(cur))?;
-                let collected: String = collected.into_iter().collect();
+                let collected = self.read_statement(false)?;
 
                 Some(if let Some(pure_label) = collected.strip_suffix(':') {
+                    label = Some(pure_label.to_owned());
                     Ok(Box::new(Label::new(pure_label.to_owned())) as AtomBox)
+                } else if let Some(expansion) = self.expand_macro(&collected)? {
+                    expansion
                 } else {
-                    parse_instruction(collected)
+                    parse_statement(collected)
                 })
             }
 
+            AsmStartToken::Directive => {
+                let collected = self.read_statement(false)?;
+                Some(parse_directive_statement(collected))
+            }
+
             AsmStartToken::Comment => {
                 self.reader.read_until(|cur, _| cur == '\n')?;
                 None
@@ -190,7 +560,22 @@ impl<'a> AsmParse<'a> {
                 let modi: String = modi.into_iter().collect();
                 let modi = modi.to_lowercase();
 
-                if let Some(modifier) = Modifier::from_name(&modi) {
+                if matches!(modi.as_str(), "if" | "ifdef" | "ifndef" | "elif" | "else" | "endif") {
+                    self.parse_conditional(&modi, start_pos)?.err().map(Err)
+                } else if self.skipping() {
+                    self.reader.read_until(|cur, _| cur == '\n')?;
+                    None
+                } else if modi == "macro" {
+                    self.parse_macro()?.err().map(Err)
+                } else if modi == "include" {
+                    match self.parse_include()? {
+                        Ok(mut atoms) => {
+                            self.atoms.append(&mut atoms);
+                            None
+                        }
+                        Err(err) => Some(Err(err)),
+                    }
+                } else if let Some(modifier) = Modifier::from_name(&modi) {
                     self.modifier = modifier;
                     None
                 } else {
@@ -199,15 +584,8 @@ impl<'a> AsmParse<'a> {
             }
 
             AsmStartToken::Number => {
-                let collected: Vec<char> = self
-                    .reader
-                    .read_until(|cur, _| cur.is_whitespace() || end_checker(cur))?;
-
-                let collected: String = collected.into_iter().collect();
-                Some(
-                    parse_parts::parse_u16_constant(collected.to_uppercase().as_str())
-                        .map(|val| Box::new(CustomInstruction::new(val)) as AtomBox),
-                )
+                let collected = self.read_statement(true)?;
+                Some(DeferredAtom::parse(collected, parse_data))
             }
 
             AsmStartToken::String => {
@@ -217,11 +595,20 @@ impl<'a> AsmParse<'a> {
                 Some(Ok(ins as AtomBox))
             }
 
+            AsmStartToken::CurlyBracket if self.reader.peek()? == '}' => {
+                self.reader.take()?;
+                Some(Err(CompileError::UnmatchedClosingBracket))
+            }
+
             AsmStartToken::CurlyBracket => {
                 let block = self.take_parse_block()?;
+                let name = self.last_label.take().unwrap_or_else(|| {
+                    self.anon_scopes += 1;
+                    format!("{{{}}}", self.anon_scopes - 1)
+                });
                 let atom: AtomBox = match self.modifier {
-                    Modifier::Scope => Box::new(LabelScope::new(block)),
-                    Modifier::StackOpt => Box::new(StackOptAtom::new(block)),
+                    Modifier::Scope => Box::new(LabelScope::new(name, block)),
+                    Modifier::StackOpt => Box::new(StackOptAtom::new(name, block)),
                 };
                 self.modifier = Modifier::Scope;
                 Some(Ok(atom))
@@ -247,6 +634,7 @@ impl<'a> AsmParse<'a> {
         };
 
         if let Some(atom) = atom {
+            self.last_label = label;
             let atom = match atom {
                 Ok(atom) => atom,
                 Err(err) => Box::new(ErrorAtom::from(err)),
@@ -262,33 +650,109 @@ impl<'a> AsmParse<'a> {
         Ok(())
     }
 
-    pub fn parse(&mut self) -> Result<(), PosCompileError> {
+    /// Error left in place of the atom being parsed, it is reported when compiling
+    fn push_error(&mut self, err: PosCompileError) {
+        let atom = Box::new(ErrorAtom::from(err.error));
+        self.atoms.push(Box::new(PositionAtom::new(atom, err.start_pos, err.end_pos)));
+    }
+
+    /// Parse up to the end, after a malformed atom the rest of its line is skipped
+    pub fn parse(&mut self) {
         while !self.reader.is_empty() {
-            self.parse_atom()?;
+            if let Err(err) = self.parse_atom() {
+                self.push_error(err);
+                let _ = self.reader.read_until(|cur, _| cur == '\n');
+            }
         }
 
-        Ok(())
+        for open in std::mem::take(&mut self.conditionals) {
+            self.push_error(CompileError::UnterminatedConditional.with_pos(open.pos));
+        }
     }
 
-    pub fn atoms(mut self) -> Result<Vec<AtomBox>, PosCompileError> {
-        self.parse()?;
-        Ok(self.atoms)
+    pub fn atoms(mut self) -> Vec<AtomBox> {
+        self.parse();
+        self.atoms
     }
 }
 
-impl<'a> From<&'a mut Chars<'a>> for AsmParse<'a> {
-    fn from(c: &'a mut Chars<'a>) -> Self {
-        let a: AsmParse = AsmParse {
-            reader: ParseReader::from(c),
+impl<'a> AsmParse<'a> {
+    fn new(reader: ParseReader<'a>, state: &'a RefCell<ParseState>) -> Self {
+        AsmParse {
+            reader,
+            state,
             atoms: Vec::new(),
             modifier: Modifier::Scope,
-        };
-        a
+            conditionals: Vec::new(),
+            last_label: None,
+            anon_scopes: 0,
+        }
     }
 }
 
-pub fn parse_listing(inp: &str) -> Result<Vec<AtomBox>, PosCompileError> {
-    let mut c = inp.chars();
-    let parser = AsmParse::from(&mut c);
-    parser.atoms()
+fn parse_file(state: &RefCell<ParseState>, file: usize) -> Vec<AtomBox> {
+    let text = state.borrow().sources.get(file).unwrap().text.clone();
+    let mut chars = text.chars();
+    let mut reader = ParseReader::from(&mut chars);
+    reader.pos.file = file;
+
+    state.borrow_mut().active.push(file);
+    let atoms = AsmParse::new(reader, state).atoms();
+    state.borrow_mut().active.pop();
+    atoms
+}
+
+/// Constants for the defines of `options`, placed before the program
+fn define_atoms(options: &AsmOptions) -> Vec<AtomBox> {
+    // Defines do not come from a file, the position points nowhere
+    let nowhere = ParsePosition {
+        file: usize::MAX,
+        ..Default::default()
+    };
+
+    let define = |(name, value): (&String, &i64)| -> AtomBox {
+        let def = ConstantDefinition::new(name.to_owned(), Expr::Num(*value), false);
+        Box::new(PositionAtom::new(Box::new(def), nowhere, nowhere))
+    };
+    options.defines.iter().map(define).collect()
+}
+
+/// Atoms of a single source, malformed parts are left as atoms failing to
+/// compile so every error is reported at once
+pub fn parse_listing(inp: &str, options: &AsmOptions) -> Vec<AtomBox> {
+    let state = ParseState::new(SourceSet::single("<input>", inp), Box::new(NoLoader), options);
+    let mut atoms = define_atoms(options);
+    atoms.append(&mut parse_file(&state, 0));
+    atoms
+}
+
+/// Parse files read through `loader` one after another, `sources` receives
+/// every file read including the ones pulled in by `@include`
+pub fn parse_files(
+    names: &[&str],
+    loader: Box<dyn SourceLoader>,
+    sources: &mut SourceSet,
+    options: &AsmOptions,
+) -> Vec<AtomBox> {
+    let state = ParseState::new(SourceSet::new(), loader, options);
+    // Files which can not be read are not part of the set, the position points nowhere
+    let nowhere = ParsePosition {
+        file: usize::MAX,
+        ..Default::default()
+    };
+
+    let mut atoms = define_atoms(options);
+    for name in names {
+        let file = state.borrow_mut().load(name);
+        match file {
+            Ok(file) => atoms.append(&mut parse_file(&state, file)),
+            Err(err) => {
+                let atom = Box::new(ErrorAtom::from(err));
+                atoms.push(Box::new(PositionAtom::new(atom, nowhere, nowhere)));
+            }
+        }
+    }
+
+    *sources = state.into_inner().sources;
+    atoms
 }
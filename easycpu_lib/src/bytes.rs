@@ -0,0 +1,108 @@
+//! Big-endian encoding of the executable and snapshot files
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[derive(Debug)]
+pub(crate) enum ReadError {
+    Truncated,
+    NotUtf8,
+}
+
+pub(crate) struct Writer(pub Vec<u8>);
+
+impl Writer {
+    pub fn u8(&mut self, val: u8) {
+        self.0.push(val);
+    }
+
+    pub fn u16(&mut self, val: u16) {
+        self.0.extend(val.to_be_bytes());
+    }
+
+    pub fn u32(&mut self, val: u32) {
+        self.0.extend(val.to_be_bytes());
+    }
+
+    pub fn u64(&mut self, val: u64) {
+        self.0.extend(val.to_be_bytes());
+    }
+
+    pub fn u128(&mut self, val: u128) {
+        self.0.extend(val.to_be_bytes());
+    }
+
+    /// Bytes prefixed with their 32 bit length
+    pub fn blob(&mut self, val: &[u8]) {
+        self.u32(val.len() as u32);
+        self.0.extend(val);
+    }
+
+    pub fn str(&mut self, val: &str) {
+        self.u16(val.len() as u16);
+        self.0.extend(val.as_bytes());
+    }
+}
+
+pub(crate) struct Reader<'a> {
+    pub data: &'a [u8],
+    pub at: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn is_empty(&self) -> bool {
+        self.at == self.data.len()
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], ReadError> {
+        let bytes = self
+            .data
+            .get(self.at..self.at.saturating_add(len))
+            .ok_or(ReadError::Truncated)?;
+        self.at += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ReadError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    pub fn u8(&mut self) -> Result<u8, ReadError> {
+        Ok(u8::from_be_bytes(self.array()?))
+    }
+
+    pub fn u16(&mut self) -> Result<u16, ReadError> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, ReadError> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, ReadError> {
+        Ok(u64::from_be_bytes(self.array()?))
+    }
+
+    pub fn u128(&mut self) -> Result<u128, ReadError> {
+        Ok(u128::from_be_bytes(self.array()?))
+    }
+
+    pub fn blob(&mut self) -> Result<&'a [u8], ReadError> {
+        let len = self.u32()? as usize;
+        self.bytes(len)
+    }
+
+    pub fn str(&mut self) -> Result<String, ReadError> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|_| ReadError::NotUtf8)
+    }
+}
@@ -1,18 +1,43 @@
-use std::{rc::Rc, slice};
+use std::{collections::HashSet, mem, rc::Rc, slice};
 
-use crate::{cpu, stack::{compile_stackop, StackOperation}, AsAny};
+use crate::{cpu, parser::ParsePosition, stack::{compile_stackop, StackOperation}, AsAny};
 
-use super::{status::ContextStatus, CompileError};
+use super::{layout::Layout, sourcemap::SourceMap, status::ContextStatus, CompileError};
 
 pub trait CompContext: AsAny {
     fn instruct(&mut self, instruction: cpu::Instruction);
     fn emit_new_label(&mut self) -> usize;
     fn emit_label(&mut self, id: usize) -> Result<(), CompileError>;
     fn resolve_label(&mut self, label_id: usize) -> Result<u16, CompileError>;
+    fn current_pc(&mut self) -> Result<u16, CompileError>;
 
     fn stack(&mut self, op: Box<dyn StackOperation>);
 
     fn reset(&mut self) {}
+
+    /// Label defined in another object, it resolves as if placed at address 0
+    fn emit_external_label(&mut self) -> Result<usize, CompileError> {
+        Err(CompileError::InstructionInStackopt)
+    }
+
+    fn is_external(&mut self, _label_id: usize) -> bool {
+        false
+    }
+
+    /// Mark the next word as an offset to `label_id` which the linker completes
+    fn relocate(&mut self, _label_id: usize) {}
+
+    /// Words of an instruction reaching a label, never fewer than in an
+    /// earlier pass so the layout settles
+    fn relax(&mut self, words: usize) -> usize {
+        words
+    }
+
+    /// Jump uses a branch, never again once it did not. May be true for a
+    /// jump not reaching while labels are placed, its branch is wrong then
+    fn relax_jump(&mut self, short: bool) -> bool {
+        short
+    }
 }
 
 #[derive(Default)]
@@ -34,6 +59,10 @@ impl CompContext for NullCompContext {
     fn resolve_label(&mut self, _: usize) -> Result<u16, CompileError> {
         panic!("Attempt to resolve label in null context");
     }
+
+    fn current_pc(&mut self) -> Result<u16, CompileError> {
+        panic!("Attempt to read pc of null context");
+    }
     
     fn stack(&mut self, _: Box<dyn StackOperation>) {
         panic!("Attempt to stack in null context");
@@ -42,9 +71,20 @@ impl CompContext for NullCompContext {
 
 pub struct MainCompContext {
     current_pc: u16,
+    // Word at 0xffff was emitted, there is no room for more
+    full: bool,
+    // Words past the end were reported for this pass
+    overflowed: bool,
     instructions: Vec<cpu::Instruction>,
+    source_map: SourceMap,
+    // Map of the pass before, to find code which still changes size
+    previous: SourceMap,
 
     label_pos: Vec<u16>,
+    external: HashSet<usize>,
+    // Address of each word referring to an external label with the label id
+    relocations: Vec<(u16, usize)>,
+    layout: Layout,
     status: Rc<ContextStatus>,
 }
 
@@ -52,8 +92,15 @@ impl MainCompContext {
     pub fn new(status: Rc<ContextStatus>) -> Self {
         MainCompContext {
             current_pc: 0,
+            full: false,
+            overflowed: false,
             instructions: Vec::new(),
+            source_map: SourceMap::new(),
+            previous: SourceMap::new(),
             label_pos: Vec::new(),
+            external: HashSet::new(),
+            relocations: Vec::new(),
+            layout: Layout::default(),
             status,
         }
     }
@@ -61,17 +108,59 @@ impl MainCompContext {
     pub fn iter_instructions(&self) -> slice::Iter<'_, cpu::Instruction> {
         self.instructions.iter()
     }
+
+    pub fn label_address(&self, id: usize) -> Option<u16> {
+        self.label_pos.get(id).copied()
+    }
+
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    pub fn relocations(&self) -> &[(u16, usize)] {
+        &self.relocations
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    pub fn label_addresses(&self) -> &[u16] {
+        &self.label_pos
+    }
+
+    /// Atom emitting the first word which moved since the pass before
+    pub fn resized(&self) -> Option<(ParsePosition, ParsePosition)> {
+        let changed = self
+            .previous
+            .iter()
+            .zip(self.source_map.iter())
+            .position(|(prev, cur)| prev != cur)
+            .unwrap_or(self.previous.len().min(self.source_map.len()));
+        // Word before the first change belongs to the atom which grew or shrank
+        self.source_map.iter().nth(changed.saturating_sub(1)).copied()
+    }
 }
 
 impl CompContext for MainCompContext {
     fn instruct(&mut self, instruction: cpu::Instruction) {
+        if self.full {
+            if !mem::replace(&mut self.overflowed, true) {
+                self.status.report_err(CompileError::ProgramTooLarge);
+            }
+            return;
+        }
         self.instructions.push(instruction);
-        self.current_pc += 1;
+        self.source_map.push(self.status.pos());
+        match self.current_pc.checked_add(1) {
+            Some(pc) => self.current_pc = pc,
+            None => self.full = true,
+        }
     }
 
     fn emit_new_label(&mut self) -> usize {
         let id = self.label_pos.len();
-        self.label_pos.push(self.current_pc.wrapping_add(8));
+        self.label_pos.push(self.current_pc);
         self.status.recompile();
         id
     }
@@ -87,19 +176,60 @@ impl CompContext for MainCompContext {
     }
 
     fn resolve_label(&mut self, label_id: usize) -> Result<u16, CompileError> {
+        // Placeholder measured as if the label was right here
         if label_id == usize::MAX {
-            return Ok(self.current_pc.wrapping_add(8));
+            return Ok(0);
         }
 
         let label_pos = self.label_pos[label_id];
         Ok(label_pos.wrapping_sub(self.current_pc))
     }
 
+    fn current_pc(&mut self) -> Result<u16, CompileError> {
+        Ok(self.current_pc)
+    }
+
     fn reset(&mut self) {
         self.current_pc = 0;
-        self.instructions.clear()
+        self.full = false;
+        self.overflowed = false;
+        self.instructions.clear();
+        self.previous = mem::take(&mut self.source_map);
+        self.relocations.clear();
+        self.layout.start_pass();
+    }
+
+    fn emit_external_label(&mut self) -> Result<usize, CompileError> {
+        let id = self.label_pos.len();
+        self.label_pos.push(0);
+        self.external.insert(id);
+        self.status.recompile();
+        Ok(id)
+    }
+
+    fn is_external(&mut self, label_id: usize) -> bool {
+        self.external.contains(&label_id)
+    }
+
+    fn relocate(&mut self, label_id: usize) {
+        if self.external.contains(&label_id) {
+            self.relocations.push((self.current_pc, label_id));
+        }
     }
     
+    fn relax(&mut self, words: usize) -> usize {
+        self.layout.relax(self.status.atom(), words)
+    }
+
+    fn relax_jump(&mut self, short: bool) -> bool {
+        let relaxed = self.layout.relax_jump(self.status.atom(), short);
+        if relaxed && !short {
+            // Placed as short, the code of this pass is not usable
+            self.status.recompile();
+        }
+        relaxed
+    }
+
     fn stack(&mut self, op: Box<dyn StackOperation>) {
         let ctx = self as &mut dyn CompContext;
         if let Err(error) = compile_stackop(ctx, op) {
@@ -1,13 +1,52 @@
+use std::collections::HashMap;
+
 use super::{
     comp::MainCompContext,
+    context::Constant,
+    label::report_unused,
+    layout::LayoutStats,
+    lint::FlowLint,
+    sourcemap::SourceMap,
+    symbols::{Symbol, SymbolTable},
     AtomBox, CompileContext, CompileError,
 };
+use crate::link::Relocation;
 use crate::parser::{CompileErrorWithPos, ParsePosition, PosCompileError};
 
-pub fn compile_program(program: Vec<AtomBox>) -> Result<Vec<u16>, Vec<PosCompileError>> {
+#[derive(Clone, Debug, Default)]
+pub struct CompiledProgram {
+    pub code: Vec<u16>,
+    /// Position of the atom each word of `code` was emitted by
+    pub source_map: SourceMap,
+    pub symbols: SymbolTable,
+    /// Labels declared `.global`, for linking
+    pub exports: SymbolTable,
+    /// Words the linker has to fix up for labels declared `.extern`
+    pub relocations: Vec<Relocation>,
+    /// Absolute addresses were used, the code can only be placed at address 0
+    pub position_dependent: bool,
+    /// Findings of the lints, as `CompileError::Warning`
+    pub warnings: Vec<PosCompileError>,
+    pub layout: LayoutStats,
+}
+
+/// Compiles the atoms over and over until label addresses stop moving.
+///
+/// Instructions sized by label addresses only grow between passes, see
+/// `Layout`, so they settle on their own. Directives sized by labels, like
+/// `.space` or `.org`, may shrink and keep moving the labels. Once they bring
+/// the labels back to an earlier pass without any instruction growing the
+/// passes would repeat, which is reported right away. The limit is left for
+/// labels moving further on every pass.
+pub fn compile_program(program: Vec<AtomBox>) -> Result<CompiledProgram, Vec<PosCompileError>> {
     let mut attempts_left = 1024;
+    let mut stuck = false;
+    // Label addresses and constants of the passes since instructions last grew
+    let mut states: Vec<(Vec<u16>, HashMap<String, Constant>)> = Vec::new();
 
     let mut ctx = CompileContext::new();
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
 
     while attempts_left > 0 {
         if !ctx.status.reset() {
@@ -15,33 +54,104 @@ pub fn compile_program(program: Vec<AtomBox>) -> Result<Vec<u16>, Vec<PosCompile
         }
 
         ctx.comp.reset();
+        ctx.labels.clear();
+        ctx.imports.clear();
+        ctx.exports.clear();
+        ctx.position_dependent = false;
+        ctx.flow = FlowLint::default();
 
         for atom in program.iter() {
             if let Err(e) = atom.compile(&mut ctx) {
                 ctx.status.report_err(e);
             }
         }
+        ctx.flow.finish(&ctx.status);
+        report_unused(&ctx);
 
-        let errors = ctx.status.take_errors();
-        if !errors.is_empty() {
-            return Err(errors);
-        }
+        // Passes go on after errors so the ones only found once labels are
+        // known are reported as well, earlier passes may see placeholders
+        errors = ctx.status.take_errors();
+        warnings = ctx.status.take_warnings();
 
         ctx.named_resolver.finish();
+        ctx.finish_pass();
 
         attempts_left -= 1;
+
+        let comp = ctx.comp.as_any()
+            .downcast_ref::<MainCompContext>()
+            .expect("Not a mian inst context");
+        if comp.layout().grew() {
+            states.clear();
+        }
+        let state = (comp.label_addresses().to_vec(), ctx.prev_constants.clone());
+        if states.last() != Some(&state) && states.contains(&state) {
+            stuck = true;
+            break;
+        }
+        states.push(state);
     }
-    if attempts_left == 0 {
-        return Err(vec![
-            CompileError::TooManyAttempts.with_pos(ParsePosition::default())
-        ]);
+    if !errors.is_empty() {
+        return Err(errors);
     }
 
-    ctx.comp.as_any()
+    let comp = ctx.comp.as_any()
         .downcast_ref::<MainCompContext>()
-        .expect("Not a mian inst context")
+        .expect("Not a mian inst context");
+
+    if attempts_left == 0 || stuck {
+        let (start, end) = comp.resized().unwrap_or_default();
+        return Err(vec![CompileError::TooManyAttempts.with_range(start, end)]);
+    }
+
+    let code = comp
         .iter_instructions()
         .map(|x| x.encode())
         .collect::<Result<Vec<u16>, _>>()
-        .map_err(|x| vec![CompileError::InvalidInstruction(x).with_pos(ParsePosition::default())])
+        .map_err(|x| vec![CompileError::InvalidInstruction(x).with_pos(ParsePosition::default())])?;
+
+    let mut symbols = SymbolTable::new();
+    for (scope, name, id) in ctx.labels.iter() {
+        if let Some(addr) = comp.label_address(*id) {
+            symbols.push(Symbol {
+                name: name.clone(),
+                scope: scope.clone(),
+                addr,
+            });
+        }
+    }
+
+    let mut exports = SymbolTable::new();
+    for (name, id) in ctx.exports.iter() {
+        if let Some(addr) = comp.label_address(*id) {
+            exports.push(Symbol {
+                name: name.clone(),
+                scope: Vec::new(),
+                addr,
+            });
+        }
+    }
+
+    let relocations = comp
+        .relocations()
+        .iter()
+        .filter_map(|(offset, id)| {
+            let (symbol, _) = ctx.imports.iter().find(|(_, import)| import == id)?;
+            Some(Relocation {
+                offset: *offset,
+                symbol: symbol.clone(),
+            })
+        })
+        .collect();
+
+    Ok(CompiledProgram {
+        code,
+        source_map: comp.source_map().clone(),
+        symbols,
+        exports,
+        relocations,
+        position_dependent: ctx.position_dependent,
+        warnings,
+        layout: comp.layout().stats(),
+    })
 }
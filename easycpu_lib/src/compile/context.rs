@@ -1,10 +1,11 @@
-use std::rc::Rc;
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use crate::cpu;
+use crate::{cpu, parser::expr::SymbolEnv};
 
 use super::{
     comp::{CompContext, MainCompContext},
     label::LabelResolver,
+    lint::FlowLint,
     status::ContextStatus,
     CompileError,
 };
@@ -13,6 +14,31 @@ pub struct CompileContext {
     pub comp: Box<dyn CompContext>,
     pub named_resolver: Box<LabelResolver>,
     pub status: Rc<ContextStatus>,
+
+    /// Names of the label scopes being compiled
+    pub scope_path: Vec<String>,
+    /// Named labels seen in the current pass with their ids
+    pub labels: Vec<(Vec<String>, String, usize)>,
+
+    /// Constants defined so far in the current pass
+    pub constants: HashMap<String, Constant>,
+    /// Constants of the previous pass, used for forward references
+    pub prev_constants: HashMap<String, Constant>,
+
+    /// Labels of other objects from `.extern` with their ids
+    pub imports: Vec<(String, usize)>,
+    /// Labels from `.global` other objects may refer to
+    pub exports: Vec<(String, usize)>,
+    /// Absolute addresses were used, the code only works placed at address 0
+    pub position_dependent: bool,
+    pub flow: FlowLint,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Constant {
+    pub value: i64,
+    /// Defined with `.set`, may be assigned again
+    pub redefinable: bool,
 }
 
 impl CompileContext {
@@ -23,7 +49,53 @@ impl CompileContext {
             comp: Box::new(MainCompContext::new(status.clone())),
             named_resolver: Box::new(LabelResolver::new()),
             status,
+            scope_path: Vec::new(),
+            labels: Vec::new(),
+            constants: HashMap::new(),
+            prev_constants: HashMap::new(),
+            imports: Vec::new(),
+            exports: Vec::new(),
+            position_dependent: false,
+            flow: FlowLint::default(),
+        }
+    }
+
+    /// Starts a new pass, constants changing since the last pass need another one
+    pub fn finish_pass(&mut self) {
+        if self.constants != self.prev_constants {
+            self.status.recompile();
         }
+        self.prev_constants = std::mem::take(&mut self.constants);
+    }
+
+    pub fn define_constant(&mut self, name: &str, constant: Constant) -> Result<(), CompileError> {
+        if let Some(prev) = self.constants.get(name) {
+            if !(prev.redefinable && constant.redefinable) {
+                return Err(CompileError::ConstantRedefined(name.to_owned()));
+            }
+        }
+        self.constants.insert(name.to_owned(), constant);
+        Ok(())
+    }
+
+    /// Value of a constant or the absolute address of a label
+    pub fn lookup_symbol(&mut self, name: &str) -> Result<i64, CompileError> {
+        if let Some(constant) = self.constants.get(name).or(self.prev_constants.get(name)) {
+            return Ok(constant.value);
+        }
+
+        let label_id = self.named_resolver.resolve_label_id(&name.to_owned())?;
+        if label_id == usize::MAX {
+            // Placeholder until labels are known
+            self.status.recompile();
+        }
+        if self.comp.is_external(label_id) {
+            return Err(CompileError::ExternalAddress(name.to_owned()));
+        }
+
+        self.position_dependent = true;
+        let offset = self.resolve_label(label_id)?;
+        Ok(offset.wrapping_add(self.current_pc()?) as i64)
     }
 
     pub fn instruct(&mut self, instruction: cpu::Instruction) {
@@ -41,4 +113,27 @@ impl CompileContext {
     pub fn resolve_label(&mut self, label_id: usize) -> Result<u16, CompileError> {
         self.comp.resolve_label(label_id)
     }
+
+    pub fn current_pc(&mut self) -> Result<u16, CompileError> {
+        self.comp.current_pc()
+    }
+}
+
+/// Symbol environment resolving names in a compile context
+pub struct ContextEnv<'a> {
+    ctx: RefCell<&'a mut CompileContext>,
+}
+
+impl<'a> ContextEnv<'a> {
+    pub fn new(ctx: &'a mut CompileContext) -> Self {
+        ContextEnv {
+            ctx: RefCell::new(ctx),
+        }
+    }
+}
+
+impl SymbolEnv for ContextEnv<'_> {
+    fn lookup(&self, name: &str) -> Result<i64, CompileError> {
+        self.ctx.borrow_mut().lookup_symbol(name)
+    }
 }
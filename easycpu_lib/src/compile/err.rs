@@ -1,3 +1,6 @@
+use std::fmt;
+
+use super::Warning;
 use crate::cpu;
 
 #[derive(Debug, Clone)]
@@ -22,7 +25,174 @@ pub enum CompileError {
     InvalidNumber(String),
 
     UnmatchedClosingBracket,
+    /// Opening bracket of a block the input ends in
+    UnclosedBracket(char),
     UnknownModifier(String),
     
     InstructionInStackopt,
+
+    UnknownDirective(String),
+    OriginBehind(u16),
+    InvalidAlignment(u16),
+    ProgramTooLarge,
+
+    InvalidExpression(String),
+    ExpressionOverflow,
+    DivisionByZero,
+    ValueOutOfRange(i64),
+    ConstantRedefined(String),
+
+    /// File name and the reason it could not be read
+    IncludeFailed(String),
+    /// Files of the cycle, from the one included again back to itself
+    IncludeCycle(Vec<String>),
+
+    InvalidMacro(String),
+    MacroRedefined(String),
+    /// Macro name, expected and supplied argument count
+    MacroArguments(String, usize, usize),
+    MacroTooDeep(String),
+    TooManyExpansions,
+
+    /// Name in an `@if` condition which is not defined
+    NotDefined(String),
+    /// Conditional modifier without its `@if`
+    UnmatchedConditional(String),
+    UnterminatedConditional,
+
+    /// Address of a label from another object used in an expression
+    ExternalAddress(String),
+    ExternalExported(String),
+    /// Operand refers to symbols, it is evaluated again while compiling
+    DeferredExpression,
+    UnterminatedString,
+
+    /// Reported as a warning, or as an error when warnings are denied
+    Warning(Box<Warning>),
+    UnknownWarning(String),
+}
+
+impl CompileError {
+    /// Stable code of the error kind, new kinds get new codes. Warnings are
+    /// identified by their name
+    pub fn code(&self) -> &'static str {
+        match self {
+            CompileError::NotEnoughArguments => "E001",
+            CompileError::NoCommandSupplied => "E002",
+            CompileError::UnknownCommand(_) => "E003",
+            CompileError::UnknownRegister(_) => "E004",
+            CompileError::UnknownLabel(_) => "E005",
+            CompileError::ShiftIsTooBig(_) => "E006",
+            CompileError::InvalidInstruction(_) => "E007",
+            CompileError::TooManyAttempts => "E008",
+            CompileError::LabelRedefined(_) => "E009",
+            CompileError::UnexpectedEndOfFile => "E010",
+            CompileError::UnexpectedToken(_) => "E011",
+            CompileError::UnknownToken(_) => "E012",
+            CompileError::InvalidNumber(_) => "E013",
+            CompileError::UnmatchedClosingBracket => "E014",
+            CompileError::UnknownModifier(_) => "E015",
+            CompileError::InstructionInStackopt => "E016",
+            CompileError::UnknownDirective(_) => "E017",
+            CompileError::OriginBehind(_) => "E018",
+            CompileError::InvalidAlignment(_) => "E019",
+            CompileError::ProgramTooLarge => "E040",
+            CompileError::UnclosedBracket(_) => "E041",
+            CompileError::InvalidExpression(_) => "E020",
+            CompileError::ExpressionOverflow => "E021",
+            CompileError::DivisionByZero => "E022",
+            CompileError::ValueOutOfRange(_) => "E023",
+            CompileError::ConstantRedefined(_) => "E024",
+            CompileError::IncludeFailed(_) => "E025",
+            CompileError::IncludeCycle(_) => "E026",
+            CompileError::InvalidMacro(_) => "E027",
+            CompileError::MacroRedefined(_) => "E028",
+            CompileError::MacroArguments(..) => "E029",
+            CompileError::MacroTooDeep(_) => "E030",
+            CompileError::TooManyExpansions => "E031",
+            CompileError::NotDefined(_) => "E032",
+            CompileError::UnmatchedConditional(_) => "E033",
+            CompileError::UnterminatedConditional => "E034",
+            CompileError::ExternalAddress(_) => "E035",
+            CompileError::ExternalExported(_) => "E036",
+            CompileError::DeferredExpression => "E037",
+            CompileError::UnterminatedString => "E038",
+            CompileError::Warning(warning) => warning.name(),
+            CompileError::UnknownWarning(_) => "E039",
+        }
+    }
 }
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::NotEnoughArguments => write!(f, "not enough arguments"),
+            CompileError::NoCommandSupplied => write!(f, "expected an instruction"),
+            CompileError::UnknownCommand(name) => write!(f, "unknown instruction `{}`", name),
+            CompileError::UnknownRegister(name) => write!(f, "unknown register `{}`", name),
+            CompileError::UnknownLabel(name) => write!(f, "unknown label `{}`", name),
+            CompileError::ShiftIsTooBig(shift) => write!(f, "shift {} does not fit", shift),
+            CompileError::InvalidInstruction(cpu::InstructionError::InvalidShift) => {
+                write!(f, "instruction shift out of range")
+            }
+            CompileError::TooManyAttempts => write!(f, "label addresses do not settle"),
+            CompileError::LabelRedefined(name) => write!(f, "label `{}` is defined twice", name),
+            CompileError::UnexpectedEndOfFile => write!(f, "unexpected end of file"),
+            CompileError::UnexpectedToken(c) => write!(f, "unexpected `{}`", c),
+            CompileError::UnknownToken(c) => write!(f, "unknown character `{}`", c),
+            CompileError::InvalidNumber(num) => write!(f, "invalid number `{}`", num),
+            CompileError::UnmatchedClosingBracket => write!(f, "`}}` without an opening `{{`"),
+            CompileError::UnclosedBracket(c) => write!(f, "`{}` is never closed", c),
+            CompileError::UnknownModifier(name) => write!(f, "unknown modifier `@{}`", name),
+            CompileError::InstructionInStackopt => {
+                write!(f, "instruction is not allowed in a stack optimised block")
+            }
+            CompileError::UnknownDirective(name) => write!(f, "unknown directive `{}`", name),
+            CompileError::OriginBehind(addr) => {
+                write!(f, "origin {:#06x} is behind the current address", addr)
+            }
+            CompileError::InvalidAlignment(align) => write!(f, "alignment {} is invalid", align),
+            CompileError::ProgramTooLarge => write!(f, "program exceeds 64K words"),
+            CompileError::InvalidExpression(expr) => write!(f, "invalid expression `{}`", expr),
+            CompileError::ExpressionOverflow => write!(f, "expression overflows"),
+            CompileError::DivisionByZero => write!(f, "division by zero"),
+            CompileError::ValueOutOfRange(val) => write!(f, "value {} is out of range", val),
+            CompileError::ConstantRedefined(name) => {
+                write!(f, "constant `{}` is defined twice", name)
+            }
+            CompileError::IncludeFailed(reason) => write!(f, "cannot include {}", reason),
+            CompileError::IncludeCycle(chain) => {
+                let chain: Vec<String> = chain.iter().map(|name| format!("`{}`", name)).collect();
+                write!(f, "include cycle {}", chain.join(" -> "))
+            }
+            CompileError::InvalidMacro(name) => write!(f, "invalid macro name `{}`", name),
+            CompileError::MacroRedefined(name) => write!(f, "macro `{}` is defined twice", name),
+            CompileError::MacroArguments(name, expected, supplied) => write!(
+                f,
+                "macro `{}` takes {} arguments but {} were supplied",
+                name, expected, supplied
+            ),
+            CompileError::MacroTooDeep(name) => {
+                write!(f, "macro `{}` is nested too deep, is it recursive?", name)
+            }
+            CompileError::TooManyExpansions => write!(f, "too many macro expansions"),
+            CompileError::NotDefined(name) => write!(f, "`{}` is not defined", name),
+            CompileError::UnmatchedConditional(name) => {
+                write!(f, "`{}` without a matching `@if`", name)
+            }
+            CompileError::UnterminatedConditional => write!(f, "`@if` without `@endif`"),
+            CompileError::ExternalAddress(name) => {
+                write!(f, "address of external label `{}` is only known when linking", name)
+            }
+            CompileError::ExternalExported(name) => {
+                write!(f, "external label `{}` cannot be exported", name)
+            }
+            CompileError::DeferredExpression => write!(f, "expression needs symbol values"),
+            CompileError::UnterminatedString => write!(f, "string is not closed on its line"),
+            CompileError::Warning(warning) => write!(f, "{}", warning),
+            CompileError::UnknownWarning(name) => write!(f, "unknown warning `{}`", name),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
@@ -1,12 +1,21 @@
 use std::{cell::RefCell, mem};
 
-use super::{Atom, AtomBox, CompileContext, CompileError};
+use super::{Atom, AtomBox, CompileContext, CompileError, Warning};
+use crate::parser::{ErrorNote, ParsePosition};
 
 use std::collections::{hash_map::Entry, HashMap};
 
+#[derive(Debug, Clone)]
+struct LabelDefinition {
+    id: usize,
+    pos: ParsePosition,
+    // Resolved by a reference once labels are known
+    used: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct LabelResolver {
-    label_map: HashMap<String, usize>,
+    label_map: HashMap<String, LabelDefinition>,
     resolving_labels: bool,
     parent: Option<Box<LabelResolver>>,
 }
@@ -33,23 +42,62 @@ impl LabelResolver {
         self.resolving_labels = false;
     }
 
-    pub fn register_label(&mut self, label: &String, id: usize) -> Result<(), CompileError> {
+    pub fn register_label(
+        &mut self,
+        label: &String,
+        id: usize,
+        pos: ParsePosition,
+    ) -> Result<(), CompileError> {
         match self.label_map.entry(label.to_owned()) {
             Entry::Occupied(_) => Err(CompileError::LabelRedefined(label.to_owned())),
             Entry::Vacant(v) => {
-                v.insert(id);
+                v.insert(LabelDefinition {
+                    id,
+                    pos,
+                    used: false,
+                });
                 Ok(())
             }
         }
     }
 
+    /// Where a label of this scope was defined
+    pub fn definition(&self, label: &String) -> Option<ParsePosition> {
+        self.label_map.get(label).map(|def| def.pos)
+    }
+
+    /// Labels of this scope no reference resolved to, sorted by position
+    pub fn unused(&self) -> Vec<(String, ParsePosition)> {
+        let mut unused: Vec<_> = self
+            .label_map
+            .iter()
+            .filter(|(_, def)| !def.used)
+            .map(|(name, def)| (name.clone(), def.pos))
+            .collect();
+        unused.sort_by_key(|(_, pos)| (pos.file, pos.pos));
+        unused
+    }
+
+    /// A scope this one is nested in defines `label`
+    pub fn shadows(&self, label: &String) -> bool {
+        let mut parent = self.parent.as_deref();
+        while let Some(scope) = parent {
+            if scope.label_map.contains_key(label) {
+                return true;
+            }
+            parent = scope.parent.as_deref();
+        }
+        false
+    }
+
     pub fn resolve_label_id(&mut self, label: &String) -> Result<usize, CompileError> {
         if self.resolving_labels {
             return Ok(usize::MAX);
         }
         
-        if let Some(id) = self.label_map.get(label) {
-            Ok(*id)
+        if let Some(def) = self.label_map.get_mut(label) {
+            def.used = true;
+            Ok(def.id)
         } else if let Some(parent) = &mut self.parent {
             parent.resolve_label_id(label)
         } else {
@@ -57,7 +105,7 @@ impl LabelResolver {
         }
     }
 
-    pub fn ready(&mut self) -> bool {
+    pub fn ready(&self) -> bool {
         !self.resolving_labels
     }
 }
@@ -81,27 +129,52 @@ impl Label {
 impl Atom for Label {
     fn compile(&self, ctx: &mut super::CompileContext) -> Result<(), super::CompileError> {
         let mut id = self.id.borrow_mut();
-        match *id {
-            Some(label_id) => ctx.emit_label(label_id),
+        let label_id = match *id {
+            Some(label_id) => {
+                ctx.emit_label(label_id)?;
+                label_id
+            }
             None => {
                 let new_id = ctx.emit_new_label();
-                ctx.named_resolver.register_label(&self.name, new_id)?;
+                let (pos, _) = ctx.status.pos();
+                if let Err(error) = ctx.named_resolver.register_label(&self.name, new_id, pos) {
+                    let note = ctx
+                        .named_resolver
+                        .definition(&self.name)
+                        .map(|pos| ErrorNote {
+                            message: String::from("label defined here"),
+                            pos,
+                        });
+                    ctx.status.report_err_with_note(error, note);
+                    return Ok(());
+                }
                 *id = Some(new_id);
-                Ok(())
+                new_id
             }
+        };
+
+        ctx.flow.label();
+        if ctx.named_resolver.shadows(&self.name) {
+            ctx.status.report_warning(Warning::ShadowedLabel(self.name.clone()));
         }
+
+        ctx.labels
+            .push((ctx.scope_path.clone(), self.name.clone(), label_id));
+        Ok(())
     }
 }
 
 #[derive(Debug)]
 pub struct LabelScope {
+    name: String,
     atoms: Vec<AtomBox>,
     scope: RefCell<Option<Box<LabelResolver>>>,
 }
 
 impl LabelScope {
-    pub fn new(atoms: Vec<AtomBox>) -> Self {
+    pub fn new(name: String, atoms: Vec<AtomBox>) -> Self {
         LabelScope {
+            name,
             atoms,
             scope: RefCell::new(None),
         }
@@ -118,10 +191,13 @@ impl Atom for LabelScope {
 
         mem::swap(&mut ctx.named_resolver, &mut parent);
         ctx.named_resolver.attach_parent(parent);
+        ctx.scope_path.push(self.name.clone());
 
-        for atom in self.atoms.iter() {
-            atom.compile(ctx)?;
-        }
+        let res = self.atoms.iter().try_for_each(|atom| atom.compile(ctx));
+
+        ctx.scope_path.pop();
+        res?;
+        report_unused(ctx);
 
         let mut parent = ctx.named_resolver.detach_parent();
         mem::swap(&mut ctx.named_resolver, &mut parent);
@@ -130,3 +206,14 @@ impl Atom for LabelScope {
         Ok(())
     }
 }
+
+/// Warn about labels of the current scope, references are only known once
+/// labels are resolved
+pub fn report_unused(ctx: &CompileContext) {
+    if !ctx.named_resolver.ready() {
+        return;
+    }
+    for (name, pos) in ctx.named_resolver.unused() {
+        ctx.status.report_warning_at(Warning::UnusedLabel(name), pos, pos);
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use crate::parser::ParsePosition;
+
+/// Statistics of laying out a program
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LayoutStats {
+    /// Passes over the atoms, the first two only measure
+    pub passes: usize,
+    /// Jumps to labels of the program
+    pub jumps: usize,
+    /// Jumps which do not reach their label with a branch and got the long form
+    pub relaxed: usize,
+}
+
+/// Atom emitting an instruction, by its position and the call sites of the
+/// macro expansions it is in
+pub type AtomKey = (ParsePosition, ParsePosition, Vec<ParsePosition>);
+
+#[derive(Clone, Copy, Debug)]
+struct Site {
+    words: usize,
+    jump: bool,
+    // Last pass emitting the instruction
+    pass: usize,
+}
+
+/// Sizes of the instructions whose form depends on the distance to a label.
+///
+/// The first pass only finds the labels. The second one places them with
+/// every jump short, whether it reaches or not. Later passes look the
+/// instructions up by the atom emitting them and only let them grow, so
+/// label addresses never go down and the layout settles.
+#[derive(Debug, Default)]
+pub struct Layout {
+    // Instructions by their atom and how many the atom emitted before them
+    sites: HashMap<(AtomKey, usize), Site>,
+    emitted: HashMap<AtomKey, usize>,
+    passes: usize,
+    grew: bool,
+}
+
+impl Layout {
+    pub fn start_pass(&mut self) {
+        self.passes += 1;
+        self.emitted.clear();
+        self.grew = false;
+    }
+
+    /// Some instruction took more words than in the pass before
+    pub fn grew(&self) -> bool {
+        self.grew
+    }
+
+    fn grow(&mut self, atom: AtomKey, words: usize, jump: bool) -> usize {
+        if self.passes <= 1 {
+            return words;
+        }
+
+        let count = self.emitted.entry(atom.clone()).or_default();
+        let key = (atom, *count);
+        *count += 1;
+
+        let pass = self.passes;
+        if pass == 2 {
+            // Other sizes are only known against the addresses of this pass
+            let words = if jump { 1 } else { words };
+            let placed = if jump { 1 } else { 0 };
+            self.sites.insert(key, Site { words: placed, jump, pass });
+            return words;
+        }
+
+        match self.sites.get_mut(&key) {
+            Some(site) => {
+                if words > site.words {
+                    site.words = words;
+                    self.grew = true;
+                }
+                site.jump = jump;
+                site.pass = pass;
+                site.words
+            }
+            None => {
+                self.sites.insert(key, Site { words, jump, pass });
+                self.grew = true;
+                words
+            }
+        }
+    }
+
+    /// Words of an instruction, at least as many as it took before
+    pub fn relax(&mut self, atom: AtomKey, words: usize) -> usize {
+        self.grow(atom, words, false)
+    }
+
+    /// Jump may use a branch, once it took the long form it keeps it. Jumps
+    /// are short while labels are placed even if `short` is false
+    pub fn relax_jump(&mut self, atom: AtomKey, short: bool) -> bool {
+        self.grow(atom, if short { 1 } else { 2 }, true) == 1
+    }
+
+    pub fn stats(&self) -> LayoutStats {
+        // Instructions of earlier passes which are gone do not count
+        let jumps = self
+            .sites
+            .values()
+            .filter(|site| site.jump && site.pass == self.passes);
+        LayoutStats {
+            passes: self.passes,
+            jumps: jumps.clone().count(),
+            relaxed: jumps.filter(|site| site.words > 1).count(),
+        }
+    }
+}
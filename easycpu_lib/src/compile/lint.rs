@@ -0,0 +1,67 @@
+use crate::cpu::Register;
+use crate::parser::ParsePosition;
+
+use super::status::ContextStatus;
+use super::warning::Warning;
+
+/// Control flow followed through a pass for the warnings about it
+#[derive(Debug, Default)]
+pub struct FlowLint {
+    // After a jump or `HALT` code is only reached through a label
+    unreachable: bool,
+    // Last instruction when execution continues past it
+    falls_through: Option<(ParsePosition, ParsePosition)>,
+    // `$FUNC` waiting for its `$RET`
+    open_function: Option<(ParsePosition, ParsePosition)>,
+}
+
+impl FlowLint {
+    /// Instruction at the current position, execution does not continue
+    /// after it when it `ends` the flow
+    pub fn instruction(&mut self, status: &ContextStatus, ends: bool) {
+        if self.unreachable {
+            status.report_warning(Warning::Unreachable);
+        }
+        self.unreachable = ends;
+        self.falls_through = (!ends).then(|| status.pos());
+    }
+
+    /// Instruction writing `dst`, writes to `PC` are jumps
+    pub fn write(&mut self, status: &ContextStatus, dst: Register) {
+        if dst == Register::ZX {
+            status.report_warning(Warning::WriteZero);
+        }
+        self.instruction(status, dst == Register::PC);
+    }
+
+    /// `HALT` ends the flow and a function waiting for its `$RET`, like the
+    /// main routine using `$FUNC` for its locals
+    pub fn halt(&mut self, status: &ContextStatus) {
+        self.instruction(status, true);
+        self.open_function = None;
+    }
+
+    pub fn label(&mut self) {
+        self.unreachable = false;
+    }
+
+    pub fn function_start(&mut self, status: &ContextStatus) {
+        if let Some((start, end)) = self.open_function.replace(status.pos()) {
+            status.report_warning_at(Warning::FunctionWithoutReturn, start, end);
+        }
+    }
+
+    pub fn function_end(&mut self) {
+        self.open_function = None;
+    }
+
+    /// End of the program
+    pub fn finish(&mut self, status: &ContextStatus) {
+        if let Some((start, end)) = self.open_function.take() {
+            status.report_warning_at(Warning::FunctionWithoutReturn, start, end);
+        }
+        if let Some((start, end)) = self.falls_through.take() {
+            status.report_warning_at(Warning::MissingHalt, start, end);
+        }
+    }
+}
@@ -4,12 +4,21 @@ pub mod err;
 pub mod compiler;
 pub mod context;
 pub mod label;
+pub mod layout;
+pub mod lint;
 pub mod namedlabel;
+pub mod sourcemap;
 pub mod status;
+pub mod symbols;
+pub mod warning;
 pub mod comp;
 
 pub use atom::{Atom, AtomBox, ErrorAtom, compile_instructions};
-pub use context::CompileContext;
+pub use context::{CompileContext, Constant, ContextEnv};
 pub use err::CompileError;
 pub use label::Label;
-pub use compiler::compile_program;
\ No newline at end of file
+pub use layout::LayoutStats;
+pub use compiler::{compile_program, CompiledProgram};
+pub use sourcemap::SourceMap;
+pub use symbols::{Symbol, SymbolTable};
+pub use warning::Warning;
\ No newline at end of file
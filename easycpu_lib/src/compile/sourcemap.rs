@@ -0,0 +1,55 @@
+use crate::parser::{ParsePosition, SourceFile, SourceSet};
+
+/// Source range of the atom which emitted each word of a program
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    spans: Vec<(ParsePosition, ParsePosition)>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn push(&mut self, span: (ParsePosition, ParsePosition)) {
+        self.spans.push(span);
+    }
+
+    pub fn clear(&mut self) {
+        self.spans.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    pub fn get(&self, addr: u16) -> Option<(ParsePosition, ParsePosition)> {
+        self.spans.get(addr as usize).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(ParsePosition, ParsePosition)> {
+        self.spans.iter()
+    }
+
+    /// Zero based line number and text of the line `addr` was compiled from
+    pub fn source_line<'a>(&self, source: &'a str, addr: u16) -> Option<(usize, &'a str)> {
+        let (start, _) = self.get(addr)?;
+        let line = source.lines().nth(start.line)?;
+        Some((start.line, line))
+    }
+
+    /// File, zero based line number and text of the line `addr` was compiled from
+    pub fn file_line<'a>(
+        &self,
+        sources: &'a SourceSet,
+        addr: u16,
+    ) -> Option<(&'a SourceFile, usize, &'a str)> {
+        let (start, _) = self.get(addr)?;
+        let file = sources.get(start.file)?;
+        Some((file, start.line, sources.line(start)?))
+    }
+}
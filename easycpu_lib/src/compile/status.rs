@@ -1,23 +1,28 @@
 use std::cell::RefCell;
 
-use crate::parser::{ParsePosition, PosCompileError};
+use crate::parser::{ErrorNote, ParsePosition, PosCompileError};
 
-use super::CompileError;
+use super::{layout::AtomKey, CompileError, Warning};
 
 #[derive(Debug)]
 pub struct ContextStatus {
     errors: RefCell<Vec<PosCompileError>>,
+    warnings: RefCell<Vec<PosCompileError>>,
     should_recompile: RefCell<bool>,
 
     pos: RefCell<(ParsePosition, ParsePosition)>,
+    // Call sites of the macro expansions being compiled, outermost first
+    expansions: RefCell<Vec<ParsePosition>>,
 }
 
 impl ContextStatus {
     pub fn new() -> Self {
         ContextStatus {
             errors: RefCell::new(Vec::new()),
+            warnings: RefCell::new(Vec::new()),
             should_recompile: RefCell::new(true),
             pos: RefCell::default(),
+            expansions: RefCell::default(),
         }
     }
 
@@ -30,23 +35,72 @@ impl ContextStatus {
     }
 
     pub fn report_err(&self, error: CompileError) {
-        let (start_pos, end_pos) = *self.pos.borrow();
+        self.report_err_with_note(error, None);
+    }
+
+    pub fn report_err_with_note(&self, error: CompileError, note: Option<ErrorNote>) {
+        let (start_pos, end_pos) = self.pos();
+        let error = self.with_context(error, start_pos, end_pos, note);
+        self.errors.borrow_mut().push(error);
+    }
+
+    pub fn report_warning(&self, warning: Warning) {
+        let (start_pos, end_pos) = self.pos();
+        self.report_warning_at(warning, start_pos, end_pos);
+    }
 
-        self.errors.borrow_mut().push(PosCompileError {
+    pub fn report_warning_at(&self, warning: Warning, start: ParsePosition, end: ParsePosition) {
+        let error = CompileError::Warning(Box::new(warning));
+        let warning = self.with_context(error, start, end, None);
+        self.warnings.borrow_mut().push(warning);
+    }
+
+    fn with_context(
+        &self,
+        error: CompileError,
+        start_pos: ParsePosition,
+        end_pos: ParsePosition,
+        note: Option<ErrorNote>,
+    ) -> PosCompileError {
+        PosCompileError {
             error,
             start_pos,
             end_pos,
-        });
+            expanded_from: self.expansions.borrow().iter().rev().copied().collect(),
+            note: note.map(Box::new),
+        }
     }
 
     pub fn take_errors(&self) -> Vec<PosCompileError> {
         self.errors.take()
     }
 
+    pub fn take_warnings(&self) -> Vec<PosCompileError> {
+        self.warnings.take()
+    }
+
+    pub fn pos(&self) -> (ParsePosition, ParsePosition) {
+        *self.pos.borrow()
+    }
+
+    /// Atom being compiled, the same one in every pass
+    pub fn atom(&self) -> AtomKey {
+        let (start, end) = self.pos();
+        (start, end, self.expansions.borrow().clone())
+    }
+
     pub fn swap_pos(
         &self,
         new_pos: (ParsePosition, ParsePosition),
     ) -> (ParsePosition, ParsePosition) {
         self.pos.replace(new_pos)
     }
+
+    pub fn enter_expansion(&self, call_site: ParsePosition) {
+        self.expansions.borrow_mut().push(call_site);
+    }
+
+    pub fn leave_expansion(&self) {
+        self.expansions.borrow_mut().pop();
+    }
 }
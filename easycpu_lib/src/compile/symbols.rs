@@ -0,0 +1,72 @@
+use std::fmt;
+
+/// Named label with the names of the blocks it is nested in
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub scope: Vec<String>,
+    pub addr: u16,
+}
+
+impl Symbol {
+    /// Splits a name written by `full_name` back into scope and name
+    pub fn from_full_name(full_name: &str, addr: u16) -> Self {
+        let mut scope: Vec<String> = full_name.split('.').map(String::from).collect();
+        let name = scope.pop().unwrap_or_default();
+        Symbol { name, scope, addr }
+    }
+
+    /// Name prefixed by the scope path, e.g. `PRINT_CHAR.LOOP`
+    pub fn full_name(&self) -> String {
+        self.scope
+            .iter()
+            .chain(Some(&self.name))
+            .cloned()
+            .collect::<Vec<String>>()
+            .join(".")
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn push(&mut self, symbol: Symbol) {
+        self.symbols.push(symbol);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn get(&self, full_name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|s| s.full_name() == full_name)
+    }
+}
+
+/// `.sym` file contents, one `<addr> <full name>` pair per line
+impl fmt::Display for SymbolTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut symbols: Vec<&Symbol> = self.symbols.iter().collect();
+        symbols.sort_by_key(|s| s.addr);
+
+        for symbol in symbols {
+            writeln!(f, "{:#06x} {}", symbol.addr, symbol.full_name())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use std::fmt;
+
+/// Code which assembles but is likely a mistake, reported next to errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// Result of an instruction is written to `ZX` and lost
+    WriteZero,
+    LoadPc,
+    /// Instruction after a jump or `HALT` without a label before it
+    Unreachable,
+    UnusedLabel(String),
+    /// Label of a block hiding a label of an enclosing block
+    ShadowedLabel(String),
+    FunctionWithoutReturn,
+    /// Execution continues past the last instruction
+    MissingHalt,
+}
+
+impl Warning {
+    /// Names warnings are enabled and disabled by
+    pub const NAMES: [&'static str; 7] = [
+        "write-zx",
+        "load-pc",
+        "unreachable",
+        "unused-label",
+        "shadowed-label",
+        "func-without-ret",
+        "missing-halt",
+    ];
+
+    pub fn name(&self) -> &'static str {
+        let idx = match self {
+            Warning::WriteZero => 0,
+            Warning::LoadPc => 1,
+            Warning::Unreachable => 2,
+            Warning::UnusedLabel(_) => 3,
+            Warning::ShadowedLabel(_) => 4,
+            Warning::FunctionWithoutReturn => 5,
+            Warning::MissingHalt => 6,
+        };
+        Self::NAMES[idx]
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::WriteZero => write!(f, "result written to `ZX` is discarded"),
+            Warning::LoadPc => write!(f, "`LOAD` into `PC` jumps to the loaded value"),
+            Warning::Unreachable => write!(f, "unreachable instruction"),
+            Warning::UnusedLabel(name) => write!(f, "label `{}` is never used", name),
+            Warning::ShadowedLabel(name) => {
+                write!(f, "label `{}` shadows a label of an outer block", name)
+            }
+            Warning::FunctionWithoutReturn => write!(f, "`$FUNC` without a matching `$RET`"),
+            Warning::MissingHalt => write!(f, "execution continues past the end, `HALT` missing"),
+        }
+    }
+}
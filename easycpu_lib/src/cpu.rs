@@ -1,3 +1,5 @@
+use std::fmt;
+
 use crate::exec::ExecCpu;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -28,18 +30,18 @@ impl From<u16> for Register {
     }
 }
 
-impl ToString for Register {
-    fn to_string(&self) -> String {
-        match &self {
-            Register::ZX => String::from("ZX"),
-            Register::PC => String::from("PC"),
-            Register::R2 => String::from("R2"),
-            Register::R3 => String::from("R3"),
-            Register::R4 => String::from("R4"),
-            Register::R5 => String::from("R5"),
-            Register::LP => String::from("LP"),
-            Register::SP => String::from("SP"),
-        }
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match &self {
+            Register::ZX => "ZX",
+            Register::PC => "PC",
+            Register::R2 => "R2",
+            Register::R3 => "R3",
+            Register::R4 => "R4",
+            Register::R5 => "R5",
+            Register::LP => "LP",
+            Register::SP => "SP",
+        })
     }
 }
 
@@ -141,9 +143,9 @@ impl AluInstruction {
             "{}{} {} {} {}",
             if is_add { "ADD" } else { "AND" },
             flags_to_string("xyo", [self.nx, self.ny, self.no]),
-            self.dst.to_string(),
-            self.src_a.to_string(),
-            self.src_b.to_string(),
+            self.dst,
+            self.src_a,
+            self.src_b,
         )
     }
 
@@ -229,8 +231,8 @@ impl MemInstruction {
             "{}{} {} {} {}",
             if is_store { "STORE" } else { "LOAD" },
             flags_to_string("hls", [self.hi, self.lo, self.sw]),
-            self.dst.to_string(),
-            self.addr.to_string(),
+            self.dst,
+            self.addr,
             self.shift,
         )
     }
@@ -316,7 +318,7 @@ impl BranchInstruction {
         format!(
             "BRANCH{} {} {}",
             flags_to_string("egl", [self.eq, self.gt, self.lt]),
-            self.cond.to_string(),
+            self.cond,
             self.shift,
         )
     }
@@ -407,16 +409,16 @@ impl Instruction {
     }
 }
 
-impl ToString for Instruction {
-    fn to_string(&self) -> String {
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Instruction::NOP => String::from("NOP"),
-            Instruction::AND(ins) => ins.display(false),
-            Instruction::ADD(ins) => ins.display(true),
-            Instruction::LOAD(ins) => ins.display(false),
-            Instruction::STORE(ins) => ins.display(true),
-            Instruction::BRANCH(ins) => ins.display(),
-            Instruction::CUSTOM(ins) => format!("0x{:04x}", ins),
+            Instruction::NOP => f.write_str("NOP"),
+            Instruction::AND(ins) => f.write_str(&ins.display(false)),
+            Instruction::ADD(ins) => f.write_str(&ins.display(true)),
+            Instruction::LOAD(ins) => f.write_str(&ins.display(false)),
+            Instruction::STORE(ins) => f.write_str(&ins.display(true)),
+            Instruction::BRANCH(ins) => f.write_str(&ins.display()),
+            Instruction::CUSTOM(ins) => write!(f, "0x{:04x}", ins),
         }
     }
 }
@@ -0,0 +1,271 @@
+use std::fmt::Write;
+
+use crate::parser::{ParsePosition, PosCompileError, SourceSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        }
+    }
+}
+
+/// Decoration of diagnostics rendered as text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Plain,
+    /// ANSI colours for terminals
+    Color,
+}
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const GREEN: &str = "\x1b[1;32m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Innermost call sites listed before a long expansion chain is cut short,
+/// the outermost one is always listed
+const SHOWN_EXPANSIONS: usize = 3;
+
+impl Style {
+    fn paint(&self, color: &str, text: &str) -> String {
+        match self {
+            Style::Plain => text.to_owned(),
+            Style::Color => format!("{}{}{}", color, text, RESET),
+        }
+    }
+}
+
+struct Renderer<'a> {
+    sources: &'a SourceSet,
+    style: Style,
+    out: String,
+    // Width of the line number column
+    gutter: usize,
+}
+
+impl Renderer<'_> {
+    fn location(&mut self, pos: ParsePosition) {
+        let arrow = self.style.paint(BLUE, "-->");
+        let pad = " ".repeat(self.gutter);
+        let _ = writeln!(self.out, "{}{} {}", pad, arrow, self.sources.describe(pos));
+    }
+
+    /// Line of `start` with the range up to `end` underlined
+    fn snippet(&mut self, start: ParsePosition, end: ParsePosition, color: &str) {
+        let Some(text) = self.sources.line(start) else {
+            return;
+        };
+        let text = text.trim_end();
+        let bar = self.style.paint(BLUE, "|");
+        let pad = " ".repeat(self.gutter);
+        let number = format!("{:>width$}", start.line + 1, width = self.gutter);
+
+        let len = match end.file == start.file && end.line == start.line {
+            true => end.line_pos.saturating_sub(start.line_pos),
+            false => usize::MAX,
+        };
+        // Whitespace closing the range is not marked
+        let marked: String = text.chars().skip(start.line_pos).take(len).collect();
+        let len = marked.trim_end().chars().count();
+        // Tabs are kept so the underline lines up with the text
+        let indent: String = text
+            .chars()
+            .take(start.line_pos)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let marks = self.style.paint(color, &"^".repeat(len.max(1)));
+
+        let _ = writeln!(self.out, "{} {}", pad, bar);
+        let _ = writeln!(
+            self.out,
+            "{} {} {}",
+            self.style.paint(BLUE, &number),
+            bar,
+            text
+        );
+        let _ = writeln!(self.out, "{} {} {}{}", pad, bar, indent, marks);
+    }
+
+    fn error(&mut self, error: &PosCompileError, severity: Severity) {
+        let lines = error
+            .note
+            .iter()
+            .map(|n| n.pos.line)
+            .chain([error.start_pos.line]);
+        self.gutter = lines
+            .max()
+            .unwrap_or_default()
+            .saturating_add(1)
+            .to_string()
+            .len();
+
+        let title = format!("{}[{}]", severity.name(), error.error.code());
+        let _ = writeln!(
+            self.out,
+            "{}{}",
+            self.style.paint(severity.color(), &title),
+            self.style.paint(BOLD, &format!(": {}", error.error))
+        );
+        self.location(error.start_pos);
+        self.snippet(error.start_pos, error.end_pos, severity.color());
+
+        let pad = " ".repeat(self.gutter);
+        let mut expansions: Vec<String> = error
+            .expanded_from
+            .iter()
+            .map(|call_site| format!("expanded from {}", self.sources.describe(*call_site)))
+            .collect();
+        // Recursive macros repeat the same few call sites up to the depth limit
+        if expansions.len() > SHOWN_EXPANSIONS + 2 {
+            let hidden = expansions.len() - SHOWN_EXPANSIONS - 1;
+            let more = format!("... {} more expansions", hidden);
+            expansions.splice(SHOWN_EXPANSIONS..expansions.len() - 1, [more]);
+        }
+        for line in expansions {
+            let _ = writeln!(self.out, "{} {} {}", pad, self.style.paint(BLUE, "="), line);
+        }
+
+        if let Some(note) = &error.note {
+            let _ = writeln!(
+                self.out,
+                "{}{}",
+                self.style.paint(GREEN, "note"),
+                self.style.paint(BOLD, &format!(": {}", note.message))
+            );
+            self.location(note.pos);
+            self.snippet(note.pos, note.pos, GREEN);
+        }
+    }
+}
+
+fn render_all(
+    errors: &[PosCompileError],
+    sources: &SourceSet,
+    style: Style,
+    severity: Severity,
+) -> String {
+    let mut renderer = Renderer {
+        sources,
+        style,
+        out: String::new(),
+        gutter: 1,
+    };
+    for (i, error) in errors.iter().enumerate() {
+        if i > 0 {
+            renderer.out.push('\n');
+        }
+        renderer.error(error, severity);
+    }
+    renderer.out
+}
+
+/// Errors with the source lines they point at, separated by blank lines
+pub fn render(errors: &[PosCompileError], sources: &SourceSet, style: Style) -> String {
+    render_all(errors, sources, style, Severity::Error)
+}
+
+/// Warnings of a compiled program, rendered like errors
+pub fn render_warnings(warnings: &[PosCompileError], sources: &SourceSet, style: Style) -> String {
+    render_all(warnings, sources, style, Severity::Warning)
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `"file"`, one based `"line"` and `"column"` fields of an object
+fn json_position(sources: &SourceSet, pos: ParsePosition, prefix: &str) -> String {
+    let file = match sources.get(pos.file) {
+        Some(file) => json_string(&file.name),
+        None => String::from("null"),
+    };
+    format!(
+        "\"{p}file\":{},\"{p}line\":{},\"{p}column\":{}",
+        file,
+        pos.line + 1,
+        pos.line_pos + 1,
+        p = prefix
+    )
+}
+
+fn render_all_json(errors: &[PosCompileError], sources: &SourceSet, severity: Severity) -> String {
+    let errors: Vec<String> = errors
+        .iter()
+        .map(|error| {
+            let expanded: Vec<String> = error
+                .expanded_from
+                .iter()
+                .map(|pos| format!("{{{}}}", json_position(sources, *pos, "")))
+                .collect();
+            let notes: Vec<String> = error
+                .note
+                .iter()
+                .map(|note| {
+                    format!(
+                        "{{\"message\":{},{}}}",
+                        json_string(&note.message),
+                        json_position(sources, note.pos, "")
+                    )
+                })
+                .collect();
+
+            format!(
+                concat!(
+                    "{{\"code\":{},\"message\":{},{},{},",
+                    "\"expanded_from\":[{}],\"notes\":[{}],\"severity\":\"{}\"}}"
+                ),
+                json_string(error.error.code()),
+                json_string(&error.error.to_string()),
+                json_position(sources, error.start_pos, ""),
+                json_position(sources, error.end_pos, "end_"),
+                expanded.join(","),
+                notes.join(","),
+                severity.name()
+            )
+        })
+        .collect();
+    format!("[{}]", errors.join(","))
+}
+
+/// Errors as a JSON array of objects with `code`, `message`, the start and
+/// end position, `expanded_from` call sites, `notes` and the `severity`
+pub fn render_json(errors: &[PosCompileError], sources: &SourceSet) -> String {
+    render_all_json(errors, sources, Severity::Error)
+}
+
+/// Warnings as a JSON array like `render_json`
+pub fn render_warnings_json(warnings: &[PosCompileError], sources: &SourceSet) -> String {
+    render_all_json(warnings, sources, Severity::Warning)
+}
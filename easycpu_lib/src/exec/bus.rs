@@ -0,0 +1,236 @@
+use std::{fmt::Debug, ops::RangeInclusive};
+
+use crate::bytes::{Reader, Writer};
+use crate::AsAny;
+
+use super::snapshot::SnapshotError;
+
+// Zero runs at least this long are left out of saved memory
+const MIN_GAP: usize = 16;
+
+/// Peripheral mapped into the address space of `ExecCpu`.
+///
+/// Addresses passed to `read` and `write` are offsets from the start of the
+/// range the device is mapped at.
+pub trait Device: Debug + AsAny {
+    fn read(&mut self, offset: u16) -> u16;
+    fn write(&mut self, offset: u16, val: u16);
+
+    /// State kept in snapshots, nothing for devices without state
+    fn save(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Restore state written by `save` of the same kind of device
+    fn load(&mut self, _data: &[u8]) -> Result<(), SnapshotError> {
+        Ok(())
+    }
+
+    fn duplicate(&self) -> Box<dyn Device>;
+}
+
+impl Clone for Box<dyn Device> {
+    fn clone(&self) -> Self {
+        self.duplicate()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Ram {
+    data: Vec<u16>,
+}
+
+impl Ram {
+    pub fn new(mut data: Vec<u16>, size: usize) -> Self {
+        data.resize(size, 0);
+        Ram { data }
+    }
+}
+
+impl Device for Ram {
+    fn read(&mut self, offset: u16) -> u16 {
+        *self.data.get(offset as usize).unwrap_or(&0)
+    }
+
+    fn write(&mut self, offset: u16, val: u16) {
+        if let Some(cell) = self.data.get_mut(offset as usize) {
+            *cell = val;
+        }
+    }
+
+    /// Size followed by the runs of words between long zero runs
+    fn save(&self) -> Vec<u8> {
+        let mut out = Writer(Vec::new());
+        out.u32(self.data.len() as u32);
+
+        let mut at = 0;
+        while at < self.data.len() {
+            if self.data[at] == 0 {
+                at += 1;
+                continue;
+            }
+            let start = at;
+            let mut zeros = 0;
+            while at < self.data.len() && zeros < MIN_GAP {
+                zeros = if self.data[at] == 0 { zeros + 1 } else { 0 };
+                at += 1;
+            }
+            let words = &self.data[start..at - zeros];
+            out.u32(start as u32);
+            out.u32(words.len() as u32);
+            words.iter().for_each(|word| out.u16(*word));
+        }
+        out.0
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        let mut input = Reader { data, at: 0 };
+        let size = input.u32()? as usize;
+        if size != self.data.len() {
+            return Err(SnapshotError::Layout(format!(
+                "{} words of RAM instead of {}",
+                size,
+                self.data.len()
+            )));
+        }
+
+        let mut memory = vec![0; size];
+        while !input.is_empty() {
+            let start = input.u32()? as usize;
+            let len = input.u32()? as usize;
+            let words = memory
+                .get_mut(start..start.saturating_add(len))
+                .ok_or_else(|| SnapshotError::Invalid(String::from("words past the RAM")))?;
+            for word in words {
+                *word = input.u16()?;
+            }
+        }
+        self.data = memory;
+        Ok(())
+    }
+
+    fn duplicate(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+}
+
+/// The keep-running register, only the lowest bit is stored and reads give 0
+#[derive(Clone, Debug)]
+pub struct HaltRegister {
+    pub keep_running: bool,
+}
+
+impl HaltRegister {
+    pub fn new() -> Self {
+        HaltRegister { keep_running: true }
+    }
+}
+
+impl Device for HaltRegister {
+    fn read(&mut self, _: u16) -> u16 {
+        // Write only in `hdl/mem.sv`, debuggers use `ExecCpu::is_running`
+        0
+    }
+
+    fn write(&mut self, _: u16, val: u16) {
+        self.keep_running = val & 1 == 1;
+    }
+
+    fn save(&self) -> Vec<u8> {
+        vec![self.keep_running as u8]
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        self.keep_running = Reader { data, at: 0 }.u8()? & 1 == 1;
+        Ok(())
+    }
+
+    fn duplicate(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Mapping {
+    range: RangeInclusive<u16>,
+    device: Box<dyn Device>,
+}
+
+/// Maps address ranges to devices, later mappings shadow earlier ones
+#[derive(Clone, Debug, Default)]
+pub struct Bus {
+    mappings: Vec<Mapping>,
+}
+
+impl Bus {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn map(&mut self, range: RangeInclusive<u16>, device: Box<dyn Device>) {
+        self.mappings.push(Mapping { range, device });
+    }
+
+    pub fn unmap(&mut self, start: u16) -> Option<Box<dyn Device>> {
+        let idx = self
+            .mappings
+            .iter()
+            .rposition(|m| *m.range.start() == start)?;
+        Some(self.mappings.remove(idx).device)
+    }
+
+    fn lookup(&mut self, addr: u16) -> Option<(u16, &mut Box<dyn Device>)> {
+        self.mappings
+            .iter_mut()
+            .rev()
+            .find(|m| m.range.contains(&addr))
+            .map(|m| (addr - m.range.start(), &mut m.device))
+    }
+
+    pub fn read(&mut self, addr: u16) -> u16 {
+        match self.lookup(addr) {
+            Some((offset, device)) => device.read(offset),
+            None => 0,
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, val: u16) {
+        if let Some((offset, device)) = self.lookup(addr) {
+            device.write(offset, val)
+        }
+    }
+
+    /// Address is served by `Ram`, which returns what was written last
+    pub fn is_ram(&self, addr: u16) -> bool {
+        self.mappings
+            .iter()
+            .rev()
+            .find(|m| m.range.contains(&addr))
+            .is_some_and(|m| m.device.as_ref().as_any().is::<Ram>())
+    }
+
+    /// Ranges and devices in the order they were mapped
+    pub fn mappings(&self) -> impl Iterator<Item = (&RangeInclusive<u16>, &dyn Device)> {
+        self.mappings.iter().map(|m| (&m.range, m.device.as_ref()))
+    }
+
+    pub fn mappings_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (&RangeInclusive<u16>, &mut Box<dyn Device>)> {
+        self.mappings.iter_mut().map(|m| (&m.range, &mut m.device))
+    }
+
+    pub fn device<T: Device + 'static>(&self) -> Option<&T> {
+        self.mappings
+            .iter()
+            .rev()
+            .find_map(|m| m.device.as_ref().as_any().downcast_ref())
+    }
+
+    pub fn device_mut<T: Device + 'static>(&mut self) -> Option<&mut T> {
+        self.mappings
+            .iter_mut()
+            .rev()
+            .find_map(|m| m.device.as_mut().as_any_mut().downcast_mut())
+    }
+}
@@ -0,0 +1,49 @@
+use crate::cpu;
+
+/// Decoded instructions by address, so fetching them again skips the bus.
+///
+/// Only words of RAM are cached, every write to one of them drops it.
+#[derive(Clone, Debug)]
+pub struct DecodeCache {
+    // Filled on the first fetch, cloning an unused cache stays cheap
+    entries: Vec<Option<cpu::Instruction>>,
+    enabled: bool,
+}
+
+impl DecodeCache {
+    pub fn new() -> Self {
+        DecodeCache {
+            entries: Vec::new(),
+            enabled: true,
+        }
+    }
+
+    pub fn get(&self, addr: u16) -> Option<cpu::Instruction> {
+        self.entries.get(addr as usize).copied().flatten()
+    }
+
+    pub fn insert(&mut self, addr: u16, ins: cpu::Instruction) {
+        if !self.enabled {
+            return;
+        }
+        if self.entries.is_empty() {
+            self.entries.resize(0xffff + 1, None);
+        }
+        self.entries[addr as usize] = Some(ins);
+    }
+
+    pub fn invalidate(&mut self, addr: u16) {
+        if let Some(entry) = self.entries.get_mut(addr as usize) {
+            *entry = None;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.clear();
+    }
+}
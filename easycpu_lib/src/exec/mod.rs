@@ -0,0 +1,353 @@
+pub mod bus;
+pub mod decode;
+pub mod observer;
+pub mod run;
+pub mod serial;
+pub mod snapshot;
+pub mod timing;
+
+use std::{any::TypeId, fmt::Debug, mem, ops::{AddAssign, RangeInclusive}};
+
+use crate::{cpu, AsAny};
+
+pub use bus::{Bus, Device, HaltRegister, Ram};
+pub use decode::DecodeCache;
+pub use observer::ExecObserver;
+pub use run::{RegCompare, RegCondition, RunLimits, StopReason};
+pub use serial::SerialPort;
+pub use snapshot::{Snapshot, SnapshotError};
+pub use timing::Timing;
+
+pub const HALT_ADDR: u16 = 0xffff;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecEvent {
+    NONE,
+    JUMP(u16),
+    REGGET(cpu::Register, u16),
+    REGSET(cpu::Register, u16),
+    MEMGET(u16, u16),
+    MEMSET(u16, u16),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExecStats {
+    pub nop: usize,
+    pub branch: usize,
+    pub store: usize,
+    pub load: usize,
+    pub alu: usize,
+    /// Clock cycles according to the `Timing` of the cpu
+    pub cycles: usize,
+}
+
+impl AddAssign<&Self> for ExecStats {
+    fn add_assign(&mut self, rhs: &Self) {
+        self.nop += rhs.nop;
+        self.branch += rhs.branch;
+        self.store += rhs.store;
+        self.load += rhs.load;
+        self.alu += rhs.alu;
+        self.cycles += rhs.cycles;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExecCpu {
+    pc: u16,
+    registers: [u16; 6],
+    bus: Bus,
+    decoded: DecodeCache,
+    events: Vec<ExecEvent>,
+    // Events are only collected for `exec_next` and watches of `run`
+    record: bool,
+    // Instruction is executing, accesses are reported to the observers
+    stepping: bool,
+    jumped: bool,
+    // Called without dynamic dispatch, `None` once detached
+    stats: Option<ExecStats>,
+    timing: Timing,
+    // Attached after `stats`
+    observers: Vec<Box<dyn ExecObserver>>,
+}
+
+const NO_STATS: ExecStats = ExecStats {
+    nop: 0,
+    branch: 0,
+    store: 0,
+    load: 0,
+    alu: 0,
+    cycles: 0,
+};
+
+impl ExecCpu {
+    /// Read register without recording an event
+    pub fn peek_reg(&self, reg: crate::cpu::Register) -> u16 {
+        match reg {
+            cpu::Register::ZX => 0,
+            cpu::Register::PC => self.pc,
+            cpu::Register::R2 => self.registers[0],
+            cpu::Register::R3 => self.registers[1],
+            cpu::Register::R4 => self.registers[2],
+            cpu::Register::R5 => self.registers[3],
+            cpu::Register::LP => self.registers[4],
+            cpu::Register::SP => self.registers[5],
+        }
+    }
+
+    pub fn get_reg(&mut self, reg: crate::cpu::Register) -> u16 {
+        let val = self.peek_reg(reg);
+        if self.record {
+            self.events.push(ExecEvent::REGGET(reg, val));
+        }
+        val
+    }
+
+    pub fn set_reg(&mut self, reg: crate::cpu::Register, val: u16) {
+        if self.record && reg != cpu::Register::PC {
+            self.events.push(ExecEvent::REGSET(reg, val));
+        }
+        if self.stepping && !self.observers.is_empty() {
+            self.observers.iter_mut().for_each(|o| o.register_write(reg, val));
+        }
+
+        match reg {
+            cpu::Register::ZX => (),
+            cpu::Register::PC => {
+                self.jumped = true;
+                if self.record {
+                    self.events.push(ExecEvent::JUMP(val));
+                }
+                self.pc = val;
+            }
+            cpu::Register::R2 => self.registers[0] = val,
+            cpu::Register::R3 => self.registers[1] = val,
+            cpu::Register::R4 => self.registers[2] = val,
+            cpu::Register::R5 => self.registers[3] = val,
+            cpu::Register::LP => self.registers[4] = val,
+            cpu::Register::SP => self.registers[5] = val,
+        }
+    }
+
+    pub fn get_mem(&mut self, addr: u16) -> u16 {
+        let val = self.bus.read(addr);
+        if self.record {
+            self.events.push(ExecEvent::MEMGET(addr, val));
+        }
+        if self.stepping && !self.observers.is_empty() {
+            self.observers.iter_mut().for_each(|o| o.memory_read(addr, val));
+        }
+
+        val
+    }
+
+    pub fn set_mem(&mut self, addr: u16, val: u16) {
+        if self.record {
+            self.events.push(ExecEvent::MEMSET(addr, val));
+        }
+        if self.stepping {
+            if let Some(stats) = &mut self.stats {
+                if addr == HALT_ADDR && val & 1 == 0 {
+                    stats.cycles += self.timing.halt();
+                }
+            }
+            if !self.observers.is_empty() {
+                self.observers.iter_mut().for_each(|o| o.memory_write(addr, val));
+            }
+        }
+        self.decoded.invalidate(addr);
+        self.bus.write(addr, val)
+    }
+}
+
+impl ExecCpu {
+    pub fn new(init_ram: Vec<u16>) -> Self {
+        let mut bus = Bus::new();
+        bus.map(0..=0xffff, Box::new(Ram::new(init_ram, 0xffff + 1)));
+        bus.map(serial::SERIAL_BASE..=serial::SERIAL_END, Box::new(SerialPort::new()));
+        bus.map(HALT_ADDR..=HALT_ADDR, Box::new(HaltRegister::new()));
+
+        Self::with_bus(bus)
+    }
+
+    pub fn with_bus(bus: Bus) -> Self {
+        Self {
+            pc: 0,
+            registers: [0; 6],
+            bus,
+            decoded: DecodeCache::new(),
+            events: Vec::new(),
+            record: false,
+            stepping: false,
+            jumped: false,
+            stats: Some(ExecStats::default()),
+            timing: Timing::Off,
+            observers: Vec::new(),
+        }
+    }
+
+    /// Map a device over `range`, shadowing whatever was mapped there before
+    pub fn map_device(&mut self, range: RangeInclusive<u16>, device: Box<dyn Device>) {
+        self.decoded.clear();
+        self.bus.map(range, device)
+    }
+
+    pub fn bus(&self) -> &Bus {
+        &self.bus
+    }
+
+    /// Bus for changes not going through `set_mem`, decoded code is dropped
+    pub fn bus_mut(&mut self) -> &mut Bus {
+        self.decoded.clear();
+        &mut self.bus
+    }
+
+    /// Decode every fetched word again instead of caching it
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decoded.set_enabled(enabled);
+    }
+
+    pub fn device<T: Device + 'static>(&self) -> Option<&T> {
+        self.bus.device()
+    }
+
+    pub fn device_mut<T: Device + 'static>(&mut self) -> Option<&mut T> {
+        if TypeId::of::<T>() == TypeId::of::<Ram>() {
+            self.decoded.clear();
+        }
+        self.bus.device_mut()
+    }
+
+    /// Machine keeps running until the halt register is cleared
+    pub fn is_running(&self) -> bool {
+        self.device::<HaltRegister>()
+            .is_none_or(|halt| halt.keep_running)
+    }
+
+    pub fn serial(&self) -> Option<&SerialPort> {
+        self.device()
+    }
+
+    pub fn serial_mut(&mut self) -> Option<&mut SerialPort> {
+        self.device_mut()
+    }
+
+    /// Add an observer called after the ones attached before, `ExecStats`
+    /// is attached from the start and called without dynamic dispatch
+    pub fn attach(&mut self, observer: Box<dyn ExecObserver>) {
+        self.observers.push(observer);
+    }
+
+    /// Remove the last attached observer of type `T`
+    pub fn detach<T: ExecObserver + 'static>(&mut self) -> Option<Box<dyn ExecObserver>> {
+        match self
+            .observers
+            .iter()
+            .rposition(|o| o.as_ref().as_any().is::<T>())
+        {
+            Some(idx) => Some(self.observers.remove(idx)),
+            None if TypeId::of::<T>() == TypeId::of::<ExecStats>() => {
+                let stats = self.stats.take()?;
+                Some(Box::new(stats))
+            }
+            None => None,
+        }
+    }
+
+    pub fn observer<T: ExecObserver + 'static>(&self) -> Option<&T> {
+        self.observers
+            .iter()
+            .rev()
+            .find_map(|o| o.as_ref().as_any().downcast_ref())
+            .or_else(|| self.stats.as_ref()?.as_any().downcast_ref())
+    }
+
+    pub fn observer_mut<T: ExecObserver + 'static>(&mut self) -> Option<&mut T> {
+        let idx = self
+            .observers
+            .iter()
+            .rposition(|o| o.as_ref().as_any().is::<T>());
+        match idx {
+            Some(idx) => self.observers[idx].as_mut().as_any_mut().downcast_mut(),
+            None => self.stats.as_mut()?.as_any_mut().downcast_mut(),
+        }
+    }
+
+    /// Zero the counters
+    pub fn reset_stats(&mut self) {
+        if let Some(stats) = self.observer_mut::<ExecStats>() {
+            *stats = ExecStats::default();
+        }
+    }
+
+    /// Count clock cycles of the `ExecStats` attached from the start with
+    /// `timing`
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+    }
+
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Statistics of the attached `ExecStats`, all zero once it is detached
+    pub fn get_stats(&self) -> &ExecStats {
+        self.observer().unwrap_or(&NO_STATS)
+    }
+
+    /// Execute one instruction and return the events it caused
+    pub fn exec_next(&mut self) -> (cpu::Instruction, Vec<ExecEvent>) {
+        let record = mem::replace(&mut self.record, true);
+        let ins = self.step();
+        self.record = record;
+
+        (ins, mem::take(&mut self.events))
+    }
+
+    /// Execute one instruction without collecting events unless `run` is
+    /// watching memory
+    pub fn step(&mut self) -> cpu::Instruction {
+        let pc = self.pc;
+        let ins = match self.decoded.get(pc) {
+            Some(ins) => ins,
+            None => {
+                let ins = cpu::Instruction::decode(self.bus.read(pc));
+                // Other devices may answer differently on every read
+                if self.bus.is_ram(pc) {
+                    self.decoded.insert(pc, ins);
+                }
+                ins
+            }
+        };
+
+        self.jumped = false;
+        self.events.clear();
+        if let Some(stats) = &mut self.stats {
+            stats.before_instruction(pc, &ins);
+            stats.cycles += self.timing.instruction();
+        }
+        if !self.observers.is_empty() {
+            self.observers.iter_mut().for_each(|o| o.before_instruction(pc, &ins));
+        }
+
+        self.stepping = true;
+        ins.execute(self);
+        self.stepping = false;
+
+        if !self.jumped {
+            self.pc = self.pc.wrapping_add(1);
+        }
+
+        // `ExecStats` only counts before the instruction
+        if !self.observers.is_empty() {
+            if let cpu::Instruction::BRANCH(branch) = &ins {
+                let target = pc.wrapping_add(branch.shift as i16 as u16);
+                let taken = self.jumped;
+                self.observers.iter_mut().for_each(|o| o.branch(pc, target, taken));
+            }
+            self.observers.iter_mut().for_each(|o| o.after_instruction(pc, &ins));
+        }
+
+        ins
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt::Debug;
+
+use crate::{cpu, AsAny};
+
+use super::ExecStats;
+
+/// Hooks into the instructions `ExecCpu` executes.
+///
+/// Observers are called in the order they were attached. Register and memory
+/// hooks only see accesses of instructions, not the ones made from outside
+/// through `set_reg` or `get_mem`. Instruction fetches are not memory reads.
+pub trait ExecObserver: Debug + AsAny {
+    fn before_instruction(&mut self, _pc: u16, _ins: &cpu::Instruction) {}
+
+    /// PC already points at the next instruction
+    fn after_instruction(&mut self, _pc: u16, _ins: &cpu::Instruction) {}
+
+    fn register_write(&mut self, _reg: cpu::Register, _val: u16) {}
+
+    fn memory_read(&mut self, _addr: u16, _val: u16) {}
+
+    fn memory_write(&mut self, _addr: u16, _val: u16) {}
+
+    /// Branch at `pc` to `target` either taken or not
+    fn branch(&mut self, _pc: u16, _target: u16, _taken: bool) {}
+
+    fn duplicate(&self) -> Box<dyn ExecObserver>;
+}
+
+impl Clone for Box<dyn ExecObserver> {
+    fn clone(&self) -> Self {
+        self.duplicate()
+    }
+}
+
+impl ExecObserver for ExecStats {
+    fn before_instruction(&mut self, _: u16, ins: &cpu::Instruction) {
+        match ins {
+            cpu::Instruction::NOP => self.nop += 1,
+            cpu::Instruction::AND(_) | cpu::Instruction::ADD(_) => self.alu += 1,
+            cpu::Instruction::LOAD(_) => self.load += 1,
+            cpu::Instruction::STORE(_) => self.store += 1,
+            cpu::Instruction::BRANCH(_) => self.branch += 1,
+            cpu::Instruction::CUSTOM(_) => self.nop += 1,
+        }
+    }
+
+    fn duplicate(&self) -> Box<dyn ExecObserver> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+
+use crate::{asm::disasm::call_len, cpu};
+
+use super::{ExecCpu, ExecEvent};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RegCompare {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RegCondition {
+    pub reg: cpu::Register,
+    pub cmp: RegCompare,
+    pub val: u16,
+}
+
+impl RegCondition {
+    pub fn new(reg: cpu::Register, cmp: RegCompare, val: u16) -> Self {
+        RegCondition { reg, cmp, val }
+    }
+
+    pub fn check(&self, cpu: &ExecCpu) -> bool {
+        let cur = cpu.peek_reg(self.reg);
+        match self.cmp {
+            RegCompare::Eq => cur == self.val,
+            RegCompare::Ne => cur != self.val,
+            RegCompare::Lt => cur < self.val,
+            RegCompare::Gt => cur > self.val,
+        }
+    }
+}
+
+/// Conditions on which `ExecCpu::run` gives control back
+#[derive(Clone, Debug, Default)]
+pub struct RunLimits {
+    pub max_steps: Option<usize>,
+    pub breakpoints: HashSet<u16>,
+    pub read_watch: HashSet<u16>,
+    pub write_watch: HashSet<u16>,
+    pub conditions: Vec<RegCondition>,
+}
+
+impl RunLimits {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    pub fn breakpoint(mut self, addr: u16) -> Self {
+        self.breakpoints.insert(addr);
+        self
+    }
+
+    pub fn watch_read(mut self, addr: u16) -> Self {
+        self.read_watch.insert(addr);
+        self
+    }
+
+    pub fn watch_write(mut self, addr: u16) -> Self {
+        self.write_watch.insert(addr);
+        self
+    }
+
+    pub fn condition(mut self, cond: RegCondition) -> Self {
+        self.conditions.push(cond);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StopReason {
+    Halted,
+    StepLimit,
+    Breakpoint(u16),
+    ReadWatch { pc: u16, addr: u16, val: u16 },
+    WriteWatch { pc: u16, addr: u16, val: u16 },
+    Condition(RegCondition),
+}
+
+impl ExecCpu {
+    /// Execute instructions until one of `limits` is hit or the cpu halts.
+    ///
+    /// Breakpoint at the current PC is ignored for the first instruction, so
+    /// calling `run` again continues past it.
+    pub fn run(&mut self, limits: &RunLimits) -> StopReason {
+        let mut steps = 0;
+        // Without watches no events are needed, which keeps the loop free of allocations
+        let watching = !limits.read_watch.is_empty() || !limits.write_watch.is_empty();
+
+        loop {
+            if !self.is_running() {
+                return StopReason::Halted;
+            }
+
+            if steps != 0 && limits.breakpoints.contains(&self.pc) {
+                return StopReason::Breakpoint(self.pc);
+            }
+
+            if limits.max_steps.is_some_and(|max| steps >= max) {
+                return StopReason::StepLimit;
+            }
+
+            let pc = self.pc;
+            self.record = watching;
+            self.step();
+            self.record = false;
+            steps += 1;
+
+            let watched = self.events.iter().find_map(|event| match *event {
+                ExecEvent::MEMGET(addr, val) if limits.read_watch.contains(&addr) => {
+                    Some(StopReason::ReadWatch { pc, addr, val })
+                }
+                ExecEvent::MEMSET(addr, val) if limits.write_watch.contains(&addr) => {
+                    Some(StopReason::WriteWatch { pc, addr, val })
+                }
+                _ => None,
+            });
+            if let Some(reason) = watched {
+                return reason;
+            }
+
+            if let Some(cond) = limits.conditions.iter().find(|c| c.check(self)) {
+                return StopReason::Condition(*cond);
+            }
+        }
+    }
+
+    /// Execute the instruction at PC, or the whole `$CALL` sequence PC is in
+    /// until the call returns. Other `limits` still apply, reaching the
+    /// return address is reported as `StopReason::StepLimit`.
+    pub fn next(&mut self, limits: &RunLimits) -> StopReason {
+        let Some(ret) = self.call_return() else {
+            return self.run(&limits.clone().steps(1));
+        };
+
+        match self.run(&limits.clone().breakpoint(ret)) {
+            StopReason::Breakpoint(addr) if addr == ret && !limits.breakpoints.contains(&ret) => {
+                StopReason::StepLimit
+            }
+            reason => reason,
+        }
+    }
+
+    /// Return address of the `$CALL` sequence PC is in, before its jump
+    fn call_return(&mut self) -> Option<u16> {
+        // Two literals follow the jump ending the sequence
+        (0..5).find_map(|back| {
+            let at = self.pc.wrapping_sub(back);
+            let words: Vec<u16> = (0..8).map(|i| self.bus.read(at.wrapping_add(i))).collect();
+            let len = call_len(at, &words)?;
+            (back as usize + 2 < len).then(|| at.wrapping_add(len as u16))
+        })
+    }
+}
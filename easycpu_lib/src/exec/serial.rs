@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+
+use crate::bytes::{Reader, Writer};
+
+use super::bus::Device;
+use super::snapshot::SnapshotError;
+
+/// First address of the serial port registers, see `hdl/mem.sv`
+pub const SERIAL_BASE: u16 = 0xF100;
+/// Last address of the serial port registers
+pub const SERIAL_END: u16 = 0xF107;
+
+// Register offsets from SERIAL_BASE
+pub const SERIAL_INP_POS: u16 = 0;
+pub const SERIAL_INP_READ_POS: u16 = 1;
+pub const SERIAL_INP_WINDOW: u16 = 2;
+pub const SERIAL_OUT_POS: u16 = 4;
+pub const SERIAL_OUT_WRITE_POS: u16 = 5;
+pub const SERIAL_OUT_WINDOW: u16 = 6;
+pub const SERIAL_OUT_WINDOW_BYTE: u16 = 7;
+
+const POS_MASK: u8 = 0x7f;
+const BUF_BITS: u8 = 128;
+
+/// Emulation of the serial port from `hdl/mem.sv`.
+///
+/// Both directions use a 128 bit ring buffer addressed by 7 bit positions.
+/// Bytes are transfered most significant bit first. Instead of modelling the
+/// line timing, bytes pushed by the host are shifted into the input ring as
+/// soon as there is room for them and bits are transmitted to the host as
+/// soon as the program moves the write position.
+#[derive(Clone, Debug, Default)]
+pub struct SerialPort {
+    inp_buf: u128,
+    out_buf: u128,
+
+    cur_inp_pos: u8,
+    read_inp_pos: u8,
+    cur_out_pos: u8,
+    write_out_pos: u8,
+
+    host_input: VecDeque<u8>,
+    host_output: Vec<u8>,
+
+    out_byte: u8,
+    out_bits: u8,
+}
+
+fn get_bit(buf: u128, pos: u8) -> bool {
+    (buf >> (pos & POS_MASK)) & 1 == 1
+}
+
+fn set_bit(buf: &mut u128, pos: u8, val: bool) {
+    let mask = 1u128 << (pos & POS_MASK);
+    if val {
+        *buf |= mask;
+    } else {
+        *buf &= !mask;
+    }
+}
+
+/// Reads `width` bits starting at `pos`, first bit ends up in the most
+/// significant position
+fn read_window(buf: u128, pos: u8, width: u8) -> u16 {
+    (0..width).fold(0, |res, i| {
+        (res << 1) | (get_bit(buf, pos.wrapping_add(i)) as u16)
+    })
+}
+
+fn write_window(buf: &mut u128, pos: u8, width: u8, val: u16) {
+    for i in 0..width {
+        let bit = (val >> (width - 1 - i)) & 1 == 1;
+        set_bit(buf, pos.wrapping_add(i), bit);
+    }
+}
+
+impl SerialPort {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Queue bytes sent by the host to the program
+    pub fn push_input(&mut self, data: &[u8]) {
+        self.host_input.extend(data);
+        self.receive();
+    }
+
+    /// Number of host bytes which were not yet shifted into the input ring
+    pub fn pending_input(&self) -> usize {
+        self.host_input.len()
+    }
+
+    /// Take bytes transmitted by the program since the previous call
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.host_output)
+    }
+
+    pub fn output(&self) -> &[u8] {
+        &self.host_output
+    }
+
+    fn unread_bits(&self) -> u8 {
+        self.cur_inp_pos.wrapping_sub(self.read_inp_pos) & POS_MASK
+    }
+
+    fn receive(&mut self) {
+        // Positions are 7 bit wide, so a full ring would look empty
+        while self.unread_bits() + 8 < BUF_BITS {
+            let Some(byte) = self.host_input.pop_front() else {
+                break;
+            };
+
+            write_window(&mut self.inp_buf, self.cur_inp_pos, 8, byte as u16);
+            self.cur_inp_pos = self.cur_inp_pos.wrapping_add(8) & POS_MASK;
+        }
+    }
+
+    fn transmit(&mut self) {
+        while self.cur_out_pos != self.write_out_pos {
+            let bit = get_bit(self.out_buf, self.cur_out_pos);
+            self.cur_out_pos = self.cur_out_pos.wrapping_add(1) & POS_MASK;
+
+            self.out_byte = (self.out_byte << 1) | bit as u8;
+            self.out_bits += 1;
+            if self.out_bits == 8 {
+                self.host_output.push(self.out_byte);
+                self.out_byte = 0;
+                self.out_bits = 0;
+            }
+        }
+    }
+}
+
+impl Device for SerialPort {
+    fn read(&mut self, offset: u16) -> u16 {
+        match offset {
+            SERIAL_INP_POS => self.cur_inp_pos as u16,
+            SERIAL_INP_READ_POS => self.read_inp_pos as u16,
+            SERIAL_INP_WINDOW => read_window(self.inp_buf, self.read_inp_pos, 16),
+            SERIAL_OUT_POS => self.cur_out_pos as u16,
+            SERIAL_OUT_WRITE_POS => self.write_out_pos as u16,
+            SERIAL_OUT_WINDOW => read_window(self.out_buf, self.write_out_pos, 16),
+            SERIAL_OUT_WINDOW_BYTE => read_window(self.out_buf, self.write_out_pos, 8),
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u16, val: u16) {
+        match offset {
+            SERIAL_INP_READ_POS => {
+                self.read_inp_pos = (val as u8) & POS_MASK;
+                self.receive();
+            }
+            SERIAL_OUT_WRITE_POS => {
+                self.write_out_pos = (val as u8) & POS_MASK;
+                self.transmit();
+            }
+            SERIAL_OUT_WINDOW => write_window(&mut self.out_buf, self.write_out_pos, 16, val),
+            SERIAL_OUT_WINDOW_BYTE => write_window(&mut self.out_buf, self.write_out_pos, 8, val),
+            _ => (),
+        }
+    }
+
+    fn save(&self) -> Vec<u8> {
+        let mut out = Writer(Vec::new());
+        out.u128(self.inp_buf);
+        out.u128(self.out_buf);
+        out.u8(self.cur_inp_pos);
+        out.u8(self.read_inp_pos);
+        out.u8(self.cur_out_pos);
+        out.u8(self.write_out_pos);
+        out.u8(self.out_byte);
+        out.u8(self.out_bits);
+        out.blob(&self.host_input.iter().copied().collect::<Vec<_>>());
+        out.blob(&self.host_output);
+        out.0
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        let mut input = Reader { data, at: 0 };
+        *self = SerialPort {
+            inp_buf: input.u128()?,
+            out_buf: input.u128()?,
+            cur_inp_pos: input.u8()? & POS_MASK,
+            read_inp_pos: input.u8()? & POS_MASK,
+            cur_out_pos: input.u8()? & POS_MASK,
+            write_out_pos: input.u8()? & POS_MASK,
+            out_byte: input.u8()?,
+            out_bits: input.u8()? % 8,
+            host_input: input.blob()?.iter().copied().collect(),
+            host_output: input.blob()?.to_vec(),
+        };
+        Ok(())
+    }
+
+    fn duplicate(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,235 @@
+use std::{fmt, ops::RangeInclusive};
+
+use crate::bytes::{crc32, ReadError, Reader, Writer};
+
+use super::{ExecCpu, ExecStats, Timing};
+
+pub const MAGIC: &[u8; 4] = b"ECPS";
+pub const VERSION: u16 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    Invalid(String),
+    UnsupportedVersion(u16),
+    Checksum,
+    /// Devices of the machine do not match the snapshot
+    Layout(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Invalid(msg) => write!(f, "invalid snapshot: {}", msg),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {}", version)
+            }
+            SnapshotError::Checksum => write!(f, "snapshot checksum does not match"),
+            SnapshotError::Layout(msg) => write!(f, "snapshot does not fit the machine: {}", msg),
+        }
+    }
+}
+
+impl From<ReadError> for SnapshotError {
+    fn from(value: ReadError) -> Self {
+        let msg = match value {
+            ReadError::Truncated => "truncated",
+            ReadError::NotUtf8 => "text is not UTF-8",
+        };
+        SnapshotError::Invalid(String::from(msg))
+    }
+}
+
+/// Saved state of the device mapped at `range`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceState {
+    pub range: RangeInclusive<u16>,
+    pub data: Vec<u8>,
+}
+
+/// State of a machine to continue it later.
+///
+/// Stored big-endian as the `ECPS` magic, version, PC, registers, the
+/// counters of `ExecStats`, the timing and the state of every mapped device, closed by a
+/// CRC-32 of everything before it. Memory is the state of `Ram`, which leaves
+/// out long zero runs.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub pc: u16,
+    /// R2 to R5, LP and SP
+    pub registers: [u16; 6],
+    pub stats: ExecStats,
+    pub timing: Timing,
+    pub devices: Vec<DeviceState>,
+}
+
+fn timing_id(timing: Timing) -> u8 {
+    match timing {
+        Timing::Off => 0,
+        Timing::Hdl => 1,
+    }
+}
+
+fn timing_from_id(id: u8) -> Result<Timing, SnapshotError> {
+    match id {
+        0 => Ok(Timing::Off),
+        1 => Ok(Timing::Hdl),
+        _ => Err(SnapshotError::Invalid(format!("unknown timing {}", id))),
+    }
+}
+
+impl Snapshot {
+    pub fn write(&self) -> Vec<u8> {
+        let mut out = Writer(MAGIC.to_vec());
+        out.u16(VERSION);
+        out.u16(self.pc);
+        self.registers.iter().for_each(|reg| out.u16(*reg));
+
+        let stats = &self.stats;
+        for count in [
+            stats.nop,
+            stats.branch,
+            stats.store,
+            stats.load,
+            stats.alu,
+            stats.cycles,
+        ] {
+            out.u64(count as u64);
+        }
+        out.u8(timing_id(self.timing));
+
+        out.u16(self.devices.len() as u16);
+        for device in self.devices.iter() {
+            out.u16(*device.range.start());
+            out.u16(*device.range.end());
+            out.blob(&device.data);
+        }
+
+        let crc = crc32(&out.0);
+        out.u32(crc);
+        out.0
+    }
+
+    pub fn read(data: &[u8]) -> Result<Self, SnapshotError> {
+        if !data.starts_with(MAGIC) {
+            return Err(SnapshotError::Invalid(String::from("missing magic")));
+        }
+        if data.len() < MAGIC.len() + 4 {
+            return Err(SnapshotError::Invalid(String::from("truncated")));
+        }
+        let (body, crc) = data.split_at(data.len() - 4);
+        if crc32(body).to_be_bytes() != crc {
+            return Err(SnapshotError::Checksum);
+        }
+
+        let mut input = Reader {
+            data: body,
+            at: MAGIC.len(),
+        };
+        let version = input.u16()?;
+        if version != VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let pc = input.u16()?;
+        let mut registers = [0; 6];
+        for reg in registers.iter_mut() {
+            *reg = input.u16()?;
+        }
+
+        let mut counts = [0; 6];
+        for count in counts.iter_mut() {
+            *count = input.u64()? as usize;
+        }
+        let [nop, branch, store, load, alu, cycles] = counts;
+        let stats = ExecStats {
+            nop,
+            branch,
+            store,
+            load,
+            alu,
+            cycles,
+        };
+        let timing = timing_from_id(input.u8()?)?;
+
+        let mut devices = Vec::new();
+        for _ in 0..input.u16()? {
+            let start = input.u16()?;
+            let end = input.u16()?;
+            let data = input.blob()?.to_vec();
+            devices.push(DeviceState {
+                range: start..=end,
+                data,
+            });
+        }
+
+        if !input.is_empty() {
+            return Err(SnapshotError::Invalid(String::from("trailing data")));
+        }
+        Ok(Snapshot {
+            pc,
+            registers,
+            stats,
+            timing,
+            devices,
+        })
+    }
+}
+
+impl ExecCpu {
+    /// Registers, devices and `ExecStats`, other observers are not saved
+    pub fn snapshot(&self) -> Snapshot {
+        let devices = self
+            .bus
+            .mappings()
+            .map(|(range, device)| DeviceState {
+                range: range.clone(),
+                data: device.save(),
+            })
+            .collect();
+
+        Snapshot {
+            pc: self.pc,
+            registers: self.registers,
+            stats: self.get_stats().clone(),
+            timing: self.timing,
+            devices,
+        }
+    }
+
+    /// Continue from `snapshot`, devices have to be mapped at the same ranges
+    /// as in the machine it was taken from. Nothing changes on an error
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        let mut bus = self.bus.clone();
+        let count = bus.mappings().count();
+        if count != snapshot.devices.len() {
+            return Err(SnapshotError::Layout(format!(
+                "{} devices instead of {}",
+                snapshot.devices.len(),
+                count
+            )));
+        }
+        for ((range, device), state) in bus.mappings_mut().zip(snapshot.devices.iter()) {
+            if *range != state.range {
+                return Err(SnapshotError::Layout(format!(
+                    "device at {:#06x}..={:#06x} instead of {:#06x}..={:#06x}",
+                    state.range.start(),
+                    state.range.end(),
+                    range.start(),
+                    range.end()
+                )));
+            }
+            device.load(&state.data)?;
+        }
+
+        self.bus = bus;
+        self.decoded.clear();
+        self.events.clear();
+        self.pc = snapshot.pc;
+        self.registers = snapshot.registers;
+        self.timing = snapshot.timing;
+        if let Some(stats) = self.observer_mut::<ExecStats>() {
+            *stats = snapshot.stats.clone();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+/// Clock cycles counted into `ExecStats::cycles`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Timing {
+    /// Cycles are not counted
+    #[default]
+    Off,
+    /// State machine of `hdl/our.sv`
+    Hdl,
+}
+
+impl Timing {
+    /// Cycles of every instruction.
+    ///
+    /// The HDL fetches an instruction in READ_INSTRUCTION and executes it in
+    /// STAGE_E. A memory access is started in STAGE_E and completes during
+    /// the next fetch, so loads and stores take as long as the other
+    /// instructions.
+    pub fn instruction(&self) -> usize {
+        match self {
+            Timing::Off => 0,
+            Timing::Hdl => 2,
+        }
+    }
+
+    /// Cycles after the store clearing the halt register until the machine
+    /// stops: the next instruction is still fetched and decoded, then the
+    /// HALT state raises `halt_out`
+    pub fn halt(&self) -> usize {
+        match self {
+            Timing::Off => 0,
+            Timing::Hdl => 3,
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use crate::bytes::{crc32, ReadError, Reader, Writer};
+use crate::compile::{CompiledProgram, SourceMap, Symbol, SymbolTable};
+use crate::image::{read_image, ImageError, ImageFormat};
+use crate::parser::ParsePosition;
+
+pub const MAGIC: &[u8; 4] = b"ECPX";
+pub const VERSION: u16 = 1;
+
+const HAS_SYMBOLS: u16 = 1;
+const HAS_SOURCE_MAP: u16 = 2;
+
+const MEMORY_WORDS: usize = 0x10000;
+// Zero runs at least this long are left out between segments
+const MIN_GAP: usize = 16;
+// Position of defines and other atoms without a source file
+const NO_FILE: u16 = 0xffff;
+
+/// Words loaded at `addr`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub addr: u16,
+    pub words: Vec<u16>,
+}
+
+/// Program with its load layout and debug information.
+///
+/// Stored big-endian as the `ECPX` magic, version, flags, entry PC and
+/// segments followed by the optional symbol table and source map, closed by
+/// a CRC-32 of everything before it
+#[derive(Clone, Debug, Default)]
+pub struct Executable {
+    pub entry: u16,
+    pub segments: Vec<Segment>,
+    pub symbols: Option<SymbolTable>,
+    pub source_map: Option<SourceMap>,
+    /// Names of the files positions in `source_map` refer to
+    pub files: Vec<String>,
+}
+
+impl From<ReadError> for ImageError {
+    fn from(value: ReadError) -> Self {
+        let msg = match value {
+            ReadError::Truncated => "truncated",
+            ReadError::NotUtf8 => "name is not UTF-8",
+        };
+        ImageError::InvalidExecutable(String::from(msg))
+    }
+}
+
+fn write_pos(out: &mut Writer, pos: &ParsePosition) {
+    out.u16(u16::try_from(pos.file).unwrap_or(NO_FILE));
+    out.u32(pos.line as u32);
+    out.u32(pos.line_pos as u32);
+    out.u32(pos.pos as u32);
+}
+
+fn read_pos(input: &mut Reader) -> Result<ParsePosition, ReadError> {
+    let file = match input.u16()? {
+        NO_FILE => usize::MAX,
+        file => file as usize,
+    };
+    Ok(ParsePosition {
+        file,
+        line: input.u32()? as usize,
+        line_pos: input.u32()? as usize,
+        pos: input.u32()? as usize,
+    })
+}
+
+impl Executable {
+    /// Single segment at address 0 without debug information
+    pub fn from_code(code: &[u16]) -> Self {
+        Executable {
+            segments: vec![Segment {
+                addr: 0,
+                words: code.to_vec(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// Code split into segments around long zero runs, like those left by
+    /// `.org` and `.space`, together with symbols and the source map
+    pub fn from_program(program: &CompiledProgram) -> Self {
+        let code = &program.code;
+        let mut segments = Vec::new();
+        let mut start = 0;
+        let mut at = 0;
+        while at < code.len() {
+            let run = code[at..].iter().take_while(|w| **w == 0).count();
+            if run >= MIN_GAP && at + run < code.len() {
+                if at > start {
+                    segments.push(Segment {
+                        addr: start as u16,
+                        words: code[start..at].to_vec(),
+                    });
+                }
+                start = at + run;
+            }
+            at += run.max(1);
+        }
+        if start < code.len() || segments.is_empty() {
+            segments.push(Segment {
+                addr: start as u16,
+                words: code[start..].to_vec(),
+            });
+        }
+
+        Executable {
+            entry: 0,
+            segments,
+            symbols: Some(program.symbols.clone()).filter(|s| !s.is_empty()),
+            source_map: Some(program.source_map.clone()).filter(|m| !m.is_empty()),
+            files: Vec::new(),
+        }
+    }
+
+    /// Checks only the magic, anything else is taken as a raw image
+    pub fn is_executable(data: &[u8]) -> bool {
+        data.starts_with(MAGIC)
+    }
+
+    /// Reads an image of any format, raw binaries are taken as executables
+    /// when they start with the magic
+    pub fn load(format: ImageFormat, data: &[u8]) -> Result<Self, ImageError> {
+        match format {
+            ImageFormat::Executable => Self::read(data),
+            ImageFormat::Bin if Self::is_executable(data) => Self::read(data),
+            _ => Ok(Self::from_code(&read_image(format, data)?)),
+        }
+    }
+
+    /// Memory contents from address 0 up to the end of the last segment
+    pub fn memory(&self) -> Vec<u16> {
+        let mut memory = Vec::new();
+        for segment in self.segments.iter() {
+            let start = segment.addr as usize;
+            let end = start + segment.words.len();
+            if memory.len() < end {
+                memory.resize(end, 0);
+            }
+            memory[start..end].copy_from_slice(&segment.words);
+        }
+        memory
+    }
+
+    pub fn write(&self) -> Vec<u8> {
+        let mut flags = 0;
+        if self.symbols.is_some() {
+            flags |= HAS_SYMBOLS;
+        }
+        if self.source_map.is_some() {
+            flags |= HAS_SOURCE_MAP;
+        }
+
+        let mut out = Writer(MAGIC.to_vec());
+        out.u16(VERSION);
+        out.u16(flags);
+        out.u16(self.entry);
+
+        out.u16(self.segments.len() as u16);
+        for segment in self.segments.iter() {
+            out.u16(segment.addr);
+            out.u32(segment.words.len() as u32);
+            for word in segment.words.iter() {
+                out.u16(*word);
+            }
+        }
+
+        if let Some(symbols) = &self.symbols {
+            out.u32(symbols.len() as u32);
+            for symbol in symbols.iter() {
+                out.u16(symbol.addr);
+                out.str(&symbol.full_name());
+            }
+        }
+
+        if let Some(map) = &self.source_map {
+            out.u16(self.files.len() as u16);
+            for file in self.files.iter() {
+                out.str(file);
+            }
+            out.u32(map.len() as u32);
+            for (start, end) in map.iter() {
+                write_pos(&mut out, start);
+                write_pos(&mut out, end);
+            }
+        }
+
+        let crc = crc32(&out.0);
+        out.u32(crc);
+        out.0
+    }
+
+    pub fn read(data: &[u8]) -> Result<Self, ImageError> {
+        if !Self::is_executable(data) {
+            return Err(ImageError::InvalidExecutable(String::from("missing magic")));
+        }
+        if data.len() < MAGIC.len() + 4 {
+            return Err(ImageError::InvalidExecutable(String::from("truncated")));
+        }
+        let (body, crc) = data.split_at(data.len() - 4);
+        if crc32(body).to_be_bytes() != crc {
+            return Err(ImageError::ExecutableChecksum);
+        }
+
+        let mut input = Reader {
+            data: body,
+            at: MAGIC.len(),
+        };
+        let version = input.u16()?;
+        if version != VERSION {
+            return Err(ImageError::UnsupportedVersion(version));
+        }
+        let flags = input.u16()?;
+        let mut exe = Executable {
+            entry: input.u16()?,
+            ..Default::default()
+        };
+
+        for _ in 0..input.u16()? {
+            let addr = input.u16()?;
+            let len = input.u32()? as usize;
+            if addr as usize + len > MEMORY_WORDS {
+                return Err(ImageError::TooLarge);
+            }
+            let words = (0..len).map(|_| input.u16()).collect::<Result<_, _>>()?;
+            exe.segments.push(Segment { addr, words });
+        }
+
+        if flags & HAS_SYMBOLS != 0 {
+            let mut symbols = SymbolTable::new();
+            for _ in 0..input.u32()? {
+                let addr = input.u16()?;
+                symbols.push(Symbol::from_full_name(&input.str()?, addr));
+            }
+            exe.symbols = Some(symbols);
+        }
+
+        if flags & HAS_SOURCE_MAP != 0 {
+            for _ in 0..input.u16()? {
+                exe.files.push(input.str()?);
+            }
+            let mut map = SourceMap::new();
+            for _ in 0..input.u32()? {
+                map.push((read_pos(&mut input)?, read_pos(&mut input)?));
+            }
+            exe.source_map = Some(map);
+        }
+
+        if input.at != body.len() {
+            return Err(ImageError::InvalidExecutable(String::from("trailing data")));
+        }
+        Ok(exe)
+    }
+}
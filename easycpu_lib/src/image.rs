@@ -0,0 +1,442 @@
+use std::{fmt, path::Path};
+
+use crate::executable::Executable;
+
+/// Memory image formats understood by `write_image` and `read_image`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Raw big-endian words
+    Bin,
+    /// Verilog `$readmemh` text, one hex word per line
+    ReadMemH,
+    /// Verilog `$readmemb` text, one binary word per line
+    ReadMemB,
+    /// Intel HEX with byte addresses and big-endian words
+    IntelHex,
+    /// Logisim `v2.0 raw` ROM image
+    Logisim,
+    /// C array of `unsigned short`
+    C,
+    /// Rust `[u16; N]` constant
+    Rust,
+    /// `ECPX` container with segments, entry PC and debug information
+    Executable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    OddLength(usize),
+    InvalidNumber(usize, String),
+    InvalidRecord(usize, String),
+    InvalidChecksum(usize),
+    MissingHeader,
+    MissingArray,
+    TooLarge,
+    InvalidExecutable(String),
+    UnsupportedVersion(u16),
+    ExecutableChecksum,
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::OddLength(len) => write!(f, "binary image has odd length {}", len),
+            ImageError::InvalidNumber(line, num) => {
+                write!(f, "line {}: invalid number {:?}", line, num)
+            }
+            ImageError::InvalidRecord(line, msg) => write!(f, "line {}: {}", line, msg),
+            ImageError::InvalidChecksum(line) => write!(f, "line {}: checksum mismatch", line),
+            ImageError::MissingHeader => write!(f, "missing `v2.0 raw` header"),
+            ImageError::MissingArray => write!(f, "no array initializer found"),
+            ImageError::TooLarge => write!(f, "image does not fit into 65536 words"),
+            ImageError::InvalidExecutable(msg) => write!(f, "invalid executable: {}", msg),
+            ImageError::UnsupportedVersion(version) => {
+                write!(f, "unsupported executable version {}", version)
+            }
+            ImageError::ExecutableChecksum => write!(f, "executable checksum mismatch"),
+        }
+    }
+}
+
+const MEMORY_WORDS: usize = 0x10000;
+const C_NAME: &str = "easycpu_image";
+const RUST_NAME: &str = "EASYCPU_IMAGE";
+
+impl ImageFormat {
+    pub const ALL: [ImageFormat; 8] = [
+        ImageFormat::Bin,
+        ImageFormat::ReadMemH,
+        ImageFormat::ReadMemB,
+        ImageFormat::IntelHex,
+        ImageFormat::Logisim,
+        ImageFormat::C,
+        ImageFormat::Rust,
+        ImageFormat::Executable,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImageFormat::Bin => "bin",
+            ImageFormat::ReadMemH => "memh",
+            ImageFormat::ReadMemB => "memb",
+            ImageFormat::IntelHex => "ihex",
+            ImageFormat::Logisim => "logisim",
+            ImageFormat::C => "c",
+            ImageFormat::Rust => "rust",
+            ImageFormat::Executable => "exe",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ImageFormat> {
+        Self::ALL
+            .into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Guess the format from a file extension, unknown extensions are raw binaries
+    pub fn from_path(path: &Path) -> ImageFormat {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match ext.as_str() {
+            "mem" | "memh" => ImageFormat::ReadMemH,
+            "memb" => ImageFormat::ReadMemB,
+            "hex" | "ihex" | "ihx" => ImageFormat::IntelHex,
+            "lgs" | "logisim" => ImageFormat::Logisim,
+            "h" | "c" => ImageFormat::C,
+            "rs" => ImageFormat::Rust,
+            "ecx" => ImageFormat::Executable,
+            _ => ImageFormat::Bin,
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+pub fn write_image(format: ImageFormat, code: &[u16]) -> Vec<u8> {
+    match format {
+        ImageFormat::Bin => code.iter().flat_map(|x| x.to_be_bytes()).collect(),
+        ImageFormat::ReadMemH => write_lines(code, |w| format!("{:04x}", w)),
+        ImageFormat::ReadMemB => write_lines(code, |w| format!("{:016b}", w)),
+        ImageFormat::IntelHex => write_intel_hex(code).into_bytes(),
+        ImageFormat::Logisim => write_logisim(code).into_bytes(),
+        ImageFormat::C => write_array(
+            code,
+            &format!("const unsigned short {}[{}] = {{", C_NAME, code.len()),
+            "};",
+        ),
+        ImageFormat::Rust => write_array(
+            code,
+            &format!("pub const {}: [u16; {}] = [", RUST_NAME, code.len()),
+            "];",
+        ),
+        ImageFormat::Executable => Executable::from_code(code).write(),
+    }
+}
+
+pub fn read_image(format: ImageFormat, data: &[u8]) -> Result<Vec<u16>, ImageError> {
+    let code = match format {
+        ImageFormat::Bin => {
+            if !data.len().is_multiple_of(2) {
+                return Err(ImageError::OddLength(data.len()));
+            }
+            data.chunks(2)
+                .map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]]))
+                .collect()
+        }
+        ImageFormat::ReadMemH => read_readmem(&String::from_utf8_lossy(data), 16)?,
+        ImageFormat::ReadMemB => read_readmem(&String::from_utf8_lossy(data), 2)?,
+        ImageFormat::IntelHex => read_intel_hex(&String::from_utf8_lossy(data))?,
+        ImageFormat::Logisim => read_logisim(&String::from_utf8_lossy(data))?,
+        ImageFormat::C | ImageFormat::Rust => read_array(&String::from_utf8_lossy(data))?,
+        ImageFormat::Executable => Executable::read(data)?.memory(),
+    };
+
+    if code.len() > MEMORY_WORDS {
+        return Err(ImageError::TooLarge);
+    }
+    Ok(code)
+}
+
+fn write_lines(code: &[u16], word: impl Fn(u16) -> String) -> Vec<u8> {
+    let mut out = String::new();
+    for w in code {
+        out.push_str(&word(*w));
+        out.push('\n');
+    }
+    out.into_bytes()
+}
+
+fn write_array(code: &[u16], open: &str, close: &str) -> Vec<u8> {
+    let mut out = format!("/* easycpu image, {} words */\n{}\n", code.len(), open);
+    for row in code.chunks(8) {
+        let words: Vec<_> = row.iter().map(|w| format!("{:#06x},", w)).collect();
+        out.push_str(&format!("    {}\n", words.join(" ")));
+    }
+    out.push_str(close);
+    out.push('\n');
+    out.into_bytes()
+}
+
+fn hex_record(addr: u16, kind: u8, data: &[u8]) -> String {
+    let [hi, lo] = addr.to_be_bytes();
+    let mut sum = (data.len() as u8)
+        .wrapping_add(hi)
+        .wrapping_add(lo)
+        .wrapping_add(kind);
+    let mut out = format!(":{:02X}{:04X}{:02X}", data.len(), addr, kind);
+    for b in data {
+        sum = sum.wrapping_add(*b);
+        out.push_str(&format!("{:02X}", b));
+    }
+    out.push_str(&format!("{:02X}\n", sum.wrapping_neg()));
+    out
+}
+
+fn write_intel_hex(code: &[u16]) -> String {
+    let bytes: Vec<u8> = code.iter().flat_map(|x| x.to_be_bytes()).collect();
+    let mut out = String::new();
+    for (i, chunk) in bytes.chunks(16).enumerate() {
+        let addr = i * 16;
+        // Switch the upper address half once the 64K byte window is exceeded
+        if addr % 0x10000 == 0 && addr > 0 {
+            out.push_str(&hex_record(0, 4, &((addr >> 16) as u16).to_be_bytes()));
+        }
+        out.push_str(&hex_record(addr as u16, 0, chunk));
+    }
+    out.push_str(&hex_record(0, 1, &[]));
+    out
+}
+
+fn write_logisim(code: &[u16]) -> String {
+    let mut words = Vec::new();
+    let mut rest = code;
+    while let Some(first) = rest.first() {
+        let run = rest.iter().take_while(|w| *w == first).count();
+        if run > 3 {
+            words.push(format!("{}*{:x}", run, first));
+        } else {
+            words.extend(rest[..run].iter().map(|w| format!("{:x}", w)));
+        }
+        rest = &rest[run..];
+    }
+
+    let mut out = String::from("v2.0 raw\n");
+    for row in words.chunks(8) {
+        out.push_str(&row.join(" "));
+        out.push('\n');
+    }
+    out
+}
+
+fn parse_word(token: &str, radix: u32, line: usize) -> Result<u16, ImageError> {
+    u16::from_str_radix(&token.replace('_', ""), radix)
+        .map_err(|_| ImageError::InvalidNumber(line, token.to_owned()))
+}
+
+fn strip_comment<'a>(line: &'a str, marker: &str) -> &'a str {
+    line.split_once(marker).map_or(line, |(code, _)| code)
+}
+
+fn set_word(code: &mut Vec<u16>, addr: usize, word: u16) -> Result<(), ImageError> {
+    if addr >= MEMORY_WORDS {
+        return Err(ImageError::TooLarge);
+    }
+    if code.len() <= addr {
+        code.resize(addr + 1, 0);
+    }
+    code[addr] = word;
+    Ok(())
+}
+
+fn read_readmem(text: &str, radix: u32) -> Result<Vec<u16>, ImageError> {
+    let mut code = Vec::new();
+    let mut addr = 0;
+    let mut in_block = false;
+
+    for (i, line) in text.lines().enumerate() {
+        let mut line = line;
+        // Block comments may span lines, only their text is skipped
+        let mut rest = String::new();
+        loop {
+            if in_block {
+                match line.split_once("*/") {
+                    Some((_, after)) => {
+                        in_block = false;
+                        line = after;
+                    }
+                    None => break,
+                }
+            } else {
+                match line.split_once("/*") {
+                    Some((before, after)) => {
+                        rest.push_str(before);
+                        rest.push(' ');
+                        in_block = true;
+                        line = after;
+                    }
+                    None => {
+                        rest.push_str(line);
+                        break;
+                    }
+                }
+            }
+        }
+
+        for token in strip_comment(&rest, "//").split_whitespace() {
+            if let Some(target) = token.strip_prefix('@') {
+                addr = parse_word(target, 16, i + 1)? as usize;
+                continue;
+            }
+            set_word(&mut code, addr, parse_word(token, radix, i + 1)?)?;
+            addr += 1;
+        }
+    }
+
+    Ok(code)
+}
+
+fn read_intel_hex(text: &str) -> Result<Vec<u16>, ImageError> {
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut base = 0;
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let invalid = |msg: &str| ImageError::InvalidRecord(i + 1, msg.to_owned());
+
+        let hex = line
+            .strip_prefix(':')
+            .ok_or_else(|| invalid("record does not start with `:`"))?;
+        if hex.len() % 2 != 0 || hex.len() < 10 {
+            return Err(invalid("truncated record"));
+        }
+        // Digits are checked first, slicing other characters could split them
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ImageError::InvalidNumber(i + 1, line.to_owned()));
+        }
+        let record = (0..hex.len())
+            .step_by(2)
+            .map(|at| u8::from_str_radix(&hex[at..at + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| ImageError::InvalidNumber(i + 1, line.to_owned()))?;
+
+        let len = record[0] as usize;
+        if record.len() != len + 5 {
+            return Err(invalid("record length mismatch"));
+        }
+        if record.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+            return Err(ImageError::InvalidChecksum(i + 1));
+        }
+
+        let offset = u16::from_be_bytes([record[1], record[2]]) as usize;
+        let data = &record[4..4 + len];
+        match record[3] {
+            0 => {
+                let start = base + offset;
+                if start + len > MEMORY_WORDS * 2 {
+                    return Err(ImageError::TooLarge);
+                }
+                if bytes.len() < start + len {
+                    bytes.resize(start + len, 0);
+                }
+                bytes[start..start + len].copy_from_slice(data);
+            }
+            1 => break,
+            2 if len == 2 => base = (u16::from_be_bytes([data[0], data[1]]) as usize) << 4,
+            4 if len == 2 => base = (u16::from_be_bytes([data[0], data[1]]) as usize) << 16,
+            // Start address records carry nothing we can use
+            3 | 5 => (),
+            _ => return Err(invalid("unsupported record type")),
+        }
+    }
+
+    if !bytes.len().is_multiple_of(2) {
+        bytes.push(0);
+    }
+    Ok(bytes
+        .chunks(2)
+        .map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]]))
+        .collect())
+}
+
+fn read_logisim(text: &str) -> Result<Vec<u16>, ImageError> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.trim() == "v2.0 raw" => (),
+        _ => return Err(ImageError::MissingHeader),
+    }
+
+    let mut code = Vec::new();
+    for (i, line) in lines {
+        for token in strip_comment(line, "#").split_whitespace() {
+            let (count, word) = match token.split_once('*') {
+                Some((count, word)) => (
+                    count
+                        .parse::<usize>()
+                        .map_err(|_| ImageError::InvalidNumber(i + 1, token.to_owned()))?,
+                    word,
+                ),
+                None => (1, token),
+            };
+            let word = parse_word(word, 16, i + 1)?;
+            if code.len().checked_add(count).is_none_or(|len| len > MEMORY_WORDS) {
+                return Err(ImageError::TooLarge);
+            }
+            code.extend(std::iter::repeat_n(word, count));
+        }
+    }
+    Ok(code)
+}
+
+fn parse_literal(token: &str, line: usize) -> Result<u16, ImageError> {
+    let invalid = || ImageError::InvalidNumber(line, token.to_owned());
+    let digits = token
+        .trim_end_matches("u16")
+        .trim_end_matches(['u', 'U'])
+        .replace('_', "");
+
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => match digits.strip_prefix("0b") {
+            Some(bin) => u32::from_str_radix(bin, 2),
+            None => digits.parse(),
+        },
+    }
+    .map_err(|_| invalid())?;
+    u16::try_from(value).map_err(|_| invalid())
+}
+
+fn read_array(text: &str) -> Result<Vec<u16>, ImageError> {
+    // Initializer is the bracketed list after the first `=`
+    let (_, init) = text.split_once('=').ok_or(ImageError::MissingArray)?;
+    let open = init.find(['{', '[']).ok_or(ImageError::MissingArray)?;
+    let close = init.rfind(['}', ']']).ok_or(ImageError::MissingArray)?;
+    if close < open {
+        return Err(ImageError::MissingArray);
+    }
+    let line_base = text[..text.len() - init.len()].lines().count();
+
+    let mut code = Vec::new();
+    let lines = init[open + 1..close].split_inclusive('\n');
+    for (line, part) in (line_base..).zip(lines) {
+        let part = strip_comment(part, "//");
+        for token in part.split([',', ' ', '\t', '\n', '\r']) {
+            if !token.is_empty() {
+                code.push(parse_literal(token, line.max(1))?);
+            }
+        }
+    }
+    Ok(code)
+}
@@ -1,11 +1,19 @@
 #![allow(clippy::new_without_default)]
+// Errors carry both ends of the range, macro call sites and a note, they are
+// only built once assembly fails
+#![allow(clippy::result_large_err)]
 
 pub mod cpu;
 pub mod exec;
 pub mod asm;
 pub mod parser;
 pub mod compile;
+pub mod image;
+pub mod executable;
+pub mod link;
+pub mod diagnostic;
 
 pub(crate) mod asany;
+pub(crate) mod bytes;
 pub mod stack;
 pub(crate) use asany::AsAny;
\ No newline at end of file
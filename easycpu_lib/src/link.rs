@@ -0,0 +1,242 @@
+use std::{collections::HashMap, fmt};
+
+use crate::compile::{CompiledProgram, Symbol, SymbolTable};
+
+const OBJECT_HEADER: &str = "EASYCPU OBJECT 1";
+const WORDS_PER_LINE: usize = 8;
+
+/// Word at `offset` holds a PC-relative reference to `symbol` of another
+/// object, compiled as if the symbol was at address 0
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Relocation {
+    pub offset: u16,
+    pub symbol: String,
+}
+
+/// Separately assembled part of a program. Addresses are relative to the
+/// start of the object
+#[derive(Clone, Debug, Default)]
+pub struct Object {
+    pub code: Vec<u16>,
+    pub exports: SymbolTable,
+    pub relocations: Vec<Relocation>,
+    pub symbols: SymbolTable,
+    /// Code uses absolute addresses and has to be placed at address 0
+    pub position_dependent: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    Unresolved(String),
+    Duplicate(String),
+    /// Index of a position dependent object which would not be placed at 0
+    NotRelocatable(usize),
+    TooLarge,
+    InvalidObject(String),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::Unresolved(name) => write!(f, "unresolved symbol `{}`", name),
+            LinkError::Duplicate(name) => write!(f, "symbol `{}` is exported more than once", name),
+            LinkError::NotRelocatable(idx) => write!(
+                f,
+                "object {} uses absolute addresses and has to be linked first",
+                idx + 1
+            ),
+            LinkError::TooLarge => write!(f, "linked program does not fit into 65536 words"),
+            LinkError::InvalidObject(msg) => write!(f, "invalid object: {}", msg),
+        }
+    }
+}
+
+impl From<CompiledProgram> for Object {
+    fn from(program: CompiledProgram) -> Self {
+        Object {
+            code: program.code,
+            exports: program.exports,
+            relocations: program.relocations,
+            symbols: program.symbols,
+            position_dependent: program.position_dependent,
+        }
+    }
+}
+
+fn parse_addr(line: usize, text: Option<&str>) -> Result<u16, LinkError> {
+    let text = text.unwrap_or_default();
+    u16::from_str_radix(text.trim_start_matches("0x"), 16)
+        .map_err(|_| LinkError::InvalidObject(format!("line {}: invalid address {:?}", line, text)))
+}
+
+impl Object {
+    /// Reads the text format written by `Display`
+    pub fn parse(text: &str) -> Result<Object, LinkError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| (idx + 1, line.trim()));
+        match lines.next() {
+            Some((_, OBJECT_HEADER)) => {}
+            _ => {
+                return Err(LinkError::InvalidObject(format!(
+                    "missing `{}`",
+                    OBJECT_HEADER
+                )))
+            }
+        }
+
+        let mut object = Object::default();
+        let mut in_code = false;
+        for (line, content) in lines {
+            if content.is_empty() {
+                continue;
+            }
+
+            if in_code {
+                for word in content.split_whitespace() {
+                    let word = u16::from_str_radix(word, 16).map_err(|_| {
+                        LinkError::InvalidObject(format!("line {}: invalid word {:?}", line, word))
+                    })?;
+                    object.code.push(word);
+                }
+                continue;
+            }
+
+            let mut words = content.split_whitespace();
+            let record = words.next().unwrap_or_default();
+            match record {
+                "ABSOLUTE" => object.position_dependent = true,
+                "CODE" => in_code = true,
+                "EXPORT" | "RELOC" | "SYMBOL" => {
+                    let addr = parse_addr(line, words.next())?;
+                    let name = words.next().ok_or_else(|| {
+                        LinkError::InvalidObject(format!("line {}: missing name", line))
+                    })?;
+                    match record {
+                        "EXPORT" => object.exports.push(Symbol::from_full_name(name, addr)),
+                        "SYMBOL" => object.symbols.push(Symbol::from_full_name(name, addr)),
+                        _ => object.relocations.push(Relocation {
+                            offset: addr,
+                            symbol: name.to_owned(),
+                        }),
+                    }
+                }
+                _ => {
+                    return Err(LinkError::InvalidObject(format!(
+                        "line {}: unknown record {:?}",
+                        line, record
+                    )))
+                }
+            }
+        }
+
+        if !in_code {
+            return Err(LinkError::InvalidObject("missing `CODE`".to_owned()));
+        }
+        Ok(object)
+    }
+}
+
+/// Text object file, a header followed by one record per line and the code
+/// as hex words
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", OBJECT_HEADER)?;
+        if self.position_dependent {
+            writeln!(f, "ABSOLUTE")?;
+        }
+        for symbol in self.exports.iter() {
+            writeln!(f, "EXPORT {:#06x} {}", symbol.addr, symbol.full_name())?;
+        }
+        for reloc in self.relocations.iter() {
+            writeln!(f, "RELOC {:#06x} {}", reloc.offset, reloc.symbol)?;
+        }
+        for symbol in self.symbols.iter() {
+            writeln!(f, "SYMBOL {:#06x} {}", symbol.addr, symbol.full_name())?;
+        }
+
+        writeln!(f, "CODE")?;
+        for chunk in self.code.chunks(WORDS_PER_LINE) {
+            let words: Vec<String> = chunk.iter().map(|w| format!("{:04x}", w)).collect();
+            writeln!(f, "{}", words.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Places the objects one after another starting at address 0 and resolves
+/// the relocations of each against the exports of all
+pub fn link(objects: &[Object]) -> Result<CompiledProgram, Vec<LinkError>> {
+    let mut errors = Vec::new();
+
+    let mut bases = Vec::with_capacity(objects.len());
+    let mut len = 0usize;
+    for (idx, object) in objects.iter().enumerate() {
+        if object.position_dependent && len != 0 {
+            errors.push(LinkError::NotRelocatable(idx));
+        }
+        bases.push(len as u16);
+        len += object.code.len();
+    }
+    if len > 0x10000 {
+        return Err(vec![LinkError::TooLarge]);
+    }
+
+    let mut globals: HashMap<String, u16> = HashMap::new();
+    let mut exports = SymbolTable::new();
+    for (object, base) in objects.iter().zip(bases.iter()) {
+        for symbol in object.exports.iter() {
+            let addr = symbol.addr.wrapping_add(*base);
+            if globals.insert(symbol.full_name(), addr).is_some() {
+                errors.push(LinkError::Duplicate(symbol.full_name()));
+            }
+            exports.push(Symbol {
+                addr,
+                ..symbol.clone()
+            });
+        }
+    }
+
+    let mut program = CompiledProgram::default();
+    for (object, base) in objects.iter().zip(bases.iter()) {
+        let start = program.code.len();
+        program.code.extend_from_slice(&object.code);
+
+        for reloc in object.relocations.iter() {
+            let Some(addr) = globals.get(&reloc.symbol) else {
+                let error = LinkError::Unresolved(reloc.symbol.clone());
+                if !errors.contains(&error) {
+                    errors.push(error);
+                }
+                continue;
+            };
+
+            let word = program
+                .code
+                .get_mut(start + reloc.offset as usize)
+                .ok_or_else(|| {
+                    vec![LinkError::InvalidObject(format!(
+                        "relocation at {:#06x} past the end of the code",
+                        reloc.offset
+                    ))]
+                })?;
+            *word = word.wrapping_add(*addr).wrapping_sub(*base);
+        }
+
+        for symbol in object.symbols.iter() {
+            program.symbols.push(Symbol {
+                addr: symbol.addr.wrapping_add(*base),
+                ..symbol.clone()
+            });
+        }
+        program.position_dependent |= object.position_dependent;
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    program.exports = exports;
+    Ok(program)
+}
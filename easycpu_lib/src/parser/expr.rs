@@ -0,0 +1,293 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::compile::CompileError;
+
+use super::parse_parts::parse_number;
+
+/// Source of symbol values while an expression is evaluated
+pub trait SymbolEnv {
+    fn lookup(&self, name: &str) -> Result<i64, CompileError>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    LogicalNot,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinaryOp {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Xor,
+    Or,
+    LogicalAnd,
+    LogicalOr,
+}
+
+impl BinaryOp {
+    // Binding strength follows C, higher binds tighter
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 9,
+            BinaryOp::Add | BinaryOp::Sub => 8,
+            BinaryOp::Shl | BinaryOp::Shr => 7,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 6,
+            BinaryOp::Eq | BinaryOp::Ne => 5,
+            BinaryOp::And => 4,
+            BinaryOp::Xor => 3,
+            BinaryOp::Or => 2,
+            BinaryOp::LogicalAnd => 1,
+            BinaryOp::LogicalOr => 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            BinaryOp::Shl | BinaryOp::Shr | BinaryOp::Le | BinaryOp::Ge => 2,
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::LogicalAnd | BinaryOp::LogicalOr => 2,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Num(i64),
+    Symbol(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+pub fn symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '$' | '.')
+}
+
+struct ExprParser<'a> {
+    source: &'a str,
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> ExprParser<'a> {
+    fn invalid(&self) -> CompileError {
+        CompileError::InvalidExpression(self.source.to_owned())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.peek().copied()
+    }
+
+    fn binary_op(&mut self) -> Option<BinaryOp> {
+        let first = self.peek()?;
+        let second = self.chars.clone().nth(1);
+        let op = match (first, second) {
+            ('*', _) => BinaryOp::Mul,
+            ('/', _) => BinaryOp::Div,
+            ('%', _) => BinaryOp::Rem,
+            ('+', _) => BinaryOp::Add,
+            ('-', _) => BinaryOp::Sub,
+            ('<', Some('<')) => BinaryOp::Shl,
+            ('>', Some('>')) => BinaryOp::Shr,
+            ('<', Some('=')) => BinaryOp::Le,
+            ('>', Some('=')) => BinaryOp::Ge,
+            ('<', _) => BinaryOp::Lt,
+            ('>', _) => BinaryOp::Gt,
+            ('=', Some('=')) => BinaryOp::Eq,
+            ('!', Some('=')) => BinaryOp::Ne,
+            ('&', Some('&')) => BinaryOp::LogicalAnd,
+            ('|', Some('|')) => BinaryOp::LogicalOr,
+            ('&', _) => BinaryOp::And,
+            ('^', _) => BinaryOp::Xor,
+            ('|', _) => BinaryOp::Or,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    fn take_binary_op(&mut self, op: BinaryOp) {
+        for _ in 0..op.len() {
+            self.chars.next();
+        }
+    }
+
+    fn char_literal(&mut self) -> Result<Expr, CompileError> {
+        let c = match self.chars.next().ok_or_else(|| self.invalid())? {
+            '\\' => match self.chars.next().ok_or_else(|| self.invalid())? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                '0' => '\0',
+                c => c,
+            },
+            c => c,
+        };
+        if self.chars.next() != Some('\'') {
+            return Err(self.invalid());
+        }
+        Ok(Expr::Num(c as i64))
+    }
+
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        match self.peek().ok_or_else(|| self.invalid())? {
+            '(' => {
+                self.chars.next();
+                let expr = self.expr(0)?;
+                if self.peek() != Some(')') {
+                    return Err(self.invalid());
+                }
+                self.chars.next();
+                Ok(expr)
+            }
+            '-' | '~' | '!' | '+' => {
+                let op = self.chars.next();
+                let expr = self.primary()?;
+                Ok(match op {
+                    Some('-') => Expr::Unary(UnaryOp::Neg, Box::new(expr)),
+                    Some('~') => Expr::Unary(UnaryOp::Not, Box::new(expr)),
+                    Some('!') => Expr::Unary(UnaryOp::LogicalNot, Box::new(expr)),
+                    _ => expr,
+                })
+            }
+            '\'' => {
+                self.chars.next();
+                self.char_literal()
+            }
+            c if symbol_char(c) => {
+                let mut word = String::new();
+                while let Some(c) = self.chars.next_if(|c| symbol_char(*c)) {
+                    word.push(c);
+                }
+
+                if c.is_ascii_digit() {
+                    parse_number(&word.to_uppercase()).map(|x| Expr::Num(x as i64))
+                } else {
+                    Ok(Expr::Symbol(word))
+                }
+            }
+            _ => Err(self.invalid()),
+        }
+    }
+
+    fn expr(&mut self, min_precedence: u8) -> Result<Expr, CompileError> {
+        let mut lhs = self.primary()?;
+
+        while let Some(op) = self.binary_op() {
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.take_binary_op(op);
+
+            let rhs = self.expr(op.precedence() + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Expr, CompileError> {
+        let mut parser = ExprParser {
+            source,
+            chars: source.chars().peekable(),
+        };
+
+        let expr = parser.expr(0)?;
+        if parser.peek().is_some() {
+            return Err(parser.invalid());
+        }
+        Ok(expr)
+    }
+
+    /// Evaluate the expression, symbols are only available with an environment
+    pub fn eval(&self, env: Option<&dyn SymbolEnv>) -> Result<i64, CompileError> {
+        let overflow = || CompileError::ExpressionOverflow;
+
+        match self {
+            Expr::Num(val) => Ok(*val),
+            Expr::Symbol(name) => match env {
+                Some(env) => env.lookup(name),
+                None => Err(CompileError::DeferredExpression),
+            },
+            Expr::Unary(op, expr) => {
+                let val = expr.eval(env)?;
+                match op {
+                    UnaryOp::Neg => val.checked_neg().ok_or_else(overflow),
+                    UnaryOp::Not => Ok(!val),
+                    UnaryOp::LogicalNot => Ok((val == 0) as i64),
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = lhs.eval(env)?;
+                // Right side of `&&` and `||` is only evaluated when it decides the result
+                match op {
+                    BinaryOp::LogicalAnd if lhs == 0 => return Ok(0),
+                    BinaryOp::LogicalOr if lhs != 0 => return Ok(1),
+                    _ => (),
+                }
+
+                let rhs = rhs.eval(env)?;
+                let shift = || u32::try_from(rhs).map_err(|_| overflow());
+                match op {
+                    BinaryOp::Mul => lhs.checked_mul(rhs).ok_or_else(overflow),
+                    BinaryOp::Div if rhs == 0 => Err(CompileError::DivisionByZero),
+                    BinaryOp::Div => lhs.checked_div(rhs).ok_or_else(overflow),
+                    BinaryOp::Rem if rhs == 0 => Err(CompileError::DivisionByZero),
+                    BinaryOp::Rem => lhs.checked_rem(rhs).ok_or_else(overflow),
+                    BinaryOp::Add => lhs.checked_add(rhs).ok_or_else(overflow),
+                    BinaryOp::Sub => lhs.checked_sub(rhs).ok_or_else(overflow),
+                    // Bits shifted out of the value are lost, which `checked_shl` misses
+                    BinaryOp::Shl => {
+                        let n = shift()?;
+                        lhs.checked_shl(n)
+                            .filter(|val| val >> n == lhs)
+                            .ok_or_else(overflow)
+                    }
+                    BinaryOp::Shr => lhs.checked_shr(shift()?).ok_or_else(overflow),
+                    BinaryOp::Lt => Ok((lhs < rhs) as i64),
+                    BinaryOp::Le => Ok((lhs <= rhs) as i64),
+                    BinaryOp::Gt => Ok((lhs > rhs) as i64),
+                    BinaryOp::Ge => Ok((lhs >= rhs) as i64),
+                    BinaryOp::Eq => Ok((lhs == rhs) as i64),
+                    BinaryOp::Ne => Ok((lhs != rhs) as i64),
+                    BinaryOp::And => Ok(lhs & rhs),
+                    BinaryOp::Xor => Ok(lhs ^ rhs),
+                    BinaryOp::Or => Ok(lhs | rhs),
+                    BinaryOp::LogicalAnd | BinaryOp::LogicalOr => Ok((rhs != 0) as i64),
+                }
+            }
+        }
+    }
+}
+
+/// Word value of an expression, negative values are two's complement
+pub fn to_u16(val: i64) -> Result<u16, CompileError> {
+    match val {
+        -0x8000..=-1 => Ok(val as i16 as u16),
+        0..=0xffff => Ok(val as u16),
+        _ => Err(CompileError::ValueOutOfRange(val)),
+    }
+}
+
+pub fn to_i8(val: i64) -> Result<i8, CompileError> {
+    i8::try_from(val).map_err(|_| CompileError::ValueOutOfRange(val))
+}
@@ -1,7 +1,10 @@
+pub mod expr;
 pub mod parse_parts;
 pub mod parse;
 pub mod position;
+pub mod sources;
 
 pub use parse_parts::{ParseParts, ParsedLabel, convert_to_u16};
 pub use parse::ParseReader;
-pub use position::{ParsePosition, PosCompileError, CompileErrorWithPos};
\ No newline at end of file
+pub use position::{ErrorNote, ParsePosition, PosCompileError, CompileErrorWithPos};
+pub use sources::{FsLoader, NoLoader, SourceFile, SourceLoader, SourceSet};
\ No newline at end of file
@@ -1,4 +1,5 @@
 use crate::compile::CompileError;
+use std::collections::VecDeque;
 use std::iter::Peekable;
 use std::str::Chars;
 
@@ -19,6 +20,9 @@ pub fn end_checker(s: char) -> bool {
 pub struct ParseReader<'a> {
     pub inp: Peekable<&'a mut dyn Iterator<Item = char>>,
     pub pos: ParsePosition,
+
+    // Characters looked at past the next one
+    ahead: VecDeque<char>,
 }
 
 #[derive(PartialEq, Clone, Copy, Debug)]
@@ -50,18 +54,33 @@ impl BlockType {
 
 impl<'a> ParseReader<'a> {
     pub fn is_empty(&mut self) -> bool {
-        self.inp.peek().is_none()
+        self.ahead.is_empty() && self.inp.peek().is_none()
     }
 
     pub fn peek(&mut self) -> Result<char, PosCompileError> {
-        match self.inp.peek() {
+        match self.ahead.front().or(self.inp.peek()) {
             Some(c) => Ok(*c),
             None => Err(CompileError::UnexpectedEndOfFile.with_pos(self.pos)),
         }
     }
 
+    /// First non-whitespace character after the next `skip` characters
+    pub fn peek_significant(&mut self, skip: usize) -> Option<char> {
+        let mut idx = skip;
+        loop {
+            while self.ahead.len() <= idx {
+                let c = self.inp.next()?;
+                self.ahead.push_back(c);
+            }
+            if !self.ahead[idx].is_whitespace() {
+                return Some(self.ahead[idx]);
+            }
+            idx += 1;
+        }
+    }
+
     pub fn take(&mut self) -> Result<char, PosCompileError> {
-        match self.inp.next() {
+        match self.ahead.pop_front().or_else(|| self.inp.next()) {
             Some(c) => {
                 self.pos.next(c);
                 Ok(c)
@@ -95,13 +114,23 @@ impl<'a> ParseReader<'a> {
 
     pub fn take_block(&mut self) -> Result<Vec<char>, PosCompileError> {
         let mut res = Vec::new();
-        let mut block_stack: Vec<BlockType> = Vec::new();
+        // Open blocks with the position of their opening character
+        let mut block_stack: Vec<(BlockType, ParsePosition)> = Vec::new();
+        let first_pos = self.pos;
         let first_char = self.take()?;
         let mut top_token = BlockType::from_start_char(first_char)
-            .ok_or(CompileError::UnknownToken(first_char).with_pos(self.pos))?;
-        block_stack.push(top_token);
+            .ok_or(CompileError::UnknownToken(first_char).with_range(first_pos, self.pos))?;
+        block_stack.push((top_token, first_pos));
 
-        while !block_stack.is_empty() {
+        while let Some(&(_, open_pos)) = block_stack.last() {
+            let cur_pos = self.pos;
+            if self.is_empty() {
+                return Err(Self::unclosed(top_token, open_pos, cur_pos));
+            }
+            // Strings end on their line, so an open one does not swallow the file
+            if top_token == BlockType::String && self.peek()? == '\n' {
+                return Err(CompileError::UnterminatedString.with_range(open_pos, cur_pos));
+            }
             let cur_char = self.take()?;
             res.push(cur_char);
 
@@ -110,23 +139,36 @@ impl<'a> ParseReader<'a> {
                     res.push(self.take()?);
                 } else if cur_char == '\"' {
                     block_stack.pop();
-                    top_token = *block_stack.last().unwrap_or(&BlockType::Parentheses);
+                    top_token = block_stack.last().map_or(BlockType::Parentheses, |b| b.0);
                 }
             } else if let Some(new_block) = BlockType::from_start_char(cur_char) {
                 top_token = new_block;
-                block_stack.push(new_block)
+                block_stack.push((new_block, cur_pos))
             } else if let Some(close_block) = BlockType::from_end_char(cur_char) {
                 if top_token != close_block {
-                    return Err(CompileError::UnexpectedToken(cur_char).with_pos(self.pos));
+                    let err = CompileError::UnexpectedToken(cur_char);
+                    return Err(err.with_range(cur_pos, self.pos));
                 }
                 block_stack.pop();
-                top_token = *block_stack.last().unwrap_or(&BlockType::Parentheses);
+                top_token = block_stack.last().map_or(BlockType::Parentheses, |b| b.0);
             }
         }
 
         res.pop();
         Ok(res)
     }
+
+    /// Error at the start of a block the input ends in
+    fn unclosed(block: BlockType, start: ParsePosition, end: ParsePosition) -> PosCompileError {
+        let bracket = match block {
+            BlockType::String => return CompileError::UnterminatedString.with_range(start, end),
+            BlockType::CurlyBracket => '{',
+            BlockType::Parentheses => '(',
+        };
+        let mut after = start;
+        after.next(bracket);
+        CompileError::UnclosedBracket(bracket).with_range(start, after)
+    }
 }
 
 impl<'a> From<&'a mut Chars<'a>> for ParseReader<'a> {
@@ -136,6 +178,7 @@ impl<'a> From<&'a mut Chars<'a>> for ParseReader<'a> {
         let p: ParseReader = ParseReader {
             inp: iter_ref.peekable(),
             pos: ParsePosition::default(),
+            ahead: VecDeque::new(),
         };
         
         p
@@ -149,6 +192,7 @@ impl<'a> From<&'a mut std::vec::IntoIter<char>> for ParseReader<'a> {
         let p: ParseReader = ParseReader {
             inp: iter_ref.peekable(),
             pos: ParsePosition::default(),
+            ahead: VecDeque::new(),
         };
 
         p
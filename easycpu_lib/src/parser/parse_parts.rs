@@ -2,6 +2,8 @@ use crate::compile::{CompileContext, CompileError};
 use crate::cpu;
 use std::collections::VecDeque;
 
+use super::expr::{self, Expr, SymbolEnv};
+
 #[derive(Clone, Debug)]
 pub struct ParsedLabel {
     pub label: String,
@@ -62,18 +64,86 @@ pub fn parse_u16_constant(inp: &str) -> Result<u16, CompileError> {
     u16::try_from(num).map_err(|_| CompileError::InvalidNumber(String::from(inp)))
 }
 
+// Operators that can not start an operand
+const BINARY_OPERATORS: [char; 9] = ['*', '/', '%', '&', '|', '^', '<', '>', '='];
+
+/// Split operands on whitespace outside of parentheses and char literals.
+/// Spaces around binary operators stay inside one operand, a sign directly
+/// in front of a number starts a new one
+pub fn split_operands(s: &str) -> VecDeque<&str> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let (mut depth, mut quoted, mut escaped) = (0, false, false);
+    let mut start = None;
+
+    for (i, c) in s.char_indices() {
+        if quoted {
+            quoted = escaped || c != '\'';
+            escaped = !escaped && c == '\\';
+            continue;
+        }
+
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '\'' => quoted = true,
+            c if c.is_whitespace() && depth <= 0 => {
+                if let Some(begin) = start.take() {
+                    ranges.push((begin, i));
+                }
+                continue;
+            }
+            _ => (),
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(begin) = start {
+        ranges.push((begin, s.len()));
+    }
+
+    let mut joined: Vec<(usize, usize)> = Vec::new();
+    for (begin, end) in ranges {
+        let part = &s[begin..end];
+        let join = joined.last().is_some_and(|prev| {
+            let prev = &s[prev.0..prev.1];
+            prev.ends_with(BINARY_OPERATORS)
+                || prev.ends_with(['+', '-', '~', '!'])
+                || part.starts_with(BINARY_OPERATORS)
+                || part.starts_with("!=")
+                || part == "+"
+                || part == "-"
+        });
+
+        match joined.last_mut() {
+            Some(prev) if join => prev.1 = end,
+            _ => joined.push((begin, end)),
+        }
+    }
+
+    joined
+        .into_iter()
+        .map(|(begin, end)| &s[begin..end])
+        .collect()
+}
+
 #[derive(Clone)]
 pub struct ParseParts<'a> {
     vec: VecDeque<&'a str>,
+    env: Option<&'a dyn SymbolEnv>,
 }
 
 impl<'a> From<VecDeque<&'a str>> for ParseParts<'a> {
     fn from(vec: VecDeque<&'a str>) -> Self {
-        ParseParts { vec }
+        ParseParts { vec, env: None }
     }
 }
 
 impl<'a> ParseParts<'a> {
+    /// Operands referring to symbols fail with `DeferredExpression` without an environment
+    pub fn with_env(mut self, env: Option<&'a dyn SymbolEnv>) -> Self {
+        self.env = env;
+        self
+    }
+
     fn pop(&mut self) -> Result<&'a str, CompileError> {
         match self.vec.pop_front() {
             Some(el) => Ok(el),
@@ -81,6 +151,10 @@ impl<'a> ParseParts<'a> {
         }
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
     pub fn pop_command(&mut self) -> Result<&'a str, CompileError> {
         self.pop().map_err(|_| CompileError::NoCommandSupplied)
     }
@@ -106,15 +180,17 @@ impl<'a> ParseParts<'a> {
         }
     }
 
-    pub fn pop_shift(&mut self) -> Result<i8, CompileError> {
+    pub fn pop_expr(&mut self) -> Result<i64, CompileError> {
         let res = self.pop()?;
-        res.parse::<i8>()
-            .map_err(|_| CompileError::InvalidNumber(String::from(res)))
+        Expr::parse(res)?.eval(self.env)
+    }
+
+    pub fn pop_shift(&mut self) -> Result<i8, CompileError> {
+        expr::to_i8(self.pop_expr()?)
     }
 
     pub fn pop_const(&mut self) -> Result<u16, CompileError> {
-        let res = self.pop()?;
-        parse_u16_constant(res)
+        expr::to_u16(self.pop_expr()?)
     }
 
     pub fn pop_label(&mut self) -> Result<ParsedLabel, CompileError> {
@@ -2,11 +2,13 @@ use std::fmt::Display;
 
 use crate::compile::{AtomBox, CompileError, Atom};
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub struct ParsePosition {
     pub pos: usize,
     pub line: usize,
     pub line_pos: usize,
+    /// Index of the file in its `SourceSet`
+    pub file: usize,
 }
 
 impl ParsePosition {
@@ -29,11 +31,21 @@ impl Display for ParsePosition {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PosCompileError {
     pub error: CompileError,
     pub start_pos: ParsePosition,
     pub end_pos: ParsePosition,
+    /// Call sites of the macro expansions the error is in, innermost first
+    pub expanded_from: Box<[ParsePosition]>,
+    pub note: Option<Box<ErrorNote>>,
+}
+
+/// Further position an error refers to, like an earlier definition
+#[derive(Debug, Clone)]
+pub struct ErrorNote {
+    pub message: String,
+    pub pos: ParsePosition,
 }
 
 pub trait CompileErrorWithPos {
@@ -47,6 +59,8 @@ impl CompileErrorWithPos for CompileError {
             error: self,
             start_pos: pos,
             end_pos: pos,
+            expanded_from: Box::default(),
+            note: None,
         }
     }
 
@@ -55,6 +69,8 @@ impl CompileErrorWithPos for CompileError {
             error: self,
             start_pos: start,
             end_pos: end,
+            expanded_from: Box::default(),
+            note: None,
         }
     }
 }
@@ -0,0 +1,113 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use super::ParsePosition;
+
+#[derive(Clone, Debug)]
+pub struct SourceFile {
+    pub name: String,
+    pub text: String,
+}
+
+/// Files taking part in an assembly, positions refer to them by index
+#[derive(Clone, Debug, Default)]
+pub struct SourceSet {
+    files: Vec<SourceFile>,
+}
+
+impl SourceSet {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn single(name: impl Into<String>, text: impl Into<String>) -> Self {
+        let mut sources = SourceSet::new();
+        sources.add(name.into(), text.into());
+        sources
+    }
+
+    pub fn add(&mut self, name: String, text: String) -> usize {
+        self.files.push(SourceFile { name, text });
+        self.files.len() - 1
+    }
+
+    pub fn get(&self, file: usize) -> Option<&SourceFile> {
+        self.files.get(file)
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.files.iter().position(|f| f.name == name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SourceFile> {
+        self.files.iter()
+    }
+
+    /// Text of the line `pos` is on
+    pub fn line(&self, pos: ParsePosition) -> Option<&str> {
+        self.get(pos.file)?.text.lines().nth(pos.line)
+    }
+
+    /// Position as `file:line:column`
+    pub fn describe(&self, pos: ParsePosition) -> String {
+        match self.get(pos.file) {
+            Some(file) => format!("{}:{}", file.name, pos),
+            None => pos.to_string(),
+        }
+    }
+}
+
+/// Reads files named by the command line and `@include`
+pub trait SourceLoader {
+    fn load(&mut self, name: &str) -> Result<String, String>;
+}
+
+pub struct FsLoader;
+
+impl SourceLoader for FsLoader {
+    fn load(&mut self, name: &str) -> Result<String, String> {
+        fs::read_to_string(name).map_err(|e| e.to_string())
+    }
+}
+
+/// Loader for sources given as a string, includes are not available
+pub struct NoLoader;
+
+impl SourceLoader for NoLoader {
+    fn load(&mut self, _: &str) -> Result<String, String> {
+        Err(String::from("includes are not available"))
+    }
+}
+
+/// Path of `name` relative to the directory of the including file
+pub fn resolve_include(from: &str, name: &str) -> String {
+    let joined = match Path::new(from).parent() {
+        Some(dir) => dir.join(name),
+        None => PathBuf::from(name),
+    };
+
+    // Normalize lexically so a file reached by different paths is detected as a cycle
+    let mut path = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir
+                if matches!(path.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                path.pop();
+            }
+            c => path.push(c),
+        }
+    }
+    path.to_string_lossy().into_owned()
+}
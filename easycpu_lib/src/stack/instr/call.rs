@@ -49,6 +49,7 @@ impl StackOperation for CallStackOp {
         let pos = comp.resolve_label(self.label_id)?;
         comp.instruct(MemOperation::LADD.instr(cpu::Register::PC, cpu::Register::PC, 2)?);
         comp.instruct(cpu::Instruction::CUSTOM(6));
+        comp.relocate(self.label_id);
         comp.instruct(cpu::Instruction::CUSTOM(pos));
 
         Ok(())
@@ -78,6 +79,7 @@ impl Atom for StackCallInstruction {
     fn compile(&self, ctx: &mut CompileContext) -> Result<(), CompileError> {
         let label_id = self.targ.resolve(ctx)?;
         ctx.comp.stack(Box::new(CallStackOp::new(label_id)));
+        ctx.flow.instruction(&ctx.status, false);
         Ok(())
     }
 }
@@ -73,6 +73,7 @@ impl Atom for StackJumpInstruction {
     fn compile(&self, ctx: &mut CompileContext) -> Result<(), CompileError> {
         let label_id = self.targ.resolve(ctx)?;
         ctx.comp.stack(Box::new(JumpStackOp::new(self.op, label_id)));
+        ctx.flow.instruction(&ctx.status, self.op == JumpOperation::JMP);
         Ok(())
     }
 }
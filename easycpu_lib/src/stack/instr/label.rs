@@ -57,6 +57,7 @@ impl Atom for StackLabelInstruction {
     fn compile(&self, ctx: &mut CompileContext) -> Result<(), CompileError> {
         let label_id = self.label.resolve(ctx)?;
         ctx.comp.stack(Box::new(LabelStackOp::new(label_id)));
+        ctx.flow.instruction(&ctx.status, false);
         Ok(())
     }
 }
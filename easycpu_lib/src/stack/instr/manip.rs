@@ -71,7 +71,11 @@ impl ManipStackOp {
             }
 
             "DROP" => {
-                let count = parts.pop_const().unwrap_or(1);
+                let count = if parts.is_empty() {
+                    1
+                } else {
+                    parts.pop_const()?
+                };
                 let count: u8 = count
                     .try_into()
                     .map_err(|_| CompileError::ShiftIsTooBig(0))?;
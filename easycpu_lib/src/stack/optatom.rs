@@ -65,6 +65,10 @@ impl CompContext for StackOptComp {
         Err(CompileError::InstructionInStackopt)
     }
 
+    fn current_pc(&mut self) -> Result<u16, CompileError> {
+        Err(CompileError::InstructionInStackopt)
+    }
+
     fn stack(&mut self, op: Box<dyn super::StackOperation>) {
         self.ops.push(op);
     }
@@ -76,9 +80,9 @@ pub struct StackOptAtom {
 }
 
 impl StackOptAtom {
-    pub fn new(atoms: Vec<AtomBox>) -> Self {
+    pub fn new(name: String, atoms: Vec<AtomBox>) -> Self {
         StackOptAtom {
-            scope: LabelScope::new(atoms),
+            scope: LabelScope::new(name, atoms),
         }
     }
 }
@@ -2,7 +2,9 @@ use std::fmt::Debug;
 
 use crate::{
     compile::{comp::CompContext, context::CompileContext, Atom, CompileError},
-    cpu, AsAny,
+    cpu,
+    stack::instr::{func::FunctionOperation, FunctionStackOp},
+    AsAny,
 };
 
 #[derive(Debug, Default)]
@@ -67,6 +69,18 @@ impl StackOpInstruction {
 impl Atom for StackOpInstruction {
     fn compile(&self, ctx: &mut CompileContext) -> Result<(), CompileError> {
         ctx.comp.stack(self.op.duplicate());
+
+        let function = (*self.op)
+            .as_any()
+            .downcast_ref::<FunctionStackOp>()
+            .map(|f| f.op);
+        let returns = function == Some(FunctionOperation::RETURN);
+        ctx.flow.instruction(&ctx.status, returns);
+        match function {
+            Some(FunctionOperation::INIT) => ctx.flow.function_start(&ctx.status),
+            Some(FunctionOperation::RETURN) => ctx.flow.function_end(),
+            None => (),
+        }
         Ok(())
     }
 }
\ No newline at end of file
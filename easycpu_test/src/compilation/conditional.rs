@@ -0,0 +1,101 @@
+use easycpu_lib::asm::AsmOptions;
+use easycpu_lib::cpu::Register;
+
+use crate::runner::{test, ExecCond, Executor, OutputTest, Test, TestGroup};
+
+const TARGET: &str = "@ifdef SIM
+  LCONST R2 1
+@elif TARGET == 2
+  LCONST R2 2
+@elif TARGET >= 3
+  @if TARGET > 3
+    LCONST R2 4
+  @else
+    LCONST R2 3
+  @endif
+@else
+  LCONST R2 SPEED
+@endif";
+
+fn target(options: AsmOptions, val: u16) -> Executor {
+    Executor::new(TARGET, vec![ExecCond::CheckReg(Register::R2, val)]).options(options)
+}
+
+pub fn conditional() -> Test {
+    TestGroup::construct(
+        "conditional".to_owned(),
+        vec![
+            test!("ifdef", target(AsmOptions::new().define("sim", 1), 1)),
+            test!("elif", target(AsmOptions::new().define("TARGET", 2), 2)),
+            test!("nested", target(AsmOptions::new().define("TARGET", 3), 3)),
+            test!(
+                "nested_if",
+                target(AsmOptions::new().define("TARGET", 5), 4)
+            ),
+            test!(
+                "else_define_constant",
+                target(
+                    AsmOptions::new()
+                        .define("TARGET", 0)
+                        .define_str("SPEED=TARGET+9")
+                        .unwrap(),
+                    9
+                )
+            ),
+            test!(
+                "ifndef",
+                Executor::new(
+                    "@ifndef SIM\nLCONST R2 1\n@endif",
+                    vec![ExecCond::CheckReg(Register::R2, 1)]
+                )
+            ),
+            test!(
+                "skipped_invalid",
+                Executor::new(
+                    "@if 0\n  UNKNOWN R9\n  @if X\n  @endif\n@endif\nLCONST R2 1",
+                    vec![ExecCond::CheckReg(Register::R2, 1)]
+                )
+            ),
+            test!(
+                "logical",
+                Executor::new(
+                    "@if !0 && 0 || 2 > 1\nLCONST R2 1\n@if 1 && 2 < 1 || 3 != 3\nLCONST R2 2\n@endif\n@endif",
+                    vec![ExecCond::CheckReg(Register::R2, 1)]
+                )
+            ),
+            test!(
+                "short_circuit",
+                Executor::new(
+                    "@if 0 && X || 1 || Y\nLCONST R2 1\n@endif",
+                    vec![ExecCond::CheckReg(Register::R2, 1)]
+                )
+            ),
+            test!(
+                "not_defined",
+                OutputTest::failing("@if SIM\n@endif", "NotDefined(\n    \"SIM\"")
+            ),
+            test!(
+                "unterminated",
+                OutputTest::failing("NOP\n@if 1\nNOP", "Error at 2:1: UnterminatedConditional")
+            ),
+            test!(
+                "unmatched_else",
+                OutputTest::failing("@else", "UnmatchedConditional(\n    \"@else\"")
+            ),
+            test!(
+                "double_else",
+                OutputTest::failing(
+                    "@if 1\n@else\n@else\n@endif",
+                    "UnmatchedConditional(\n    \"@else\""
+                )
+            ),
+            test!(
+                "elif_after_else",
+                OutputTest::failing(
+                    "@if 1\n@else\n@elif 1\n@endif",
+                    "UnmatchedConditional(\n    \"@elif\""
+                )
+            ),
+        ],
+    )
+}
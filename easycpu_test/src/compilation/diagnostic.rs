@@ -0,0 +1,83 @@
+use crate::runner::{test, OutputTest, Test, TestGroup};
+
+pub fn diagnostic() -> Test {
+    TestGroup::construct(
+        "diagnostic".to_owned(),
+        vec![
+            test!(
+                "snippet",
+                OutputTest::diagnostic(
+                    "NOP\n  INC R9 R2\n",
+                    "error[E004]: unknown register `R9`\n --> main.s:2:3\n  |\n2 |   INC R9 R2\n  |   ^^^^^^^^^\n"
+                )
+            ),
+            test!(
+                "label_note",
+                OutputTest::diagnostic(
+                    "LOOP: NOP\nLOOP:",
+                    "error[E009]: label `LOOP` is defined twice\n --> main.s:2:1\n  |\n2 | LOOP:\n  | ^^^^^\nnote: label defined here\n --> main.s:1:1\n  |\n1 | LOOP: NOP\n  | ^\n"
+                )
+            ),
+            test!(
+                "expansion",
+                OutputTest::diagnostic(
+                    "@macro M { INC R9 R2 }\nM",
+                    "error[E004]: unknown register `R9`\n --> main.s:1:12\n  |\n1 | @macro M { INC R9 R2 }\n  |            ^^^^^^^^^\n  = expanded from main.s:2:1\n"
+                )
+            ),
+            test!(
+                "recursion",
+                OutputTest::diagnostic(
+                    "@macro M { M }\nM",
+                    "error[E030]: macro `M` is nested too deep, is it recursive?\n --> main.s:1:12\n  |\n1 | @macro M { M }\n  |            ^\n  = expanded from main.s:1:12\n  = expanded from main.s:1:12\n  = expanded from main.s:1:12\n  = ... 60 more expansions\n  = expanded from main.s:2:1\n"
+                )
+            ),
+            test!(
+                "several",
+                OutputTest::diagnostic(
+                    "INC R9 R2\nFOO",
+                    "error[E004]: unknown register `R9`\n --> main.s:1:1\n  |\n1 | INC R9 R2\n  | ^^^^^^^^^\n\nerror[E003]: unknown instruction `FOO`\n --> main.s:2:1\n  |\n2 | FOO\n  | ^^^\n"
+                )
+            ),
+            test!(
+                "gutter",
+                OutputTest::diagnostic(
+                    format!("{}FOO", "NOP\n".repeat(10)),
+                    "error[E003]: unknown instruction `FOO`\n  --> main.s:11:1\n   |\n11 | FOO\n   | ^^^\n"
+                )
+            ),
+            test!(
+                "unsettled",
+                OutputTest::diagnostic(
+                    "NOP\n.space END + 1\nEND: HALT",
+                    "error[E008]: label addresses do not settle\n --> main.s:2:1\n  |\n2 | .space END + 1\n  | ^^^^^^^^^^^^^^\n"
+                )
+            ),
+            test!(
+                "unclosed",
+                OutputTest::diagnostic(
+                    "NOP\nF: {\n  NOP\n",
+                    "error[E041]: `{` is never closed\n --> main.s:2:4\n  |\n2 | F: {\n  |    ^\n"
+                )
+            ),
+            test!(
+                "unclosed_macro",
+                OutputTest::diagnostic(
+                    "@macro M {\n  NOP",
+                    "error[E041]: `{` is never closed\n --> main.s:1:10\n  |\n1 | @macro M {\n  |          ^\n"
+                )
+            ),
+            test!(
+                "json",
+                OutputTest::diagnostic_json(
+                    "LOOP: NOP\nLOOP:",
+                    "{\"code\":\"E009\",\"message\":\"label `LOOP` is defined twice\",\"file\":\"main.s\",\"line\":2,\"column\":1,\"end_file\":\"main.s\",\"end_line\":2,\"end_column\":6,\"expanded_from\":[],\"notes\":[{\"message\":\"label defined here\",\"file\":\"main.s\",\"line\":1,\"column\":1}],\"severity\":\"error\"}"
+                )
+            ),
+            test!(
+                "json_escape",
+                OutputTest::diagnostic_json("LCONST R2 \"a", "\"code\":\"E")
+            ),
+        ],
+    )
+}
@@ -0,0 +1,98 @@
+use crate::runner::{test, ExecCond, Executor, OutputTest, Test, TestGroup};
+use easycpu_lib::cpu::Register;
+
+pub fn directive() -> Test {
+    TestGroup::construct(
+        "directive".to_owned(),
+        vec![
+            test!(
+                "org",
+                OutputTest::symbols("NOP\n.org 0x10\nA: NOP", vec![("A", 0x10)])
+            ),
+            test!(
+                "align",
+                OutputTest::symbols(
+                    "NOP\n.align 4\nA: NOP\n.align 4\nB: .align 4\nC:",
+                    vec![("A", 4), ("B", 8), ("C", 8)]
+                )
+            ),
+            test!(
+                "space",
+                OutputTest::symbols(
+                    "A: .space 3\nB: .fill 2 7\nC:",
+                    vec![("A", 0), ("B", 3), ("C", 5)]
+                )
+            ),
+            test!(
+                "fill",
+                Executor::new(
+                    "HALT\n.fill 3 0xabcd",
+                    vec![ExecCond::CheckMem(1, 0xabcd), ExecCond::CheckMem(3, 0xabcd)]
+                )
+            ),
+            test!(
+                "word",
+                Executor::new(
+                    "HALT\nTABLE: .word TABLE END 5 -1\nEND:",
+                    vec![
+                        ExecCond::CheckMem(1, 1),
+                        ExecCond::CheckMem(2, 5),
+                        ExecCond::CheckMem(3, 5),
+                        ExecCond::CheckMem(4, 0xffff)
+                    ]
+                )
+            ),
+            test!(
+                "jump_table",
+                Executor::new(
+                    "LLABEL R3 TABLE
+                    INC R3 R3
+                    LOAD R3 R3 0
+                    MOV PC R3
+                    TABLE: .word FIRST SECOND
+                    FIRST: LCONST R2 1
+                    HALT
+                    SECOND: LCONST R2 2
+                    HALT",
+                    vec![ExecCond::CheckReg(Register::R2, 2)]
+                )
+            ),
+            test!(
+                "org_behind",
+                OutputTest::failing("NOP\nNOP\n.org 1", "OriginBehind")
+            ),
+            test!(
+                "align_zero",
+                OutputTest::failing(".align 0", "InvalidAlignment")
+            ),
+            test!(
+                "org_overflow",
+                OutputTest::failing(".org 0xfffe\nNOP\nNOP\nNOP", "ProgramTooLarge")
+            ),
+            test!(
+                "space_overflow",
+                OutputTest::failing(".space 0xffff\nNOP\nNOP\nHALT", "ProgramTooLarge")
+            ),
+            test!(
+                "fill_overflow",
+                OutputTest::failing("NOP\nNOP\n.fill 0xffff 1", "ProgramTooLarge")
+            ),
+            test!(
+                "align_overflow",
+                OutputTest::failing(".org 0xffff\nNOP\n.align 2", "ProgramTooLarge")
+            ),
+            test!(
+                "full_memory",
+                OutputTest::layout("NOP\n.space 0xffff", 0x10000, 0, 0)
+            ),
+            test!(
+                "unknown",
+                OutputTest::failing(".byte 1", "UnknownDirective")
+            ),
+            test!(
+                "stackopt",
+                OutputTest::failing("@STACKOPT {\n.space 1\n}", "InstructionInStackopt")
+            ),
+        ],
+    )
+}
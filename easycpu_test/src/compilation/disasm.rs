@@ -0,0 +1,80 @@
+use crate::runner::{test, OutputTest, Test, TestGroup};
+
+pub fn disasm() -> Test {
+    TestGroup::construct(
+        "disasm".to_owned(),
+        vec![
+            test!("empty", OutputTest::disasm("", vec![])),
+            test!(
+                "alu",
+                OutputTest::disasm(
+                    "MOV R2 R3\nINC R2 R2\nDEC R3 R3\nSUB R4 R2 R3\nNOT R5 R4\nHALT",
+                    vec![
+                        "MOV R2 R3",
+                        "INC R2 R2",
+                        "DEC R3 R3",
+                        "SUB R4 R2 R3",
+                        "NOT R5 R4",
+                        "HALT"
+                    ]
+                )
+            ),
+            test!(
+                "consts",
+                OutputTest::disasm(
+                    "LCONST R2 0x1234\nLCONST R3 0xfff0\nLCONST R4 2\nACONST R2 100\nHALT",
+                    vec![
+                        "LCONST R2 0x1234",
+                        "LCONST R3 0xfff0",
+                        "LCONST R4 0x0002",
+                        "ACONST R2 0x0064"
+                    ]
+                )
+            ),
+            test!(
+                "jumps",
+                OutputTest::disasm(
+                    "LOOP: DEC R2 R2\nJNE R2 LOOP\nJMP END\n0 0 0\nEND: HALT",
+                    vec!["LOOP:", "JNE R2 LOOP", "JMP END", "END:"]
+                )
+            ),
+            test!(
+                "long_jumps",
+                OutputTest::disasm(
+                    "START: JEQ R2 END\nHALT\n\"padding words to force a long jump\"\nEND: JMP START",
+                    vec![
+                        "JEQ R2 END",
+                        "JMP START",
+                        "\"padding words to force a long jump\""
+                    ]
+                )
+            ),
+            test!(
+                "long_jump_in_short_range",
+                OutputTest::disasm("LADD PC PC 1\n2\nHALT", vec!["LADD PC PC 1"])
+            ),
+            test!(
+                "generated_labels",
+                OutputTest::disasm(
+                    "LLABEL R2 $DATA\nJMP $SKIP\n$DATA: 1 2 3\n$SKIP: HALT",
+                    vec!["LLABEL R2 L__DATA", "JMP L__SKIP", "L__DATA:", "L__SKIP:"]
+                )
+            ),
+            test!(
+                "jump_into_constant",
+                OutputTest::disasm("LCONST R2 5\nBRANCH ZX -1\nHALT", vec![])
+            ),
+            test!(
+                "call",
+                OutputTest::disasm(
+                    "$INIT\n$CALL FUNC\nHALT\nFUNC: $RET",
+                    vec!["$CALL FUNC", "FUNC:"]
+                )
+            ),
+            test!(
+                "simp_calc",
+                OutputTest::disasm(include_str!("../../../asm/simp_calc.s"), vec!["MAIN:"])
+            ),
+        ],
+    )
+}
@@ -0,0 +1,141 @@
+use easycpu_lib::cpu::Register;
+
+use crate::runner::{test, ExecCond, Executor, OutputTest, Test, TestGroup};
+
+fn reg(code: &str, val: u16) -> Executor {
+    Executor::new(code, vec![ExecCond::CheckReg(Register::R2, val)])
+}
+
+pub fn expr() -> Test {
+    TestGroup::construct(
+        "expr".to_owned(),
+        vec![
+            test!("arithmetic", reg("LCONST R2 2+3*4", 14)),
+            test!("parentheses", reg("LCONST R2 (2 + 3) * 4", 20)),
+            test!(
+                "bitwise",
+                reg("LCONST R2 ~0x00ff & 0x0ff0 | 1 << 2", 0x0f04)
+            ),
+            test!("shift_right", reg("LCONST R2 0x1234>>4", 0x0123)),
+            test!(
+                "comparison",
+                reg("LCONST R2 (3 > 2) + (2 <= 2) + (1 == 2) + (1 != 2) + (2 < 1)", 3)
+            ),
+            test!("comparison_precedence", reg("LCONST R2 1 + 1 == 2 && 4 >= 4 << 1", 0)),
+            test!("logical_not", reg("LCONST R2 !5 + !0", 1)),
+            test!("div_rem", reg("LCONST R2 100/7*10 + 100%7", 142)),
+            test!("negative", reg("LCONST R2 -(2*3)", 0xfffa)),
+            test!("char", reg("LCONST R2 'a'", 0x61)),
+            test!("char_escape", reg("LCONST R2 '\\n' + ' '", 0x2a)),
+            test!("char_comment", reg("LCONST R2 '#' # comment", 0x23)),
+            test!(
+                "equ",
+                reg(".equ SERIAL_BASE 0xF100\nLCONST R2 SERIAL_BASE+4", 0xf104)
+            ),
+            test!(
+                "assignment",
+                reg("SERIAL_BASE = 0xF100\nLCONST R2 SERIAL_BASE + 4", 0xf104)
+            ),
+            test!("forward", reg("LCONST R2 SIZE*2\n.equ SIZE, 3", 6)),
+            test!("set", reg(".set N 1\n.set N N+1\nLCONST R2 N\n.set N 5", 2)),
+            test!(
+                "label_arithmetic",
+                reg("LCONST R2 END-START\nHALT\nSTART: 1 2 3\nEND:", 3)
+            ),
+            test!(
+                "label_constant",
+                reg(
+                    ".equ LEN END-START\nLCONST R2 LEN\nHALT\nSTART: \"abcd\"\nEND:",
+                    4
+                )
+            ),
+            test!(
+                "shift",
+                Executor::new(
+                    ".equ OFF 2\nLLABEL R3 DATA\nLOAD R2 R3 OFF-1\nHALT\nDATA: 5 6 7",
+                    vec![ExecCond::CheckReg(Register::R2, 6)]
+                )
+            ),
+            test!(
+                "data",
+                Executor::new(
+                    "HALT\n.equ X 0x10\n'A' 2*X 1+2\n.word (X - 1)",
+                    vec![
+                        ExecCond::CheckMem(1, 0x41),
+                        ExecCond::CheckMem(2, 0x20),
+                        ExecCond::CheckMem(3, 3),
+                        ExecCond::CheckMem(4, 0x0f),
+                    ]
+                )
+            ),
+            test!(
+                "stack_const",
+                Executor::new(
+                    ".equ BASE 40\n$INIT\n$PCONST BASE+2\n$POP R2",
+                    vec![ExecCond::CheckReg(Register::R2, 42)]
+                )
+            ),
+            test!(
+                "stack_block",
+                Executor::new(
+                    "$INIT\n$ADD ($PCONST 'A'; $PCONST (1+1))\n$POP R2",
+                    vec![ExecCond::CheckReg(Register::R2, 0x43)]
+                )
+            ),
+            test!(
+                "directive",
+                Executor::new(
+                    ".equ N 3\nHALT\n.fill N-1 'z'",
+                    vec![ExecCond::CheckMem(2, 0x7a)]
+                )
+            ),
+            test!(
+                "overflow",
+                OutputTest::failing("LCONST R2 0x8000*2", "ValueOutOfRange")
+            ),
+            test!(
+                "negative_overflow",
+                OutputTest::failing("LCONST R2 -0x8001", "ValueOutOfRange")
+            ),
+            test!(
+                "shift_overflow",
+                OutputTest::failing("LOAD R2 R3 128", "ValueOutOfRange")
+            ),
+            test!(
+                "intermediate_overflow",
+                OutputTest::failing(
+                    "LCONST R2 0x7fffffff*0x7fffffff*0x7fffffff",
+                    "ExpressionOverflow"
+                )
+            ),
+            test!(
+                "shift_left_overflow",
+                OutputTest::failing("LCONST R2 (1 << 63) >> 62", "ExpressionOverflow")
+            ),
+            test!(
+                "shift_left_sign",
+                OutputTest::failing("LCONST R2 (3 << 62) >> 62", "ExpressionOverflow")
+            ),
+            test!(
+                "division_by_zero",
+                OutputTest::failing("LCONST R2 1/(2-2)", "DivisionByZero")
+            ),
+            test!(
+                "unknown_symbol",
+                OutputTest::failing("LCONST R2 MISSING+1", "UnknownLabel")
+            ),
+            test!(
+                "redefined",
+                OutputTest::failing(".equ A 1\n.equ A 2", "ConstantRedefined")
+            ),
+            test!(
+                "set_equ",
+                OutputTest::failing(".equ A 1\n.set A 2", "ConstantRedefined")
+            ),
+            test!(
+                "invalid",
+                OutputTest::failing("LCONST R2 (1+", "InvalidExpression")
+            ),
+        ],
+    )
+}
@@ -0,0 +1,132 @@
+use easycpu_lib::image::{ImageError, ImageFormat};
+
+use crate::runner::{test, ImageTest, OutputTest, Test, TestGroup};
+
+fn sample() -> Vec<u16> {
+    let mut code = vec![0x2dca, 0x1e02, 0x4000, 0xffff, 0x0001];
+    code.extend([0; 20]);
+    code.extend((0..40).map(|x| x * 0x0123));
+    code
+}
+
+pub fn image() -> Test {
+    let round_trips = ImageFormat::ALL
+        .into_iter()
+        .map(|format| test!(format.name(), ImageTest::round_trip(format, sample())))
+        .collect();
+
+    TestGroup::construct(
+        "image".to_owned(),
+        vec![
+            TestGroup::construct("round_trip".to_owned(), round_trips),
+            test!(
+                "empty",
+                ImageTest::round_trip(ImageFormat::IntelHex, vec![])
+            ),
+            test!(
+                "odd_bin",
+                ImageTest::read(ImageFormat::Bin, "abc", Err(ImageError::OddLength(3)))
+            ),
+            test!(
+                "readmemh",
+                ImageTest::read(
+                    ImageFormat::ReadMemH,
+                    "// header\n12_34 ffff /* skipped\n 5555 */ 0001\n@5 00aa\n",
+                    Ok(vec![0x1234, 0xffff, 0x0001, 0, 0, 0x00aa])
+                )
+            ),
+            test!(
+                "readmemb",
+                ImageTest::read(
+                    ImageFormat::ReadMemB,
+                    "0000_0000_0000_0011\n1000000000000000 // top bit\n",
+                    Ok(vec![3, 0x8000])
+                )
+            ),
+            test!(
+                "ihex",
+                ImageTest::read(
+                    ImageFormat::IntelHex,
+                    ":0400000012340001B5\n:02000600ABCD80\n:00000001FF\n",
+                    Ok(vec![0x1234, 0x0001, 0, 0xabcd])
+                )
+            ),
+            test!(
+                "ihex_checksum",
+                ImageTest::read(
+                    ImageFormat::IntelHex,
+                    ":0400000012340001B6\n:00000001FF\n",
+                    Err(ImageError::InvalidChecksum(1))
+                )
+            ),
+            test!(
+                "ihex_multibyte",
+                ImageTest::read(
+                    ImageFormat::IntelHex,
+                    ":0\u{e9}000000000\n",
+                    Err(ImageError::InvalidNumber(1, String::from(":0\u{e9}000000000")))
+                )
+            ),
+            test!(
+                "logisim",
+                ImageTest::read(
+                    ImageFormat::Logisim,
+                    "v2.0 raw\n# comment\n1 3*ab ffff\n",
+                    Ok(vec![1, 0xab, 0xab, 0xab, 0xffff])
+                )
+            ),
+            test!(
+                "logisim_run_overflow",
+                ImageTest::read(
+                    ImageFormat::Logisim,
+                    "v2.0 raw\n1 18446744073709551615*ab\n",
+                    Err(ImageError::TooLarge)
+                )
+            ),
+            test!(
+                "logisim_too_large",
+                ImageTest::read(
+                    ImageFormat::Logisim,
+                    "v2.0 raw\n65535*0 2*1\n",
+                    Err(ImageError::TooLarge)
+                )
+            ),
+            test!(
+                "logisim_header",
+                ImageTest::read(
+                    ImageFormat::Logisim,
+                    "1 2 3\n",
+                    Err(ImageError::MissingHeader)
+                )
+            ),
+            test!(
+                "c_array",
+                ImageTest::read(
+                    ImageFormat::C,
+                    "static const uint16_t prog[] = {\n    0x1234, 17, // data\n    0xffffU\n};\n",
+                    Ok(vec![0x1234, 17, 0xffff])
+                )
+            ),
+            test!(
+                "executable",
+                OutputTest::executable("NOP\nSTART: LCONST R2 5\nLOOP: JMP LOOP", 1, 1)
+            ),
+            test!(
+                "executable_segments",
+                OutputTest::executable("LCONST R2 1\n.org 0x40\nDATA: .word 7\n.space 20", 0x40, 2)
+            ),
+            test!(
+                "executable_checksum",
+                OutputTest::corrupt_executable("NOP\nHALT", 12, ImageError::ExecutableChecksum)
+            ),
+            test!(
+                "rust_array",
+                ImageTest::read(
+                    ImageFormat::Rust,
+                    "const PROG: [u16; 3] = [0x1234, 0b11, 0xffff_u16];",
+                    Ok(vec![0x1234, 3, 0xffff])
+                )
+            ),
+        ],
+    )
+}
@@ -0,0 +1,119 @@
+use crate::runner::{test, OutputTest, Source, Test, TestGroup};
+
+pub fn include() -> Test {
+    TestGroup::construct(
+        "include".to_owned(),
+        vec![
+            test!(
+                "inline",
+                OutputTest::symbols(
+                    Source::files(
+                        vec![
+                            ("main.s", "NOP\n@include \"lib.s\"\nB: NOP"),
+                            ("lib.s", "A: NOP\nNOP"),
+                        ],
+                        vec!["main.s"]
+                    ),
+                    vec![("A", 1), ("B", 3)]
+                )
+            ),
+            test!(
+                "relative",
+                OutputTest::symbols(
+                    Source::files(
+                        vec![
+                            ("src/main.s", "@include \"lib/a.s\"\nMAIN:"),
+                            ("src/lib/a.s", "A: NOP\n@include \"../b.s\""),
+                            ("src/b.s", "B: NOP"),
+                        ],
+                        vec!["src/main.s"]
+                    ),
+                    vec![("A", 0), ("B", 1), ("MAIN", 2)]
+                )
+            ),
+            test!(
+                "several_files",
+                OutputTest::symbols(
+                    Source::files(
+                        vec![("a.s", "A: NOP"), ("b.s", "B: LLABEL R2 A")],
+                        vec!["a.s", "b.s"]
+                    ),
+                    vec![("A", 0), ("B", 1)]
+                )
+            ),
+            test!(
+                "twice",
+                OutputTest::symbols(
+                    Source::files(
+                        vec![
+                            ("main.s", "@include \"w.s\"\n@include \"w.s\"\nEND:"),
+                            ("w.s", ".word 1 2"),
+                        ],
+                        vec!["main.s"]
+                    ),
+                    vec![("END", 4)]
+                )
+            ),
+            test!(
+                "cycle",
+                OutputTest::failing(
+                    Source::files(
+                        vec![
+                            ("a.s", "@include \"b.s\""),
+                            ("b.s", "NOP\n@include \"./a.s\""),
+                        ],
+                        vec!["a.s"]
+                    ),
+                    "b.s:2:1: IncludeCycle([\"a.s\", \"b.s\", \"a.s\"])"
+                )
+            ),
+            test!(
+                "cycle_self",
+                OutputTest::failing(
+                    Source::files(
+                        vec![
+                            ("main.s", "@include \"a.s\""),
+                            ("a.s", "NOP\n@include \"a.s\"")
+                        ],
+                        vec!["main.s"]
+                    ),
+                    "a.s:2:1: IncludeCycle([\"a.s\", \"a.s\"])"
+                )
+            ),
+            test!(
+                "missing",
+                OutputTest::failing(
+                    Source::files(vec![("a.s", "NOP\n@include \"none.s\"")], vec!["a.s"]),
+                    "a.s:2:1: IncludeFailed(\"none.s: not found\")"
+                )
+            ),
+            test!(
+                "missing_root",
+                OutputTest::failing(
+                    Source::files(vec![], vec!["a.s"]),
+                    "IncludeFailed(\"a.s: not found\")"
+                )
+            ),
+            test!(
+                "error_file",
+                OutputTest::failing(
+                    Source::files(
+                        vec![
+                            ("a.s", "NOP\n@include \"b.s\""),
+                            ("b.s", "NOP\nNOP\nINC R9 R2")
+                        ],
+                        vec!["a.s"]
+                    ),
+                    "b.s:3:1: UnknownRegister"
+                )
+            ),
+            test!(
+                "not_a_string",
+                OutputTest::failing(
+                    Source::files(vec![("a.s", "@include b.s")], vec!["a.s"]),
+                    "UnexpectedToken"
+                )
+            ),
+        ],
+    )
+}
@@ -0,0 +1,77 @@
+use crate::runner::{test, OutputTest, Test, TestGroup};
+
+/// Jumps each reaching over the next one, only the last is out of reach at
+/// first so they grow one pass after another
+fn chain(jumps: usize) -> String {
+    let mut code = String::new();
+    for i in 0..jumps {
+        code += &format!("JMP L{}\n.space 14\n", i);
+        if i > 0 {
+            code += &format!("L{}:\n", i - 1);
+        }
+        code += ".space 1\n";
+    }
+    code + &format!(".space 16\nL{}: HALT", jumps - 1)
+}
+
+pub fn layout() -> Test {
+    TestGroup::construct(
+        "layout".to_owned(),
+        vec![
+            test!("short", OutputTest::layout("A: NOP\nJMP A", 2, 1, 0)),
+            test!(
+                "far_forward",
+                OutputTest::layout("JEQ R2 END\n.space 40\nEND: HALT", 44, 1, 1)
+            ),
+            test!(
+                "far_backward",
+                OutputTest::layout("A: NOP\n.space 40\nJNE R2 A", 44, 1, 1)
+            ),
+            test!(
+                "in_reach",
+                OutputTest::layout("JMP A\n.space 30\nA: HALT", 32, 1, 0)
+            ),
+            test!(
+                "cascade",
+                OutputTest::layout(
+                    "JMP B\nJMP A\n.space 29\nB: NOP\n.space 40\nA: HALT",
+                    75,
+                    2,
+                    2
+                )
+            ),
+            test!(
+                "external",
+                OutputTest::layout(".extern F\nJMP F\nHALT", 3, 1, 1)
+            ),
+            // Would alternate between sizes without growing only
+            test!(
+                "label_const",
+                OutputTest::layout("LCONST R2 3 - END\nEND: HALT", 3, 0, 0)
+            ),
+            test!(
+                "label_const_literal",
+                OutputTest::layout("LCONST R2 4098 - END\nEND: HALT", 4, 0, 0)
+            ),
+            // Space shrinks back to nothing on every other pass
+            test!(
+                "label_space",
+                OutputTest::failing(".space 41 - END\nEND: HALT", "TooManyAttempts")
+            ),
+            // Space gives back the words the jumps grew by, a pass later each
+            test!(
+                "space_after_relax",
+                OutputTest::symbols(
+                    "S: JMP B\nJMP A\n.space 29\nB: NOP\n\
+                     E: .space 50 - (E - S)\nX: .space 40\nA: HALT",
+                    vec![("S", 0), ("B", 33), ("E", 34), ("X", 50), ("A", 90)]
+                )
+            ),
+            test!("chain", OutputTest::layout(chain(8), 153, 8, 8)),
+            test!(
+                "stackopt",
+                OutputTest::layout("@STACKOPT {\nA: $PUZX\n$JEQ A\n}\nHALT", 2, 1, 0)
+            ),
+        ],
+    )
+}
@@ -0,0 +1,91 @@
+use easycpu_lib::cpu::Register;
+
+use crate::runner::{test, ExecCond, Executor, OutputTest, Source, Test, TestGroup};
+
+const MAIN: &str = "
+.extern FUNC DATA SKIP
+$INIT
+$CALL FUNC
+LLABEL R5 DATA
+LOAD R5 R5 0
+JMP SKIP
+HALT";
+
+const LIB: &str = "
+.global FUNC DATA SKIP
+FUNC:
+$FUNC 0 0 0
+LCONST R4 7
+$RET
+DATA: .word 0x1234
+SKIP:
+LCONST R3 9
+HALT";
+
+pub fn link() -> Test {
+    TestGroup::construct(
+        "link".to_owned(),
+        vec![
+            test!(
+                "references",
+                Executor::linked(
+                    vec![MAIN, LIB],
+                    vec![
+                        ExecCond::CheckReg(Register::R4, 7),
+                        ExecCond::CheckReg(Register::R5, 0x1234),
+                        ExecCond::CheckReg(Register::R3, 9)
+                    ]
+                )
+            ),
+            test!(
+                "conditional",
+                Executor::linked(
+                    vec![
+                        ".extern T\nLCONST R2 0\nJEQ R2 T\nHALT",
+                        "NOP\n.global T\nT: LCONST R3 5\nHALT"
+                    ],
+                    vec![ExecCond::CheckReg(Register::R3, 5)]
+                )
+            ),
+            test!(
+                "backwards",
+                Executor::linked(
+                    vec![
+                        ".global BACK\nJMP START\nBACK: LCONST R3 1\nHALT\nSTART:\n.extern GO\nJMP GO",
+                        ".extern BACK\n.global GO\nGO: LCONST R2 2\nJMP BACK",
+                    ],
+                    vec![
+                        ExecCond::CheckReg(Register::R2, 2),
+                        ExecCond::CheckReg(Register::R3, 1)
+                    ]
+                )
+            ),
+            test!(
+                "unresolved",
+                OutputTest::failing(Source::objects(vec![MAIN]), "unresolved symbol `FUNC`")
+            ),
+            test!(
+                "duplicate",
+                OutputTest::failing(
+                    Source::objects(vec![MAIN, LIB, LIB]),
+                    "symbol `DATA` is exported more than once"
+                )
+            ),
+            test!(
+                "position_dependent",
+                OutputTest::failing(
+                    Source::objects(vec![MAIN, ".global X\n.org 0x40\nX: HALT"]),
+                    "object 2 uses absolute addresses"
+                )
+            ),
+            test!(
+                "external_address",
+                OutputTest::failing(".extern F\nLCONST R2 F", "ExternalAddress")
+            ),
+            test!(
+                "export_external",
+                OutputTest::failing(".extern F\n.global F", "ExternalExported")
+            ),
+        ],
+    )
+}
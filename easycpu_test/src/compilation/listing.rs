@@ -0,0 +1,26 @@
+use crate::runner::{test, OutputTest, Test, TestGroup};
+
+pub fn listing() -> Test {
+    TestGroup::construct(
+        "listing".to_owned(),
+        vec![
+            test!(
+                "single_words",
+                OutputTest::listing(
+                    "NOP\n\nADD R2 R2 R2",
+                    "    1 0000       0000  NOP                      NOP\n\
+                     \x20   2\n\
+                     \x20   3 0001       5092  ADD R2 R2 R2             ADD R2 R2 R2\n"
+                )
+            ),
+            test!(
+                "expansion",
+                OutputTest::listing(
+                    "ACONST R2 8",
+                    "    1 0000-0001  2089  LOAD R2 PC 1             ACONST R2 8\n\
+                     \x20     0001       0008  0x0008\n"
+                )
+            ),
+        ],
+    )
+}
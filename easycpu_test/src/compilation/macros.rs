@@ -0,0 +1,91 @@
+use easycpu_lib::cpu::Register;
+
+use crate::runner::{test, ExecCond, Executor, OutputTest, Test, TestGroup};
+
+const ADD_TO: &str = "@macro ADD_TO reg val {\n  LCONST R4 \\val\n  ADD \\reg \\reg R4\n}\n";
+
+pub fn macros() -> Test {
+    TestGroup::construct(
+        "macros".to_owned(),
+        vec![
+            test!(
+                "params",
+                Executor::new(
+                    format!("{}ADD_TO R2 3\nADD_TO R2 4\nADD_TO R3 1+1", ADD_TO),
+                    vec![
+                        ExecCond::CheckReg(Register::R2, 7),
+                        ExecCond::CheckReg(Register::R3, 2)
+                    ]
+                )
+            ),
+            test!(
+                "case_insensitive",
+                Executor::new(
+                    "@macro Set R v { LCONST \\R \\V }\nset r2 'a'",
+                    vec![ExecCond::CheckReg(Register::R2, 0x61)]
+                )
+            ),
+            test!(
+                "nested",
+                Executor::new(
+                    format!(
+                        "{}@macro ADD_BOTH v {{\nADD_TO R2 \\v\nADD_TO R3 \\v\n}}\nADD_BOTH 5",
+                        ADD_TO
+                    ),
+                    vec![
+                        ExecCond::CheckReg(Register::R2, 5),
+                        ExecCond::CheckReg(Register::R3, 5)
+                    ]
+                )
+            ),
+            test!(
+                "local_labels",
+                OutputTest::symbols(
+                    "@macro SKIP { JMP END\nNOP\nEND: }\nSTART: SKIP\nSKIP",
+                    vec![("START", 0), ("SKIP{0}.END", 2), ("SKIP{1}.END", 4)]
+                )
+            ),
+            test!(
+                "outer_label",
+                Executor::new(
+                    "@macro GO target { JMP \\target }\nGO END\nLCONST R2 1\nEND:",
+                    vec![ExecCond::CheckReg(Register::R2, 0)]
+                )
+            ),
+            test!(
+                "undefined_before_use",
+                OutputTest::failing("M\n@macro M { NOP }", "UnknownCommand")
+            ),
+            test!(
+                "redefined",
+                OutputTest::failing("@macro M { NOP }\n@macro M { NOP }", "MacroRedefined")
+            ),
+            test!(
+                "invalid_param",
+                OutputTest::failing("@macro M 1x { NOP }", "InvalidMacro")
+            ),
+            test!(
+                "argument_count",
+                OutputTest::failing(format!("{}ADD_TO R2", ADD_TO), "MacroArguments")
+            ),
+            test!(
+                "recursion",
+                OutputTest::failing("@macro M { M }\nM", "MacroTooDeep")
+            ),
+            test!(
+                "error_call_site",
+                OutputTest::failing(
+                    format!("{}NOP\nADD_TO R9 1", ADD_TO),
+                    "Error at 3:3: UnknownRegister(\n    \"R9\",\n)\n  expanded from 6:1"
+                )
+            ),
+            test!(
+                "nested_call_site",
+                OutputTest::failing(
+                    "@macro A { INC R9 R9 }\n@macro B {\n A\n}\nB",
+                    "expanded from 3:2\n  expanded from 5:1"
+                )
+            ),
+        ],
+    )
+}
@@ -1,10 +1,44 @@
 use crate::runner::{Test, TestGroup};
 
+mod conditional;
+mod diagnostic;
+mod directive;
+mod disasm;
+mod expr;
+mod image;
+mod include;
+mod layout;
+mod link;
+mod listing;
+mod macros;
+mod recovery;
 mod simple;
+mod sourcemap;
+mod symbols;
+mod warning;
 
 pub fn compilation_test() -> Test {
     TestGroup::construct(
         "compilation".to_owned(),
-        vec![simple::simple(), simple::label(), simple::stack()],
+        vec![
+            simple::simple(),
+            simple::label(),
+            simple::stack(),
+            sourcemap::sourcemap(),
+            symbols::symbols(),
+            listing::listing(),
+            directive::directive(),
+            disasm::disasm(),
+            expr::expr(),
+            image::image(),
+            include::include(),
+            macros::macros(),
+            conditional::conditional(),
+            link::link(),
+            diagnostic::diagnostic(),
+            recovery::recovery(),
+            warning::warning(),
+            layout::layout(),
+        ],
     )
 }
@@ -0,0 +1,53 @@
+use crate::runner::{test, OutputTest, Test, TestGroup};
+
+pub fn recovery() -> Test {
+    TestGroup::construct(
+        "recovery".to_owned(),
+        vec![
+            test!(
+                "bracket",
+                OutputTest::diagnostic(
+                    "{ NOP )\nFOO",
+                    "error[E011]: unexpected `)`\n --> main.s:1:7\n  |\n1 | { NOP )\n  |       ^\n\nerror[E003]: unknown instruction `FOO`\n --> main.s:2:1\n  |\n2 | FOO\n  | ^^^\n"
+                )
+            ),
+            test!(
+                "string",
+                OutputTest::diagnostic(
+                    "\"abc\nFOO",
+                    "error[E038]: string is not closed on its line\n --> main.s:1:1\n  |\n1 | \"abc\n  | ^^^^\n\nerror[E003]: unknown instruction `FOO`\n --> main.s:2:1\n  |\n2 | FOO\n  | ^^^\n"
+                )
+            ),
+            test!(
+                "closing_bracket",
+                OutputTest::diagnostic(
+                    "}\nFOO",
+                    "error[E014]: `}` without an opening `{`\n --> main.s:1:1\n  |\n1 | }\n  | ^\n\nerror[E003]: unknown instruction `FOO`\n --> main.s:2:1\n  |\n2 | FOO\n  | ^^^\n"
+                )
+            ),
+            test!(
+                "block",
+                OutputTest::diagnostic(
+                    "{\n  FOO\n  BAR\n}",
+                    "error[E003]: unknown instruction `FOO`\n --> main.s:2:3\n  |\n2 |   FOO\n  |   ^^^\n\nerror[E003]: unknown instruction `BAR`\n --> main.s:3:3\n  |\n3 |   BAR\n  |   ^^^\n"
+                )
+            ),
+            test!(
+                "after_block",
+                OutputTest::diagnostic_json("{\n  FOO\n  (\n}\nBAR", "`BAR`")
+            ),
+            test!(
+                "label_pass",
+                OutputTest::diagnostic_json("INC R9 R2\nJMP MISSING\nL: NOP", "`MISSING`")
+            ),
+            test!(
+                "conditional",
+                OutputTest::diagnostic_json("@if 1\nFOO", "\"code\":\"E034\"")
+            ),
+            test!(
+                "valid_after_error",
+                OutputTest::failing("FOO\nLCONST R2 'a'\nHALT", "UnknownCommand")
+            ),
+        ],
+    )
+}
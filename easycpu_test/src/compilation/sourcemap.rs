@@ -0,0 +1,26 @@
+use crate::runner::{test, OutputTest, Test, TestGroup};
+
+pub fn sourcemap() -> Test {
+    TestGroup::construct(
+        "sourcemap".to_owned(),
+        vec![
+            test!("empty", OutputTest::source_lines("", vec![])),
+            test!(
+                "lines",
+                OutputTest::source_lines("NOP\nADD R2 R2 R2\n\nNOP", vec![1, 2, 4])
+            ),
+            test!(
+                "lconst",
+                OutputTest::source_lines("NOP\nLCONST R2 0x4000", vec![1, 2, 2, 2])
+            ),
+            test!(
+                "stack",
+                OutputTest::source_lines("$INIT\n$DUP", vec![1, 1, 1, 1, 2, 2, 2])
+            ),
+            test!(
+                "stackopt",
+                OutputTest::source_lines("NOP\n@STACKOPT {\n  $ADD\n}", vec![1, 2, 2, 2, 2, 2])
+            ),
+        ],
+    )
+}
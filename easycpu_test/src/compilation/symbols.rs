@@ -0,0 +1,35 @@
+use crate::runner::{test, OutputTest, Test, TestGroup};
+
+pub fn symbols() -> Test {
+    TestGroup::construct(
+        "symbols".to_owned(),
+        vec![
+            test!("none", OutputTest::symbols("NOP", vec![])),
+            test!(
+                "top_level",
+                OutputTest::symbols("START: NOP\nEND: NOP", vec![("START", 0), ("END", 1)])
+            ),
+            test!(
+                "named_scope",
+                OutputTest::symbols(
+                    "NOP\nPRINT: {\n  LOOP: NOP\n  JMP LOOP\n}",
+                    vec![("PRINT", 1), ("PRINT.LOOP", 1)]
+                )
+            ),
+            test!(
+                "anonymous_scope",
+                OutputTest::symbols(
+                    "MAIN: {\n  NOP\n  { SKIP: NOP }\n  { SKIP: NOP }\n}",
+                    vec![("MAIN", 0), ("MAIN.{0}.SKIP", 1), ("MAIN.{1}.SKIP", 2)]
+                )
+            ),
+            test!(
+                "stackopt",
+                OutputTest::symbols(
+                    "FN: @STACKOPT {\n  $ADD\n  END:\n}",
+                    vec![("FN", 0), ("FN.END", 5)]
+                )
+            ),
+        ],
+    )
+}
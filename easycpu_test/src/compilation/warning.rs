@@ -0,0 +1,90 @@
+use easycpu_lib::asm::AsmOptions;
+
+use crate::runner::{test, OutputTest, Test, TestGroup};
+
+const FUNC: &str = "$INIT\n$CALL F\nHALT\nF:\n$FUNC 0 0 0\n";
+
+/// Options with these `-W` flags
+fn flags(flags: &[&str]) -> AsmOptions {
+    flags.iter().fold(AsmOptions::new(), |options, flag| {
+        options.warning(flag).expect("known warning flag")
+    })
+}
+
+pub fn warning() -> Test {
+    TestGroup::construct(
+        "warning".to_owned(),
+        vec![
+            test!(
+                "clean",
+                OutputTest::warnings("LOOP: INC R2 R2\nJNE R2 LOOP\nHALT", vec![])
+            ),
+            test!("write_zx", OutputTest::warnings("INC ZX R2\nHALT", vec!["write-zx"])),
+            test!("load_pc", OutputTest::warnings("LOAD PC R2 0", vec!["load-pc"])),
+            test!(
+                "unreachable",
+                OutputTest::warnings(
+                    "JMP END\nINC R2 R2\nINC R2 R2\nEND: HALT",
+                    vec!["unreachable"]
+                )
+            ),
+            test!(
+                "after_halt",
+                OutputTest::warnings("HALT\nNOP\nHALT", vec!["unreachable"])
+            ),
+            test!("data_after_halt", OutputTest::warnings("HALT\n\"abc\" 0", vec![])),
+            test!(
+                "after_ret",
+                OutputTest::warnings(
+                    format!("{}$RET\nNOP", FUNC),
+                    vec!["unreachable", "missing-halt"]
+                )
+            ),
+            test!(
+                "unused_label",
+                OutputTest::warning_text(
+                    "A: NOP\nB: JMP A",
+                    "warning[unused-label]: label `B` is never used\n --> main.s:2:1\n  |\n2 | B: JMP A\n  | ^\n"
+                )
+            ),
+            test!(
+                "shadowed_label",
+                OutputTest::warnings(
+                    "L: NOP\n{\n  L: NOP\n  JNE R2 L\n}\nJMP L",
+                    vec!["shadowed-label"]
+                )
+            ),
+            test!(
+                "func_without_ret",
+                OutputTest::warnings(format!("{}JMP F", FUNC), vec!["func-without-ret"])
+            ),
+            test!(
+                "func_with_ret",
+                OutputTest::warnings(format!("{}$RET", FUNC), vec![])
+            ),
+            test!("missing_halt", OutputTest::warnings("LCONST R2 1", vec!["missing-halt"])),
+            test!("ends_with_jump", OutputTest::warnings("LOOP: JMP LOOP", vec![])),
+            test!(
+                "rendered",
+                OutputTest::warning_text(
+                    "NOP\nINC ZX R2\nHALT",
+                    "warning[write-zx]: result written to `ZX` is discarded\n --> main.s:2:1\n  |\n2 | INC ZX R2\n  | ^^^^^^^^^\n"
+                )
+            ),
+            test!(
+                "disabled",
+                OutputTest::warnings("INC ZX R2", vec![])
+                    .options(flags(&["no-write-zx", "no-missing-halt"]))
+            ),
+            test!(
+                "enabled_again",
+                OutputTest::warnings("INC ZX R2\nHALT", vec!["write-zx"])
+                    .options(flags(&["no-write-zx", "write-zx"]))
+            ),
+            test!(
+                "denied",
+                OutputTest::error_code("INC ZX R2\nHALT", "write-zx").options(flags(&["error"]))
+            ),
+        ],
+    )
+}
@@ -0,0 +1,78 @@
+use easycpu_lib::{cpu::Register, exec::Device};
+
+use crate::runner::{test, ExecCond, Executor, Test, TestGroup};
+
+#[derive(Clone, Debug, Default)]
+struct Multiplier {
+    a: u16,
+    b: u16,
+}
+
+impl Device for Multiplier {
+    fn read(&mut self, offset: u16) -> u16 {
+        match offset {
+            0 => self.a,
+            1 => self.b,
+            _ => self.a.wrapping_mul(self.b),
+        }
+    }
+
+    fn write(&mut self, offset: u16, val: u16) {
+        match offset {
+            0 => self.a = val,
+            1 => self.b = val,
+            _ => (),
+        }
+    }
+
+    fn duplicate(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+}
+
+fn multiplier() -> ExecCond {
+    ExecCond::MapDevice(0xF200..=0xF202, Box::<Multiplier>::default())
+}
+
+pub fn device() -> Test {
+    let mut g = TestGroup::new("device");
+
+    g.add(test!(
+        "multiplier",
+        Executor::new(
+            "LCONST r3 0xF200
+            STORE r2 r3 0
+            STORE r4 r3 1
+            LOAD r2 r3 2",
+            vec![
+                multiplier(),
+                ExecCond::SetReg(Register::R2, 12),
+                ExecCond::SetReg(Register::R4, 11),
+                ExecCond::CheckReg(Register::R2, 132),
+            ],
+        )
+    ));
+
+    g.add(test!(
+        "shadows_ram",
+        Executor::new(
+            "LCONST r3 0xF200; STORE r2 r3 2",
+            vec![
+                multiplier(),
+                ExecCond::SetReg(Register::R2, 0x1234),
+                ExecCond::CheckMem(0xF202, 0),
+                ExecCond::CheckMem(0xF203, 0),
+            ],
+        )
+    ));
+
+    g.add(test!(
+        "halt_register",
+        Executor::new(
+            "LCONST r2 2; STORE r2 ZX -1; LCONST r3 7",
+            vec![ExecCond::CheckReg(Register::R3, 0)],
+        )
+    ));
+
+    g.into()
+}
@@ -1,10 +1,25 @@
 use crate::runner::{Test, TestGroup};
 
+mod device;
+mod observer;
+mod run;
+mod serial;
 mod simple;
+mod snapshot;
+mod timing;
 
 pub fn exec_test() -> Test {
     TestGroup::construct(
         "exec".to_owned(),
-        vec![simple::simple(), simple::stack()],
+        vec![
+            simple::simple(),
+            simple::stack(),
+            serial::serial(),
+            device::device(),
+            run::run(),
+            observer::observer(),
+            timing::timing(),
+            snapshot::snapshot(),
+        ],
     )
 }
@@ -0,0 +1,34 @@
+use crate::runner::{test, ObserverTest, Test, TestGroup};
+
+pub fn observer() -> Test {
+    let mut g = TestGroup::new("observer");
+
+    g.add(test!(
+        "alu",
+        ObserverTest::new("INC r2 r2\nADD r3 r2 r2", vec!["R2 = 0x1", "R3 = 0x2"])
+    ));
+    g.add(test!(
+        "store",
+        ObserverTest::new("STORE r2 r3 2", vec!["[0x2] <- 0x0"])
+    ));
+    g.add(test!(
+        "load",
+        ObserverTest::new("LOAD r2 pc 1\n0x0042", vec!["[0x1] -> 0x42", "R2 = 0x42"])
+    ));
+    g.add(test!(
+        "branch",
+        ObserverTest::new(
+            "JEQ r2 A\nNOP\nA: JNE r2 A",
+            vec!["PC = 0x2", "0x0 -> 0x2 taken", "0x2 -> 0x2 not taken"]
+        )
+    ));
+    g.add(test!(
+        "jump",
+        ObserverTest::new(
+            "JMP A\n.space 40\nA: NOP",
+            vec!["[0x1] -> 0x2a", "PC = 0x2a"]
+        )
+    ));
+
+    g.into()
+}
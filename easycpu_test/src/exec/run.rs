@@ -0,0 +1,129 @@
+use easycpu_lib::{
+    cpu::Register,
+    exec::{ExecEvent, RegCompare, RegCondition, RunLimits, StopReason},
+};
+
+use crate::runner::{test, ExecCond, Executor, Test, TestGroup};
+
+const COUNTER: &str = "
+LCONST r2 0x4000
+LCONST r3 5
+LOOP:
+STORE r3 r2 0
+DEC r3 r3
+JNE r3 LOOP
+LOAD r4 r2 0
+";
+
+pub fn run() -> Test {
+    let mut g = TestGroup::new("run");
+
+    g.add(test!(
+        "breakpoint",
+        Executor::new(
+            COUNTER,
+            vec![
+                ExecCond::RunUntil(RunLimits::new().breakpoint(5), StopReason::Breakpoint(5)),
+                ExecCond::RunUntil(RunLimits::new().breakpoint(5), StopReason::Breakpoint(5)),
+                ExecCond::CheckReg(Register::R4, 1),
+            ],
+        )
+    ));
+
+    g.add(test!(
+        "watch",
+        Executor::new(
+            COUNTER,
+            vec![
+                ExecCond::RunUntil(
+                    RunLimits::new().watch_write(0x4000),
+                    StopReason::WriteWatch {
+                        pc: 5,
+                        addr: 0x4000,
+                        val: 5,
+                    },
+                ),
+                ExecCond::RunUntil(
+                    RunLimits::new().watch_read(0x4000),
+                    StopReason::ReadWatch {
+                        pc: 8,
+                        addr: 0x4000,
+                        val: 1,
+                    },
+                ),
+            ],
+        )
+    ));
+
+    let cond = RegCondition::new(Register::R3, RegCompare::Lt, 3);
+    g.add(test!(
+        "condition",
+        Executor::new(
+            COUNTER,
+            vec![
+                ExecCond::RunUntil(RunLimits::new().condition(cond), StopReason::Condition(cond)),
+            ],
+        )
+    ));
+
+    g.add(test!(
+        "events",
+        Executor::new(
+            "STORE r3 r2 1\nJNE r3 END\nNOP\nEND:",
+            vec![
+                ExecCond::SetReg(Register::R2, 0x4000),
+                ExecCond::SetReg(Register::R3, 7),
+                ExecCond::Step(vec![
+                    ExecEvent::REGGET(Register::R2, 0x4000),
+                    ExecEvent::REGGET(Register::R3, 7),
+                    ExecEvent::MEMSET(0x4001, 7),
+                ]),
+                ExecCond::Step(vec![
+                    ExecEvent::REGGET(Register::R3, 7),
+                    ExecEvent::REGGET(Register::PC, 1),
+                    ExecEvent::JUMP(3),
+                ]),
+                ExecCond::CheckMem(0x4001, 7),
+            ],
+        )
+    ));
+
+    g.add(test!(
+        "steps",
+        Executor::new(
+            COUNTER,
+            vec![
+                ExecCond::RunUntil(RunLimits::new().steps(3), StopReason::StepLimit),
+                ExecCond::RunUntil(RunLimits::new(), StopReason::Halted),
+            ],
+        )
+    ));
+
+    // Falls off the end of memory back to the start
+    g.add(test!(
+        "wrap",
+        Executor::new(
+            "NOP",
+            vec![
+                ExecCond::SetReg(Register::PC, 0xfffe),
+                ExecCond::RunUntil(RunLimits::new().breakpoint(0), StopReason::Breakpoint(0)),
+            ],
+        )
+    ));
+
+    // Stopped inside the call sequence, `next` continues at the `HALT` after
+    // the return
+    g.add(test!(
+        "next_call",
+        Executor::new(
+            "$INIT\n$CALL F\nHALT\nF:\n$FUNC 0 0 0\n$RET",
+            vec![
+                ExecCond::RunUntil(RunLimits::new().steps(5), StopReason::StepLimit),
+                ExecCond::Next(StopReason::StepLimit),
+                ExecCond::Next(StopReason::Halted),
+            ],
+        )
+    ));
+
+    g.into()
+}
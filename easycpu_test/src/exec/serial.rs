@@ -0,0 +1,96 @@
+use easycpu_lib::cpu::Register;
+
+use crate::runner::{test, ExecCond, Executor, Test, TestGroup};
+
+const PRINT_CHAR: &str = "
+LCONST r3 0xF104
+STORE r2 r3 +2
+LOAD r2 r3 +1
+ACONST r2 8
+STORE r2 r3 +1
+";
+
+pub(super) const PRINT_BYTE: &str = "
+LCONST r3 0xF104
+STORE r2 r3 +3
+LOAD r2 r3 +1
+ACONST r2 8
+STORE r2 r3 +1
+";
+
+pub(super) const READ_CHAR: &str = "
+LCONST r3 0xF100
+LCONST r4 0x7f
+WAIT:
+LOAD r2 r3 0
+LOAD r5 r3 1
+SUB r2 r2 r5
+AND r2 r2 r4
+ACONST r2 -8
+JLT r2 WAIT
+
+MOV r2 ZX
+LOAD.HS r2 r3 +2
+LOAD r4 r3 +1
+ACONST r4 8
+STORE r4 r3 +1
+";
+
+pub fn serial() -> Test {
+    let mut g = TestGroup::new("serial");
+
+    g.add(test!(
+        "print_char",
+        Executor::new(
+            format!("LCONST r2 0x4800 {}", PRINT_CHAR),
+            vec![ExecCond::CheckSerial(b"H".to_vec())],
+        )
+    ));
+
+    g.add(test!(
+        "print_byte_window",
+        Executor::new(
+            format!("LCONST r2 0x69 {}", PRINT_BYTE),
+            vec![ExecCond::CheckSerial(b"i".to_vec())],
+        )
+    ));
+
+    g.add(test!(
+        "input_pos",
+        Executor::new(
+            "LCONST r3 0xF100; LOAD r2 r3 0",
+            vec![
+                ExecCond::SerialInput(b"AB".to_vec()),
+                ExecCond::CheckReg(Register::R2, 16),
+            ],
+        )
+    ));
+
+    g.add(test!(
+        "halt_cell",
+        Executor::new(
+            "LCONST r3 0xFFFF; LOAD r2 r3 0",
+            vec![
+                ExecCond::SetReg(Register::R2, 7),
+                ExecCond::CheckReg(Register::R2, 0),
+            ],
+        )
+    ));
+
+    g.add(test!(
+        "echo",
+        Executor::new(
+            format!("{} {}", READ_CHAR, PRINT_BYTE),
+            vec![
+                ExecCond::SerialInput(b"x".to_vec()),
+                ExecCond::CheckSerial(b"x".to_vec()),
+            ],
+        )
+        .add_case(vec![
+            ExecCond::SerialInput(b"\n".to_vec()),
+            ExecCond::CheckSerial(b"\n".to_vec()),
+        ])
+    ));
+
+    g.into()
+}
@@ -32,6 +32,49 @@ pub fn simple() -> Test {
         ])
     ));
 
+    g.add(test!(
+        "long_jump",
+        Executor::new(
+            "JEQ r3 FAR; LCONST r2 1; HALT; .space 40; FAR: LCONST r2 2",
+            vec![
+                ExecCond::SetReg(Register::R3, 0),
+                ExecCond::CheckReg(Register::R2, 2),
+            ],
+        )
+        .add_case(vec![
+            ExecCond::SetReg(Register::R3, 5),
+            ExecCond::CheckReg(Register::R2, 1),
+        ])
+    ));
+
+    // Constants sized by the label after them keep their largest size
+    g.add(test!(
+        "label_const",
+        Executor::new("LCONST r2 3 - END; END:", vec![ExecCond::CheckReg(Register::R2, 1)])
+    ));
+
+    g.add(test!(
+        "label_const_literal",
+        Executor::new(
+            "LCONST r2 4098 - END; END:",
+            vec![ExecCond::CheckReg(Register::R2, 4095)],
+        )
+    ));
+
+    g.add(test!(
+        "self_modifying",
+        Executor::new(
+            "LLABEL r3 PATCH
+            LCONST r4 0x5490 # DEC r2 r2
+            LCONST r5 2
+            PATCH: INC r2 r2
+            STORE r4 r3 0
+            DEC r5 r5
+            JNE r5 PATCH",
+            vec![ExecCond::CheckReg(Register::R2, 0)],
+        )
+    ));
+
     g.add(test!(
         "store",
         Executor::new(
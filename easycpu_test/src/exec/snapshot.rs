@@ -0,0 +1,45 @@
+use easycpu_lib::exec::SnapshotError;
+
+use super::serial::{PRINT_BYTE, READ_CHAR};
+use crate::runner::{test, SnapshotTest, Test, TestGroup};
+
+const COUNTER: &str = "
+LCONST r2 0x4000
+LCONST r3 5
+LOOP:
+STORE r3 r2 0
+INC r2 r2
+DEC r3 r3
+JNE r3 LOOP
+LCONST r4 0x9000
+STORE r2 r4 0
+";
+
+pub fn snapshot() -> Test {
+    let second = READ_CHAR.replace("WAIT", "WAIT2");
+    let echo_twice = format!("{} {} {} {}", READ_CHAR, PRINT_BYTE, second, PRINT_BYTE);
+
+    TestGroup::construct(
+        "snapshot".to_owned(),
+        vec![
+            test!("start", SnapshotTest::resume(COUNTER, 0)),
+            test!("loop", SnapshotTest::resume(COUNTER, 9)),
+            test!("after_loop", SnapshotTest::resume(COUNTER, 27)),
+            // First byte is echoed, the second waits in the serial port
+            test!("serial", SnapshotTest::resume(&echo_twice, 30).input(b"ab")),
+            test!(
+                "magic",
+                SnapshotTest::corrupt(
+                    COUNTER,
+                    0,
+                    SnapshotError::Invalid(String::from("missing magic"))
+                )
+            ),
+            test!(
+                "checksum",
+                SnapshotTest::corrupt(COUNTER, 9, SnapshotError::Checksum)
+            ),
+            test!("layout", SnapshotTest::layout(COUNTER)),
+        ],
+    )
+}
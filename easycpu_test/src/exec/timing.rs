@@ -0,0 +1,48 @@
+use crate::runner::{test, ExecCond, Executor, Test, TestGroup};
+
+pub fn timing() -> Test {
+    TestGroup::construct(
+        "timing".to_owned(),
+        vec![
+            // Two cycles per instruction, three more until the HALT state
+            test!(
+                "alu",
+                Executor::new("INC r2 r2\nINC r2 r2", vec![ExecCond::CheckCycles(9)])
+            ),
+            // Memory accesses overlap with fetching the next instruction
+            test!(
+                "memory",
+                Executor::new(
+                    "LOAD r3 r2 0\nSTORE r3 r2 1",
+                    vec![ExecCond::CheckCycles(9)]
+                )
+            ),
+            // Literal of LCONST is stepped over as an instruction
+            test!(
+                "loop",
+                Executor::new(
+                    "
+                    LCONST r3 3
+                    LOOP:
+                    DEC r3 r3
+                    JNE r3 LOOP
+                    ",
+                    vec![ExecCond::CheckCycles(21)]
+                )
+            ),
+            // Counted past the width of a word
+            test!(
+                "long",
+                Executor::new(
+                    "
+                    LCONST r3 20000
+                    LOOP:
+                    DEC r3 r3
+                    JNE r3 LOOP
+                    ",
+                    vec![ExecCond::CheckCycles(80009)]
+                )
+            ),
+        ],
+    )
+}
@@ -16,7 +16,7 @@ pub mod stack;
 fn write_perf(path: String, perf: &[(String, PerformanceLog)]) -> Result<(), io::Error> {
     let mut f = fs::File::create(path)?;
 
-    f.write_all(b"test_name,proglen,cycles,nop,alu,load,store,branch\n")?;
+    f.write_all(b"test_name,proglen,cycles,nop,alu,load,store,branch,hdl_cycles\n")?;
     for (name, entry) in perf.iter() {
         let cycles = entry.exec.nop
             + entry.exec.alu
@@ -25,7 +25,7 @@ fn write_perf(path: String, perf: &[(String, PerformanceLog)]) -> Result<(), io:
             + entry.exec.branch;
         writeln!(
             f,
-            "\"{}\",{},{},{},{},{},{},{}",
+            "\"{}\",{},{},{},{},{},{},{},{}",
             name,
             entry.program_len,
             cycles,
@@ -33,7 +33,8 @@ fn write_perf(path: String, perf: &[(String, PerformanceLog)]) -> Result<(), io:
             entry.exec.alu,
             entry.exec.load,
             entry.exec.store,
-            entry.exec.branch
+            entry.exec.branch,
+            entry.exec.cycles
         )?;
     }
 
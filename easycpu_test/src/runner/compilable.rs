@@ -1,3 +1,7 @@
+use easycpu_lib::asm::AsmOptions;
+use easycpu_lib::compile::CompiledProgram;
+use easycpu_lib::parser::PosCompileError;
+
 use super::{TestContext, TestError, Testable};
 
 pub struct CompilableTest {
@@ -9,18 +13,31 @@ impl CompilableTest {
         CompilableTest { code: code.into() }
     }
 
-    pub fn compile(code: &str) -> Result<Vec<u16>, TestError> {
-        let errors = easycpu_lib::asm::parse_and_compile(code);
+    pub fn compile(code: &str) -> Result<CompiledProgram, TestError> {
+        Self::compile_with(code, &AsmOptions::new())
+    }
+
+    pub fn compile_with(code: &str, options: &AsmOptions) -> Result<CompiledProgram, TestError> {
+        let errors = easycpu_lib::asm::parse_and_compile(code, options);
         match errors {
-            Err(e) => Err(TestError::CompilationError(
-                e.into_iter()
-                    .map(|e| format!("Error at {}: {:#?}", e.start_pos, e.error))
-                    .collect::<Vec<String>>()
-                    .join("\n"),
-            )),
+            Err(e) => Err(TestError::CompilationError(Self::describe(&e))),
             Ok(res) => Ok(res),
         }
     }
+
+    pub fn describe(errors: &[PosCompileError]) -> String {
+        errors
+            .iter()
+            .map(|e| {
+                let mut msg = format!("Error at {}: {:#?}", e.start_pos, e.error);
+                for call_site in e.expanded_from.iter() {
+                    msg += &format!("\n  expanded from {}", call_site);
+                }
+                msg
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
 }
 
 impl Testable for CompilableTest {
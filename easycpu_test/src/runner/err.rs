@@ -1,18 +1,20 @@
+use std::fmt;
+
 #[derive(Debug, Clone)]
 pub enum TestError {
     CompilationError(String),
     InvalidResult(String),
-    TimedOut,
+    TimedOut(String),
     Elevating,
 }
 
-impl ToString for TestError {
-    fn to_string(&self) -> String {
+impl fmt::Display for TestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            TestError::CompilationError(e) => format!("Failed to compile: {}", e),
-            TestError::InvalidResult(e) => format!("Invalid {}", e),
-            TestError::TimedOut => "Timed out".to_owned(),
-            TestError::Elevating => String::from(""),
+            TestError::CompilationError(e) => write!(f, "Failed to compile: {}", e),
+            TestError::InvalidResult(e) => write!(f, "Invalid {}", e),
+            TestError::TimedOut(at) => write!(f, "Timed out at {}", at),
+            TestError::Elevating => Ok(()),
         }
     }
 }
@@ -28,4 +30,32 @@ impl TestError {
             Ok(())
         }
     }
+
+    /// Like `check_eq` for counts which do not fit in a word
+    pub fn check_count(name: String, expected: usize, actual: usize) -> Result<(), TestError> {
+        if expected != actual {
+            Err(TestError::InvalidResult(format!(
+                "{}: {} != {}",
+                name, expected, actual
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Like `check_eq` for any value printed with `Debug`
+    pub fn check_value<T: PartialEq + fmt::Debug>(
+        name: String,
+        expected: &T,
+        actual: &T,
+    ) -> Result<(), TestError> {
+        if expected != actual {
+            Err(TestError::InvalidResult(format!(
+                "{}: {:?} != {:?}",
+                name, expected, actual
+            )))
+        } else {
+            Ok(())
+        }
+    }
 }
@@ -1,9 +1,13 @@
+use std::ops::RangeInclusive;
+
 use easycpu_lib::{
+    asm::AsmOptions,
     cpu,
-    exec::{ExecCpu, ExecStats},
+    exec::{Device, ExecCpu, ExecEvent, ExecStats, RunLimits, StopReason, Timing},
+    parser::SourceSet,
 };
 
-use super::{log::PerformanceLog, CompilableTest, TestContext, TestError, Testable};
+use super::{log::PerformanceLog, Source, TestContext, TestError, Testable};
 
 #[derive(Clone, Debug)]
 pub enum ExecCond {
@@ -15,13 +19,25 @@ pub enum ExecCond {
 
     CheckStack(Vec<u16>),
     SetStack(Vec<u16>),
+
+    CheckSerial(Vec<u8>),
+    SerialInput(Vec<u8>),
+
+    MapDevice(RangeInclusive<u16>, Box<dyn Device>),
+    RunUntil(RunLimits, StopReason),
+    /// Execute one instruction reporting these events
+    Step(Vec<ExecEvent>),
+    /// Execute like the debugger `next`, with no other limits
+    Next(StopReason),
+    /// Clock cycles of the HDL timing
+    CheckCycles(usize),
 }
 
 impl ExecCond {
     pub fn check_cond(&self, cpu: &mut ExecCpu) -> Result<(), TestError> {
         match self {
             ExecCond::CheckReg(reg, val) => TestError::check_eq(
-                format!("Register {}", reg.to_string()),
+                format!("Register {}", reg),
                 *val,
                 cpu.get_reg(*reg),
             ),
@@ -49,6 +65,23 @@ impl ExecCond {
                 Ok(())
             }
 
+            ExecCond::CheckSerial(expected) => {
+                let actual = cpu.serial().map(|s| s.output()).unwrap_or_default();
+                if actual != expected.as_slice() {
+                    Err(TestError::InvalidResult(format!(
+                        "serial output: {:?} != {:?}",
+                        String::from_utf8_lossy(expected),
+                        String::from_utf8_lossy(actual)
+                    )))
+                } else {
+                    Ok(())
+                }
+            }
+
+            ExecCond::CheckCycles(cycles) => {
+                TestError::check_count(String::from("cycles"), *cycles, cpu.get_stats().cycles)
+            }
+
             _ => Ok(()),
         }
     }
@@ -71,6 +104,46 @@ impl ExecCond {
                 }
             }
 
+            ExecCond::MapDevice(range, device) => {
+                cpu.map_device(range.clone(), device.clone());
+            }
+
+            ExecCond::RunUntil(limits, expected) => {
+                let actual = cpu.run(limits);
+                if actual != *expected {
+                    return Err(TestError::InvalidResult(format!(
+                        "stop reason: {:?} != {:?}",
+                        expected, actual
+                    )));
+                }
+            }
+
+            ExecCond::Next(expected) => {
+                let actual = cpu.next(&RunLimits::new());
+                if actual != *expected {
+                    return Err(TestError::InvalidResult(format!(
+                        "stop reason: {:?} != {:?}",
+                        expected, actual
+                    )));
+                }
+            }
+
+            ExecCond::SerialInput(data) => {
+                if let Some(serial) = cpu.serial_mut() {
+                    serial.push_input(data);
+                }
+            }
+
+            ExecCond::Step(expected) => {
+                let (_, events) = cpu.exec_next();
+                if events != *expected {
+                    return Err(TestError::InvalidResult(format!(
+                        "events: {:?} != {:?}",
+                        expected, events
+                    )));
+                }
+            }
+
             _ => {}
         }
 
@@ -79,15 +152,26 @@ impl ExecCond {
 }
 
 pub struct Executor {
-    code: String,
+    source: Source,
     combos: Vec<Vec<ExecCond>>,
+    options: AsmOptions,
 }
 
 impl Executor {
     pub fn new(code: impl Into<String>, conds: Vec<ExecCond>) -> Executor {
         Executor {
-            code: code.into() + " \nHALT",
+            source: Source::Code(code.into() + " \nHALT"),
             combos: vec![conds],
+            options: AsmOptions::new(),
+        }
+    }
+
+    /// Objects linked in order, the program has to halt by itself
+    pub fn linked(objects: Vec<&str>, conds: Vec<ExecCond>) -> Executor {
+        Executor {
+            source: Source::objects(objects),
+            combos: vec![conds],
+            options: AsmOptions::new(),
         }
     }
 
@@ -103,14 +187,25 @@ impl Executor {
         self.combos.push(conds);
         self
     }
+
+    pub fn options(mut self, options: AsmOptions) -> Self {
+        self.options = options;
+        self
+    }
 }
 
 impl Testable for Executor {
     fn run(&self, ctx: &TestContext) -> Result<(), TestError> {
-        let compiled = CompilableTest::compile(&self.code)?;
-        let program_len = compiled.len();
+        let mut sources = SourceSet::new();
+        let compiled = self
+            .source
+            .build(&self.options, &mut sources)
+            .map_err(|failure| TestError::CompilationError(failure.message))?;
+        let program_len = compiled.code.len();
 
-        let cpu = ExecCpu::new(compiled);
+        let mut cpu = ExecCpu::new(compiled.code);
+        cpu.set_timing(Timing::Hdl);
+        let limits = RunLimits::new().steps(0xf000);
 
         let mut stats = ExecStats {
             ..Default::default()
@@ -123,14 +218,17 @@ impl Testable for Executor {
                 c.apply_cond(&mut cpu)?;
             }
 
-            let mut lim = 0xf000;
-            while cpu.get_mem(0xffff) != 0 {
-                cpu.exec_next();
-
-                lim -= 1;
-                if lim == 0 {
-                    return Err(TestError::TimedOut);
-                }
+            if cpu.run(&limits) == StopReason::StepLimit {
+                let pc = cpu.peek_reg(cpu::Register::PC);
+                let line = match &self.source {
+                    Source::Code(code) => compiled.source_map.source_line(code, pc),
+                    _ => None,
+                };
+                let at = match line {
+                    Some((line, text)) => format!("line {}: {}", line + 1, text.trim()),
+                    None => format!("{:#06x}", pc),
+                };
+                return Err(TestError::TimedOut(at));
             }
 
             stats += cpu.get_stats();
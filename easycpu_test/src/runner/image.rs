@@ -0,0 +1,55 @@
+use easycpu_lib::image::{read_image, write_image, ImageError, ImageFormat};
+
+use super::{TestContext, TestError, Testable};
+
+/// Writes code in an image format and reads it back, or reads a hand written image
+pub struct ImageTest {
+    format: ImageFormat,
+    input: Option<String>,
+    expected: Result<Vec<u16>, ImageError>,
+}
+
+impl ImageTest {
+    pub fn round_trip(format: ImageFormat, code: Vec<u16>) -> ImageTest {
+        ImageTest {
+            format,
+            input: None,
+            expected: Ok(code),
+        }
+    }
+
+    pub fn read(
+        format: ImageFormat,
+        input: impl Into<String>,
+        expected: Result<Vec<u16>, ImageError>,
+    ) -> ImageTest {
+        ImageTest {
+            format,
+            input: Some(input.into()),
+            expected,
+        }
+    }
+}
+
+impl Testable for ImageTest {
+    fn run(&self, _: &TestContext) -> Result<(), TestError> {
+        let data = match (&self.input, &self.expected) {
+            (Some(input), _) => input.clone().into_bytes(),
+            (None, Ok(code)) => write_image(self.format, code),
+            (None, Err(_)) => unreachable!("round trip always expects code"),
+        };
+        let actual = read_image(self.format, &data);
+
+        if actual != self.expected {
+            return Err(TestError::InvalidResult(format!(
+                "{} image:\n{}\n{:x?} != {:x?}",
+                self.format,
+                String::from_utf8_lossy(&data),
+                self.expected,
+                actual
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -47,7 +47,7 @@ impl Logger {
                 println!("Test at {}", self.position);
             }
 
-            println!("{}\n", err.to_string());
+            println!("{}\n", err);
         }
     }
 
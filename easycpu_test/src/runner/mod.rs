@@ -2,7 +2,12 @@ mod compilable;
 mod err;
 mod executor;
 mod group;
+mod image;
 mod log;
+mod observer;
+mod output;
+mod snapshot;
+mod source;
 mod stackopt;
 mod test;
 
@@ -10,6 +15,11 @@ pub use compilable::CompilableTest;
 pub use err::TestError;
 pub use executor::{ExecCond, Executor};
 pub use group::TestGroup;
+pub use image::ImageTest;
 pub use log::{LogEntry, Logger, PerformanceLog};
+pub use observer::ObserverTest;
+pub use output::OutputTest;
+pub use snapshot::SnapshotTest;
+pub use source::Source;
 pub use stackopt::StackOptExec;
 pub use test::{test, Test, TestContext, Testable};
@@ -0,0 +1,90 @@
+use easycpu_lib::{
+    cpu,
+    exec::{ExecCpu, ExecObserver, ExecStats, RunLimits},
+};
+
+use super::{CompilableTest, TestContext, TestError, Testable};
+
+/// Writes down every hook call except the instruction ones
+#[derive(Clone, Debug, Default)]
+struct Recorder {
+    calls: Vec<String>,
+    instructions: usize,
+}
+
+impl ExecObserver for Recorder {
+    fn after_instruction(&mut self, _: u16, _: &cpu::Instruction) {
+        self.instructions += 1;
+    }
+
+    fn register_write(&mut self, reg: cpu::Register, val: u16) {
+        self.calls.push(format!("{} = {:#x}", reg, val));
+    }
+
+    fn memory_read(&mut self, addr: u16, val: u16) {
+        self.calls.push(format!("[{:#x}] -> {:#x}", addr, val));
+    }
+
+    fn memory_write(&mut self, addr: u16, val: u16) {
+        self.calls.push(format!("[{:#x}] <- {:#x}", addr, val));
+    }
+
+    fn branch(&mut self, pc: u16, target: u16, taken: bool) {
+        let verb = if taken { "taken" } else { "not taken" };
+        self.calls
+            .push(format!("{:#x} -> {:#x} {}", pc, target, verb));
+    }
+
+    fn duplicate(&self) -> Box<dyn ExecObserver> {
+        Box::new(self.clone())
+    }
+}
+
+/// Runs a program with an observer attached and checks the calls it got
+pub struct ObserverTest {
+    code: String,
+    calls: Vec<String>,
+}
+
+impl ObserverTest {
+    pub fn new(code: &str, calls: Vec<&str>) -> ObserverTest {
+        ObserverTest {
+            code: code.to_owned() + "\nHALT",
+            calls: calls.into_iter().map(String::from).collect(),
+        }
+    }
+}
+
+impl Testable for ObserverTest {
+    fn run(&self, _: &TestContext) -> Result<(), TestError> {
+        let compiled = CompilableTest::compile(&self.code)?;
+        let mut cpu = ExecCpu::new(compiled.code);
+        cpu.attach(Box::<Recorder>::default());
+        cpu.run(&RunLimits::new().steps(0x1000));
+
+        let recorder = cpu.observer::<Recorder>().ok_or_else(|| {
+            TestError::InvalidResult(String::from("observer detached while running"))
+        })?;
+        // Without the `HALT` store
+        let calls = &recorder.calls[..recorder.calls.len().saturating_sub(1)];
+        if calls != self.calls.as_slice() {
+            return Err(TestError::InvalidResult(format!(
+                "calls: {:?} != {:?}",
+                self.calls, calls
+            )));
+        }
+
+        let stats = cpu.get_stats();
+        let counted = stats.nop + stats.alu + stats.load + stats.store + stats.branch;
+        TestError::check_count(String::from("instructions"), counted, recorder.instructions)?;
+
+        cpu.detach::<ExecStats>();
+        if cpu.get_stats().alu != 0 || cpu.observer::<Recorder>().is_none() {
+            return Err(TestError::InvalidResult(String::from(
+                "wrong observer detached",
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,330 @@
+use easycpu_lib::{
+    asm::{disasm::disassemble_program, listing::listing, parse_and_compile, AsmOptions},
+    compile::CompiledProgram,
+    diagnostic::{render, render_json, render_warnings, Style},
+    executable::Executable,
+    image::{ImageError, ImageFormat},
+    parser::SourceSet,
+};
+
+use super::{source::Failure, Source, TestContext, TestError, Testable};
+
+#[derive(Debug)]
+enum Expected {
+    /// One based source line of every compiled word
+    SourceLines(Vec<usize>),
+    /// Full names and addresses of the symbol table
+    Symbols(Vec<(String, u16)>),
+    Listing(String),
+    /// Lines of the disassembly, which has to assemble back to the same words
+    Disassembly(Vec<String>),
+    /// Words of the program, its jumps and how many of them were relaxed
+    Layout(usize, usize, usize),
+    /// Entry and segments of the program written as an executable and loaded
+    /// back like a raw binary
+    Executable(u16, usize),
+    /// Error loading the executable after changing the byte at the offset
+    CorruptExecutable(usize, ImageError),
+    /// Names of the warnings in the order they are reported
+    Warnings(Vec<String>),
+    /// Whole plain text rendering of the warnings
+    WarningText(String),
+
+    /// Text contained in the errors
+    Error(String),
+    /// Code of one of the errors
+    ErrorCode(String),
+    /// Whole plain text rendering of the errors
+    Diagnostic(String),
+    /// Text contained in the JSON rendering of the errors
+    DiagnosticJson(String),
+}
+
+impl Expected {
+    fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            Expected::Error(_)
+                | Expected::ErrorCode(_)
+                | Expected::Diagnostic(_)
+                | Expected::DiagnosticJson(_)
+        )
+    }
+}
+
+/// Assembles a program and compares one of its outputs, or its errors, with
+/// the expected ones
+pub struct OutputTest {
+    source: Source,
+    options: AsmOptions,
+    expected: Expected,
+}
+
+impl OutputTest {
+    fn new(source: impl Into<Source>, expected: Expected) -> OutputTest {
+        OutputTest {
+            source: source.into(),
+            options: AsmOptions::new(),
+            expected,
+        }
+    }
+
+    pub fn source_lines(source: impl Into<Source>, lines: Vec<usize>) -> OutputTest {
+        Self::new(source, Expected::SourceLines(lines))
+    }
+
+    pub fn symbols(source: impl Into<Source>, symbols: Vec<(&str, u16)>) -> OutputTest {
+        let symbols = symbols
+            .into_iter()
+            .map(|(name, addr)| (name.to_owned(), addr))
+            .collect();
+        Self::new(source, Expected::Symbols(symbols))
+    }
+
+    pub fn listing(source: impl Into<Source>, expected: impl Into<String>) -> OutputTest {
+        Self::new(source, Expected::Listing(expected.into()))
+    }
+
+    pub fn disasm(source: impl Into<Source>, lines: Vec<&str>) -> OutputTest {
+        let lines = lines.into_iter().map(String::from).collect();
+        Self::new(source, Expected::Disassembly(lines))
+    }
+
+    pub fn layout(
+        source: impl Into<Source>,
+        words: usize,
+        jumps: usize,
+        relaxed: usize,
+    ) -> OutputTest {
+        Self::new(source, Expected::Layout(words, jumps, relaxed))
+    }
+
+    pub fn executable(source: impl Into<Source>, entry: u16, segments: usize) -> OutputTest {
+        Self::new(source, Expected::Executable(entry, segments))
+    }
+
+    pub fn corrupt_executable(
+        source: impl Into<Source>,
+        offset: usize,
+        error: ImageError,
+    ) -> OutputTest {
+        Self::new(source, Expected::CorruptExecutable(offset, error))
+    }
+
+    pub fn warnings(source: impl Into<Source>, names: Vec<&str>) -> OutputTest {
+        let names = names.into_iter().map(String::from).collect();
+        Self::new(source, Expected::Warnings(names))
+    }
+
+    pub fn warning_text(source: impl Into<Source>, expected: &str) -> OutputTest {
+        Self::new(source, Expected::WarningText(expected.to_owned()))
+    }
+
+    pub fn failing(source: impl Into<Source>, error: impl Into<String>) -> OutputTest {
+        Self::new(source, Expected::Error(error.into()))
+    }
+
+    pub fn error_code(source: impl Into<Source>, code: &str) -> OutputTest {
+        Self::new(source, Expected::ErrorCode(code.to_owned()))
+    }
+
+    pub fn diagnostic(source: impl Into<Source>, expected: &str) -> OutputTest {
+        Self::new(source, Expected::Diagnostic(expected.to_owned()))
+    }
+
+    pub fn diagnostic_json(source: impl Into<Source>, expected: &str) -> OutputTest {
+        Self::new(source, Expected::DiagnosticJson(expected.to_owned()))
+    }
+
+    pub fn options(mut self, options: AsmOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn check_failure(&self, failure: &Failure, sources: &SourceSet) -> Result<(), TestError> {
+        let (matches, actual) = match &self.expected {
+            Expected::Error(expected) => {
+                let actual = failure.message.clone();
+                (actual.contains(expected), actual)
+            }
+            Expected::ErrorCode(code) => {
+                let codes: Vec<&str> = failure.errors.iter().map(|e| e.error.code()).collect();
+                (codes.contains(&code.as_str()), format!("{:?}", codes))
+            }
+            Expected::Diagnostic(expected) => {
+                let actual = render(&failure.errors, sources, Style::Plain);
+                (&actual == expected, actual)
+            }
+            Expected::DiagnosticJson(expected) => {
+                let actual = render_json(&failure.errors, sources);
+                (actual.contains(expected), actual)
+            }
+            _ => unreachable!("successful outputs are checked on the program"),
+        };
+
+        if !matches {
+            return Err(TestError::InvalidResult(format!(
+                "errors, expected {:?}:\n{}",
+                self.expected, actual
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_program(
+        &self,
+        compiled: &CompiledProgram,
+        sources: &SourceSet,
+    ) -> Result<(), TestError> {
+        match &self.expected {
+            Expected::SourceLines(expected) => {
+                TestError::check_count(
+                    String::from("source map length"),
+                    compiled.code.len(),
+                    compiled.source_map.len(),
+                )?;
+                let lines: Vec<usize> = compiled
+                    .source_map
+                    .iter()
+                    .map(|(start, _)| start.line + 1)
+                    .collect();
+                TestError::check_value(String::from("source lines"), expected, &lines)
+            }
+            Expected::Symbols(expected) => {
+                let symbols: Vec<(String, u16)> = compiled
+                    .symbols
+                    .iter()
+                    .map(|s| (s.full_name(), s.addr))
+                    .collect();
+                TestError::check_value(String::from("symbols"), expected, &symbols)
+            }
+            Expected::Listing(expected) => {
+                let code = sources.get(0).map(|file| file.text.as_str());
+                let actual = listing(code.unwrap_or_default(), compiled);
+                if actual != *expected {
+                    return Err(TestError::InvalidResult(format!(
+                        "listing:\n{}\n!=\n{}",
+                        expected, actual
+                    )));
+                }
+                Ok(())
+            }
+            Expected::Disassembly(lines) => Self::check_disasm(compiled, lines),
+            Expected::Layout(words, jumps, relaxed) => {
+                let layout = &compiled.layout;
+                let length = compiled.code.len();
+                TestError::check_count(String::from("words"), *words, length)?;
+                TestError::check_count(String::from("jumps"), *jumps, layout.jumps)?;
+                TestError::check_count(String::from("relaxed"), *relaxed, layout.relaxed)
+            }
+            Expected::Executable(entry, segments) => {
+                Self::check_executable(compiled, *entry, *segments)
+            }
+            Expected::CorruptExecutable(offset, error) => {
+                let mut data = Self::to_executable(compiled, 0).write();
+                data[*offset] ^= 0x01;
+                let actual = Executable::load(ImageFormat::Bin, &data).err();
+                TestError::check_value(String::from("error"), &Some(error.clone()), &actual)
+            }
+            Expected::Warnings(expected) => {
+                let actual: Vec<String> = compiled
+                    .warnings
+                    .iter()
+                    .map(|w| w.error.code().to_owned())
+                    .collect();
+                TestError::check_value(String::from("warnings"), expected, &actual)
+            }
+            Expected::WarningText(expected) => {
+                let actual = render_warnings(&compiled.warnings, sources, Style::Plain);
+                if actual != *expected {
+                    return Err(TestError::InvalidResult(format!("warnings:\n{}", actual)));
+                }
+                Ok(())
+            }
+            _ => unreachable!("failures are checked on the errors"),
+        }
+    }
+
+    fn check_disasm(compiled: &CompiledProgram, lines: &[String]) -> Result<(), TestError> {
+        let source = disassemble_program(&compiled.code, Some(&compiled.symbols));
+
+        let recompiled = parse_and_compile(&source, &AsmOptions::new()).map_err(|e| {
+            TestError::InvalidResult(format!(
+                "disassembly does not compile: {:?}\n{}",
+                e[0].error, source
+            ))
+        })?;
+        if recompiled.code != compiled.code {
+            return Err(TestError::InvalidResult(format!(
+                "reassembled code differs:\n{}",
+                source
+            )));
+        }
+
+        for line in lines {
+            if !source.lines().any(|l| l.trim() == line) {
+                return Err(TestError::InvalidResult(format!(
+                    "disassembly is missing {:?}:\n{}",
+                    line, source
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn to_executable(compiled: &CompiledProgram, entry: u16) -> Executable {
+        let mut exe = Executable::from_program(compiled);
+        exe.entry = entry;
+        exe.files = vec![String::from("main.s")];
+        exe
+    }
+
+    fn check_executable(
+        compiled: &CompiledProgram,
+        entry: u16,
+        segments: usize,
+    ) -> Result<(), TestError> {
+        let exe = Self::to_executable(compiled, entry);
+        let loaded = Executable::load(ImageFormat::Bin, &exe.write())
+            .map_err(|e| TestError::InvalidResult(e.to_string()))?;
+
+        TestError::check_eq(String::from("entry"), entry, loaded.entry)?;
+        TestError::check_count(String::from("segments"), segments, loaded.segments.len())?;
+        TestError::check_value(String::from("memory"), &compiled.code, &loaded.memory())?;
+
+        let symbols = |exe: &Executable| -> Vec<(String, u16)> {
+            exe.symbols
+                .iter()
+                .flat_map(|s| s.iter())
+                .map(|s| (s.full_name(), s.addr))
+                .collect()
+        };
+        TestError::check_value(String::from("symbols"), &symbols(&exe), &symbols(&loaded))?;
+
+        let spans = |exe: &Executable| {
+            let spans: Vec<_> = exe.source_map.iter().flat_map(|m| m.iter()).collect();
+            format!("{:?}", spans)
+        };
+        if spans(&exe) != spans(&loaded) || loaded.files != exe.files {
+            return Err(TestError::InvalidResult(String::from("source map differs")));
+        }
+        Ok(())
+    }
+}
+
+impl Testable for OutputTest {
+    fn run(&self, _: &TestContext) -> Result<(), TestError> {
+        let mut sources = SourceSet::new();
+        let result = self.source.build(&self.options, &mut sources);
+
+        match (result, self.expected.is_failure()) {
+            (Ok(compiled), false) => self.check_program(&compiled, &sources),
+            (Err(failure), true) => self.check_failure(&failure, &sources),
+            (Ok(_), true) => Err(TestError::InvalidResult(format!(
+                "compilation succeeded, expected {:?}",
+                self.expected
+            ))),
+            (Err(failure), false) => Err(TestError::CompilationError(failure.message)),
+        }
+    }
+}
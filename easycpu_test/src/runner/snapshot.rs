@@ -0,0 +1,154 @@
+use easycpu_lib::{
+    cpu::Register,
+    exec::{ExecCpu, Ram, RunLimits, Snapshot, SnapshotError, StopReason, Timing},
+};
+
+use super::{CompilableTest, TestContext, TestError, Testable};
+
+enum Expected {
+    /// Same result as running without interruption
+    Resumed,
+    /// Error after changing the byte at the given offset
+    Corrupt(usize, SnapshotError),
+    /// Error restoring into a machine with another device mapped
+    Layout,
+}
+
+/// Stops a program after some steps, saves the machine and continues it from
+/// the file in a fresh machine
+pub struct SnapshotTest {
+    code: String,
+    steps: usize,
+    input: Vec<u8>,
+    expected: Expected,
+}
+
+impl SnapshotTest {
+    pub fn resume(code: &str, steps: usize) -> SnapshotTest {
+        SnapshotTest {
+            code: code.to_owned() + " \nHALT",
+            steps,
+            input: Vec::new(),
+            expected: Expected::Resumed,
+        }
+    }
+
+    pub fn corrupt(code: &str, offset: usize, error: SnapshotError) -> SnapshotTest {
+        SnapshotTest {
+            expected: Expected::Corrupt(offset, error),
+            ..Self::resume(code, 0)
+        }
+    }
+
+    pub fn layout(code: &str) -> SnapshotTest {
+        SnapshotTest {
+            expected: Expected::Layout,
+            ..Self::resume(code, 0)
+        }
+    }
+
+    /// Bytes sent to the serial port before running
+    pub fn input(mut self, input: &[u8]) -> Self {
+        self.input = input.to_vec();
+        self
+    }
+
+    fn finish(cpu: &mut ExecCpu) -> Result<(), TestError> {
+        match cpu.run(&RunLimits::new().steps(0xf000)) {
+            StopReason::Halted => Ok(()),
+            _ => Err(TestError::TimedOut(String::from("after restoring"))),
+        }
+    }
+
+    fn check_same(expected: &mut ExecCpu, actual: &mut ExecCpu) -> Result<(), TestError> {
+        let regs = [
+            Register::PC,
+            Register::R2,
+            Register::R3,
+            Register::R4,
+            Register::R5,
+            Register::LP,
+            Register::SP,
+        ];
+        for reg in regs {
+            TestError::check_eq(
+                format!("Register {}", reg),
+                expected.peek_reg(reg),
+                actual.peek_reg(reg),
+            )?;
+        }
+
+        for addr in 0..=0xffff {
+            TestError::check_eq(
+                format!("Memory {:#06x}", addr),
+                expected.get_mem(addr),
+                actual.get_mem(addr),
+            )?;
+        }
+
+        let serial = |cpu: &ExecCpu| cpu.serial().map(|s| s.output().to_vec());
+        if serial(expected) != serial(actual) {
+            return Err(TestError::InvalidResult(format!(
+                "serial output: {:?} != {:?}",
+                serial(expected),
+                serial(actual)
+            )));
+        }
+
+        let (expected, actual) = (expected.get_stats(), actual.get_stats());
+        TestError::check_count(String::from("cycles"), expected.cycles, actual.cycles)?;
+        TestError::check_count(String::from("loads"), expected.load, actual.load)?;
+        TestError::check_count(String::from("stores"), expected.store, actual.store)
+    }
+}
+
+impl Testable for SnapshotTest {
+    fn run(&self, _: &TestContext) -> Result<(), TestError> {
+        let compiled = CompilableTest::compile(&self.code)?;
+        let mut cpu = ExecCpu::new(compiled.code);
+        cpu.set_timing(Timing::Hdl);
+        if let Some(serial) = cpu.serial_mut() {
+            serial.push_input(&self.input);
+        }
+
+        let mut reference = cpu.clone();
+        Self::finish(&mut reference)?;
+
+        cpu.run(&RunLimits::new().steps(self.steps));
+        let mut data = cpu.snapshot().write();
+
+        let mut resumed = ExecCpu::new(Vec::new());
+        let result = match &self.expected {
+            Expected::Resumed => Snapshot::read(&data).and_then(|s| resumed.restore(&s)),
+            Expected::Corrupt(offset, error) => {
+                data[*offset] ^= 0x01;
+                let actual = Snapshot::read(&data).err();
+                if actual.as_ref() != Some(error) {
+                    return Err(TestError::InvalidResult(format!(
+                        "error: {:?} != {:?}",
+                        error, actual
+                    )));
+                }
+                return Ok(());
+            }
+            Expected::Layout => {
+                resumed.map_device(0x8000..=0x80ff, Box::new(Ram::new(Vec::new(), 0x100)));
+                match Snapshot::read(&data).and_then(|s| resumed.restore(&s)) {
+                    Err(SnapshotError::Layout(_)) => {}
+                    other => {
+                        return Err(TestError::InvalidResult(format!(
+                            "restore: {:?} instead of a layout error",
+                            other
+                        )))
+                    }
+                }
+                // Machine is left as it was
+                return TestError::check_eq(String::from("PC"), 0, resumed.peek_reg(Register::PC));
+            }
+        };
+        result.map_err(|e| TestError::InvalidResult(e.to_string()))?;
+
+        Self::finish(&mut resumed)?;
+        Self::check_same(&mut reference, &mut resumed)
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use easycpu_lib::{
+    asm::{assemble_files, parse_and_compile, AsmOptions},
+    compile::CompiledProgram,
+    link::{link, Object},
+    parser::{PosCompileError, SourceLoader, SourceSet},
+};
+
+use super::CompilableTest;
+
+/// Loader serving files from memory
+#[derive(Clone)]
+struct MemLoader(HashMap<String, String>);
+
+impl SourceLoader for MemLoader {
+    fn load(&mut self, name: &str) -> Result<String, String> {
+        self.0
+            .get(name)
+            .cloned()
+            .ok_or_else(|| String::from("not found"))
+    }
+}
+
+/// Program a test assembles
+#[derive(Clone)]
+pub enum Source {
+    /// Single file named `main.s`
+    Code(String),
+    /// In-memory files assembled starting from the roots
+    Files(HashMap<String, String>, Vec<String>),
+    /// Objects assembled one by one, passed through the text format and
+    /// linked in order
+    Objects(Vec<String>),
+}
+
+/// Program which did not assemble or link
+pub struct Failure {
+    /// Errors as text, matched by tests expecting an error
+    pub message: String,
+    /// Errors of the assembler, empty when linking failed
+    pub errors: Vec<PosCompileError>,
+}
+
+impl Failure {
+    fn compile(errors: Vec<PosCompileError>) -> Failure {
+        Failure {
+            message: CompilableTest::describe(&errors),
+            errors,
+        }
+    }
+
+    fn link(message: String) -> Failure {
+        Failure {
+            message,
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl Source {
+    pub fn files(files: Vec<(&str, &str)>, roots: Vec<&str>) -> Source {
+        Source::Files(
+            files
+                .into_iter()
+                .map(|(name, text)| (name.to_owned(), text.to_owned()))
+                .collect(),
+            roots.into_iter().map(String::from).collect(),
+        )
+    }
+
+    pub fn objects(objects: Vec<&str>) -> Source {
+        Source::Objects(objects.into_iter().map(String::from).collect())
+    }
+
+    /// Assemble the program, `sources` gets the files positions refer to
+    pub fn build(
+        &self,
+        options: &AsmOptions,
+        sources: &mut SourceSet,
+    ) -> Result<CompiledProgram, Failure> {
+        match self {
+            Source::Code(code) => {
+                *sources = SourceSet::single("main.s", code.as_str());
+                parse_and_compile(code, options).map_err(Failure::compile)
+            }
+            Source::Files(files, roots) => {
+                let roots: Vec<&str> = roots.iter().map(|r| r.as_str()).collect();
+                let loader = Box::new(MemLoader(files.clone()));
+                assemble_files(&roots, loader, sources, options).map_err(|errors| {
+                    let message = errors
+                        .iter()
+                        .map(|e| format!("{}: {:?}", sources.describe(e.start_pos), e.error))
+                        .collect::<Vec<String>>()
+                        .join("\n");
+                    Failure { message, errors }
+                })
+            }
+            Source::Objects(objects) => {
+                let mut parsed = Vec::new();
+                for code in objects {
+                    let compiled = parse_and_compile(code, options).map_err(Failure::compile)?;
+                    let text = Object::from(compiled).to_string();
+                    let object = Object::parse(&text)
+                        .map_err(|e| Failure::link(format!("{}:\n{}", e, text)))?;
+                    if object.to_string() != text {
+                        return Err(Failure::link(format!(
+                            "object text: {:?} != {:?}",
+                            text,
+                            object.to_string()
+                        )));
+                    }
+                    parsed.push(object);
+                }
+
+                link(&parsed).map_err(|errs| {
+                    let errs: Vec<String> = errs.iter().map(|e| e.to_string()).collect();
+                    Failure::link(errs.join("; "))
+                })
+            }
+        }
+    }
+}
+
+impl From<&str> for Source {
+    fn from(code: &str) -> Self {
+        Source::Code(code.to_owned())
+    }
+}
+
+impl From<String> for Source {
+    fn from(code: String) -> Self {
+        Source::Code(code)
+    }
+}
@@ -1,4 +1,10 @@
-use easycpu_lib::parser::ParsePosition;
+use easycpu_lib::{
+    asm::AsmOptions,
+    compile::CompiledProgram,
+    diagnostic::{render_json, render_warnings_json},
+    executable::Executable,
+    parser::{ParsePosition, SourceSet},
+};
 use js_sys::Array;
 use wasm_bindgen::prelude::*;
 
@@ -31,19 +37,113 @@ pub struct CompileError {
 #[wasm_bindgen]
 impl CompileError {
     pub fn get_message(&self) -> String {
-        format!("{:#?}", self.err)
+        self.err.to_string()
+    }
+
+    /// Stable code like `E004`
+    pub fn get_code(&self) -> String {
+        self.err.code().to_owned()
+    }
+}
+
+fn to_js_errors(errs: Vec<easycpu_lib::parser::PosCompileError>) -> Array {
+    Array::from_iter(errs.into_iter().map(|e| {
+        JsValue::from(CompileError {
+            err: e.error,
+            start: e.start_pos.into(),
+            end: e.end_pos.into(),
+        })
+    }))
+}
+
+/// Defines and warning flags passed to the assembler
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct CompileOptions {
+    options: AsmOptions,
+}
+
+#[wasm_bindgen]
+impl CompileOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn define(&mut self, name: &str, value: i32) {
+        self.options = self.options.clone().define(name, value as i64);
+    }
+
+    /// Warning flag like `-W` of the CLI: a name, `no-` and a name or `error`
+    pub fn warning(&mut self, flag: &str) -> Result<(), String> {
+        self.options = self.options.clone().warning(flag).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+pub fn compile(source: &str, options: Option<CompileOptions>) -> Result<Vec<u16>, Array> {
+    let options = options.unwrap_or_default().options;
+    easycpu_lib::asm::parse_and_compile(source, &options)
+        .map(|program| program.code)
+        .map_err(to_js_errors)
+}
+
+/// Errors of `source` as a JSON array, the warnings when it compiles
+#[wasm_bindgen]
+pub fn diagnostics(source: &str, options: Option<CompileOptions>) -> String {
+    let options = options.unwrap_or_default().options;
+    let sources = SourceSet::single("", source);
+    match easycpu_lib::asm::parse_and_compile(source, &options) {
+        Ok(program) => render_warnings_json(&program.warnings, &sources),
+        Err(errs) => render_json(&errs, &sources),
+    }
+}
+
+#[wasm_bindgen]
+pub struct Program {
+    program: CompiledProgram,
+}
+
+#[wasm_bindgen]
+impl Program {
+    pub fn code(&self) -> Vec<u16> {
+        self.program.code.clone()
+    }
+
+    /// Warnings as `CompileError`s
+    pub fn warnings(&self) -> Array {
+        to_js_errors(self.program.warnings.clone())
+    }
+
+    /// Executable with symbols and source map, loadable by `DebugCpu::load`
+    pub fn executable(&self) -> Vec<u8> {
+        Executable::from_program(&self.program).write()
+    }
+
+    /// Source range the word at `addr` was compiled from
+    pub fn source_start(&self, addr: u16) -> Option<Position> {
+        self.program.source_map.get(addr).map(|(start, _)| start.into())
+    }
+
+    pub fn source_end(&self, addr: u16) -> Option<Position> {
+        self.program.source_map.get(addr).map(|(_, end)| end.into())
+    }
+
+    /// Full label names, matching `symbol_addresses` by index
+    pub fn symbol_names(&self) -> Vec<String> {
+        self.program.symbols.iter().map(|s| s.full_name()).collect()
+    }
+
+    pub fn symbol_addresses(&self) -> Vec<u16> {
+        self.program.symbols.iter().map(|s| s.addr).collect()
     }
 }
 
 #[wasm_bindgen]
-pub fn compile(source: &str) -> Result<Vec<u16>, Array> {
-    easycpu_lib::asm::parse_and_compile(source).map_err(|errs| {
-        Array::from_iter(errs.into_iter().map(|e| {
-            JsValue::from(CompileError {
-                err: e.error,
-                start: e.start_pos.into(),
-                end: e.end_pos.into(),
-            })
-        }))
-    })
+pub fn compile_program(source: &str, options: Option<CompileOptions>) -> Result<Program, Array> {
+    let options = options.unwrap_or_default().options;
+    easycpu_lib::asm::parse_and_compile(source, &options)
+        .map(|program| Program { program })
+        .map_err(to_js_errors)
 }
@@ -1,5 +1,10 @@
 use wasm_bindgen::prelude::*;
-use easycpu_lib::{cpu, exec::ExecCpu};
+use easycpu_lib::{
+    cpu,
+    executable::Executable,
+    exec::{ExecCpu, RunLimits, Snapshot, StopReason},
+    image::ImageFormat,
+};
 
 #[wasm_bindgen]
 pub struct RegistersState {
@@ -12,6 +17,28 @@ pub struct RegistersState {
     pub lp: u16,
 }
 
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StopKind {
+    Halted,
+    StepLimit,
+    Breakpoint,
+    Watchpoint,
+    Condition,
+}
+
+impl From<StopReason> for StopKind {
+    fn from(value: StopReason) -> Self {
+        match value {
+            StopReason::Halted => StopKind::Halted,
+            StopReason::StepLimit => StopKind::StepLimit,
+            StopReason::Breakpoint(_) => StopKind::Breakpoint,
+            StopReason::ReadWatch { .. } | StopReason::WriteWatch { .. } => StopKind::Watchpoint,
+            StopReason::Condition(_) => StopKind::Condition,
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub struct DebugCpu {
     cpu: ExecCpu
@@ -27,6 +54,14 @@ impl DebugCpu {
         } 
     }
 
+    /// Executable or raw big-endian binary, starting at the entry point
+    pub fn load(image: Vec<u8>) -> Result<DebugCpu, String> {
+        let exe = Executable::load(ImageFormat::Bin, &image).map_err(|e| e.to_string())?;
+        let mut cpu = ExecCpu::new(exe.memory());
+        cpu.set_reg(cpu::Register::PC, exe.entry);
+        Ok(Self { cpu })
+    }
+
     pub fn reset(&mut self, init_ram: Vec<u16>) {
         self.cpu = ExecCpu::new(init_ram)
     }
@@ -51,8 +86,28 @@ impl DebugCpu {
         self.cpu.exec_next();
     }
 
+    pub fn run(&mut self, max_steps: usize, breakpoints: Vec<u16>) -> StopKind {
+        let limits = breakpoints
+            .into_iter()
+            .fold(RunLimits::new().steps(max_steps), RunLimits::breakpoint);
+        self.cpu.run(&limits).into()
+    }
+
     pub fn keep_running(&mut self) -> bool {
-        self.cpu.get_mem(0xffff) != 0
+        self.cpu.is_running()
+    }
+
+    pub fn serial_write(&mut self, data: Vec<u8>) {
+        if let Some(serial) = self.cpu.serial_mut() {
+            serial.push_input(&data)
+        }
+    }
+
+    pub fn serial_read(&mut self) -> Vec<u8> {
+        self.cpu
+            .serial_mut()
+            .map(|serial| serial.take_output())
+            .unwrap_or_default()
     }
 
     pub fn read_memory(&mut self, mut from: u16, to: u16) -> Vec<u16> {
@@ -69,4 +124,14 @@ impl DebugCpu {
     pub fn write_memory(&mut self, addr: u16, val: u16) {
         self.cpu.set_mem(addr, val);
     }
+
+    /// Registers, memory, devices and statistics in the snapshot format
+    pub fn export_state(&self) -> Vec<u8> {
+        self.cpu.snapshot().write()
+    }
+
+    pub fn import_state(&mut self, state: Vec<u8>) -> Result<(), String> {
+        let snapshot = Snapshot::read(&state).map_err(|e| e.to_string())?;
+        self.cpu.restore(&snapshot).map_err(|e| e.to_string())
+    }
 }
\ No newline at end of file
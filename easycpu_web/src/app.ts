@@ -74,7 +74,7 @@ export class App extends EventTarget {
                     const line = e.start.line
                     const col = e.start.column
                     const msg = e.get_message()
-                    return `Error at ${line}:${col}: ${msg}`
+                    return `error[${e.get_code()}] at ${line}:${col}: ${msg}`
                 })
                 .join('\n\n')
         )